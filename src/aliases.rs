@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::strip_bom;
+use crate::error::{Error, Result};
+use crate::profiles::{
+    claudectx_dir, ensure_profiles_dir, profile_exists, slugify, state_write_error,
+};
+
+/// Profile slug per alias slug
+pub type Aliases = BTreeMap<String, String>;
+
+/// Get the path to the aliases file (~/.claudectx/.aliases.json)
+pub fn aliases_path() -> PathBuf {
+    claudectx_dir().join(".aliases.json")
+}
+
+/// Load profile aliases, or an empty map if none were defined yet
+pub fn load_aliases() -> Result<Aliases> {
+    let path = aliases_path();
+    if !path.exists() {
+        return Ok(Aliases::new());
+    }
+
+    let content = fs::read_to_string(&path).map_err(|e| Error::io("Failed to read aliases", e))?;
+    serde_json::from_str(strip_bom(&content))
+        .map_err(|e| Error::parse("Failed to parse aliases", e))
+}
+
+fn save_aliases(aliases: &Aliases) -> Result<()> {
+    ensure_profiles_dir()?;
+    let json = serde_json::to_string_pretty(aliases)
+        .map_err(|e| Error::parse("Failed to serialize aliases", e))?;
+    fs::write(aliases_path(), json).map_err(|e| state_write_error("Failed to write aliases", e))
+}
+
+/// Make `alias` resolve to profile `name`. An alias can't shadow an existing
+/// profile, since launch mode looks aliases up first.
+pub fn set_alias(alias: &str, name: &str) -> Result<()> {
+    let (alias, slug) = (slugify(alias), slugify(name));
+    if alias.is_empty() {
+        return Err(Error::Other(
+            "An alias needs at least one letter or digit".to_string(),
+        ));
+    }
+    if !profile_exists(name)? {
+        return Err(Error::profile_not_found(&slug));
+    }
+    if profile_exists(&alias)? {
+        return Err(Error::Other(format!(
+            "'{}' is already a profile name; pick another alias",
+            alias
+        )));
+    }
+
+    let mut aliases = load_aliases()?;
+    aliases.insert(alias, slug);
+    save_aliases(&aliases)
+}
+
+/// Remove `alias`, returning the profile it pointed to
+pub fn remove_alias(alias: &str) -> Result<String> {
+    let alias = slugify(alias);
+    let mut aliases = load_aliases()?;
+    let Some(slug) = aliases.remove(&alias) else {
+        return Err(Error::Other(format!("No alias named '{}'", alias)));
+    };
+    save_aliases(&aliases)?;
+    Ok(slug)
+}
+
+/// The profile `name` refers to: the target of the alias of that name, else
+/// `name` itself
+pub fn resolve_alias(name: &str) -> Result<String> {
+    Ok(load_aliases()?
+        .remove(&slugify(name))
+        .unwrap_or_else(|| name.to_string()))
+}
+
+/// Fail if `name` is an alias, since a profile of that name would be
+/// shadowed by it in launch mode
+pub fn ensure_not_alias(name: &str) -> Result<()> {
+    let slug = slugify(name);
+    match load_aliases()?.get(&slug) {
+        Some(target) => Err(Error::Other(format!(
+            "'{}' is an alias of '{}'; remove it with 'claudectx alias --remove {}' or pick another name",
+            slug, target, slug
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Point the aliases of profile `old` at `new` after a rename
+pub fn retarget_aliases(old: &str, new: &str) -> Result<()> {
+    let mut aliases = load_aliases()?;
+    let mut changed = false;
+    for target in aliases.values_mut().filter(|target| *target == old) {
+        *target = new.to_string();
+        changed = true;
+    }
+    if changed {
+        save_aliases(&aliases)?;
+    }
+    Ok(())
+}
+
+/// Remove the aliases of profile `slug` after it was deleted
+pub fn remove_aliases_to(slug: &str) -> Result<()> {
+    let mut aliases = load_aliases()?;
+    let before = aliases.len();
+    aliases.retain(|_, target| target != slug);
+    if aliases.len() != before {
+        save_aliases(&aliases)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::{Error, Result};
+
+/// Temporary directory removed when dropped
+pub struct StagingDir {
+    path: PathBuf,
+}
+
+impl StagingDir {
+    /// Create a fresh, empty directory under the system temp dir
+    pub fn new() -> Result<Self> {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let path = std::env::temp_dir().join(format!("claudectx-{}-{}", std::process::id(), nanos));
+        fs::create_dir_all(&path)
+            .map_err(|e| Error::io("Failed to create staging directory", e))?;
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for StagingDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Run the system `tar` with the given arguments
+fn run_tar(args: &[&std::ffi::OsStr]) -> Result<()> {
+    let status = Command::new("tar")
+        .args(args)
+        .status()
+        .map_err(|e| Error::Other(format!("Failed to run tar - is it installed?: {}", e)))?;
+
+    if !status.success() {
+        return Err(Error::Other(format!("tar exited with status: {}", status)));
+    }
+    Ok(())
+}
+
+/// Extract a .tar.gz archive into `dest`
+pub fn extract_archive(archive: &Path, dest: &Path) -> Result<()> {
+    if !archive.is_file() {
+        return Err(Error::MissingConfig(format!(
+            "Archive {:?} not found",
+            archive
+        )));
+    }
+    run_tar(&[
+        "-xzf".as_ref(),
+        archive.as_os_str(),
+        "-C".as_ref(),
+        dest.as_os_str(),
+    ])
+}
+
+/// Create a .tar.gz archive `out` holding the contents of `src_dir`
+pub fn create_archive(src_dir: &Path, out: &Path) -> Result<()> {
+    // tar resolves -f after -C on some platforms, so pin the output path
+    let out = std::env::current_dir()
+        .map(|cwd| cwd.join(out))
+        .unwrap_or_else(|_| out.to_path_buf());
+    run_tar(&[
+        "-czf".as_ref(),
+        out.as_os_str(),
+        "-C".as_ref(),
+        src_dir.as_os_str(),
+        ".".as_ref(),
+    ])
+}
@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::error::{Error, Result};
+use crate::profiles::slugify;
 
 /// OAuth account structure from ~/.claude.json
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,37 +20,246 @@ pub struct OAuthAccount {
     pub workspace_role: Option<String>,
 }
 
-/// Get the home directory, with CLAUDECTX_HOME override for testing.
-/// This is needed because dirs::home_dir() doesn't respect USERPROFILE
-/// environment variable when set for child processes on Windows.
+/// Locate the home directory: CLAUDECTX_HOME (override for testing), then
+/// the platform lookup, then $HOME / %USERPROFILE% directly. The explicit
+/// USERPROFILE check is needed because dirs::home_dir() doesn't respect it
+/// when set for child processes on Windows.
+fn find_home_dir() -> Option<PathBuf> {
+    let from_env = |name: &str| {
+        std::env::var_os(name)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+
+    from_env("CLAUDECTX_HOME")
+        .or_else(dirs::home_dir)
+        .or_else(|| from_env("HOME"))
+        .or_else(|| from_env("USERPROFILE"))
+}
+
+/// Fail early with a clean error when no home directory can be found
+/// (e.g. in containers), instead of writing state to a relative path
+pub fn ensure_home_dir() -> Result<()> {
+    find_home_dir().map(|_| ()).ok_or_else(|| {
+        Error::Other(
+            "Could not determine the home directory; set $HOME (or CLAUDECTX_HOME)".to_string(),
+        )
+    })
+}
+
+/// Get the home directory. Callers run after `ensure_home_dir`, so the
+/// empty fallback is never used in practice.
 pub fn home_dir() -> PathBuf {
-    if let Ok(home) = std::env::var("CLAUDECTX_HOME") {
-        return PathBuf::from(home);
+    find_home_dir().unwrap_or_default()
+}
+
+/// Known Claude config locations, in lookup order. The first entry is the
+/// canonical ~/.claude.json used when none of them exist yet.
+fn claude_config_candidates() -> Vec<PathBuf> {
+    #[allow(unused_mut)]
+    let mut candidates = vec![home_dir().join(".claude.json")];
+
+    #[cfg(windows)]
+    if let Ok(appdata) = std::env::var("APPDATA") {
+        candidates.push(PathBuf::from(appdata).join("claude.json"));
+    }
+
+    candidates
+}
+
+/// Claude config file override for the current invocation (--config)
+static CONFIG_PATH_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Read and patch `path` as the Claude config for the rest of this run
+pub fn set_config_path_override(path: PathBuf) {
+    let _ = CONFIG_PATH_OVERRIDE.set(path);
+}
+
+/// Every Claude config location claudectx considers, highest priority first,
+/// each labelled with what put it there (`claudectx doctor`)
+pub fn discovered_config_paths() -> Vec<(&'static str, PathBuf)> {
+    let mut found = vec![];
+    if let Some(path) = CONFIG_PATH_OVERRIDE.get() {
+        found.push(("--config", path.clone()));
+    }
+    if let Some(path) = std::env::var_os("CLAUDE_CONFIG_PATH").filter(|p| !p.is_empty()) {
+        found.push(("$CLAUDE_CONFIG_PATH", PathBuf::from(path)));
     }
-    dirs::home_dir().expect("Failed to find home directory")
+    if let Some(dir) = std::env::var_os("CLAUDE_CONFIG_DIR").filter(|d| !d.is_empty()) {
+        found.push(("$CLAUDE_CONFIG_DIR", config_dir_file(&PathBuf::from(dir))));
+    }
+    for path in claude_config_candidates() {
+        found.push(("default", path));
+    }
+    found
 }
 
-/// Get the path to ~/.claude.json
+/// Get the path to the Claude config (~/.claude.json by default).
+/// --config takes precedence, then CLAUDE_CONFIG_PATH, then Claude Code's own
+/// CLAUDE_CONFIG_DIR; otherwise the first existing known location is used,
+/// falling back to ~/.claude.json.
 pub fn claude_config_path() -> PathBuf {
-    home_dir().join(".claude.json")
+    if let Some(path) = CONFIG_PATH_OVERRIDE.get() {
+        return path.clone();
+    }
+
+    if let Ok(path) = std::env::var("CLAUDE_CONFIG_PATH") {
+        if !path.is_empty() {
+            return PathBuf::from(path);
+        }
+    }
+
+    if let Ok(dir) = std::env::var("CLAUDE_CONFIG_DIR") {
+        if !dir.is_empty() {
+            return config_dir_file(&PathBuf::from(dir));
+        }
+    }
+
+    let candidates = claude_config_candidates();
+    candidates
+        .iter()
+        .find(|path| path.exists() || path.is_symlink())
+        .unwrap_or(&candidates[0])
+        .clone()
+}
+
+/// Config file inside a CLAUDE_CONFIG_DIR: .claude.json, or config.json when
+/// only that one exists
+fn config_dir_file(dir: &std::path::Path) -> PathBuf {
+    let dotfile = dir.join(".claude.json");
+    let legacy = dir.join("config.json");
+    if !dotfile.exists() && legacy.exists() {
+        legacy
+    } else {
+        dotfile
+    }
+}
+
+/// Error returned when ~/.claude.json can't be read
+pub fn missing_config_error(path: &std::path::Path) -> Error {
+    Error::MissingConfig(format!(
+        "Failed to read Claude config at {:?} - is Claude Code installed?",
+        path
+    ))
+}
+
+/// Drop a leading UTF-8 byte order mark, which some editors write and
+/// serde_json rejects
+pub fn strip_bom(content: &str) -> &str {
+    content.strip_prefix('\u{feff}').unwrap_or(content)
 }
 
 /// Read the Claude config file as a JSON Value (preserves all fields)
-pub fn read_claude_config() -> serde_json::Value {
+pub fn read_claude_config() -> Result<serde_json::Value> {
     let path = claude_config_path();
-    let content = fs::read_to_string(&path).unwrap_or_else(|_| {
-        panic!(
-            "Failed to read Claude config at {:?} - is Claude Code installed?",
-            path
-        )
-    });
-    serde_json::from_str(&content).expect("Failed to parse Claude config JSON")
+    let content = fs::read_to_string(&path).map_err(|_| missing_config_error(&path))?;
+    serde_json::from_str(strip_bom(&content))
+        .map_err(|e| Error::parse("Failed to parse Claude config JSON", e))
 }
 
 /// Extract the oauthAccount from the config
-pub fn get_oauth_account(config: &serde_json::Value) -> OAuthAccount {
-    let account_value = config
-        .get("oauthAccount")
-        .expect("oauthAccount field is missing from claude.json");
-    serde_json::from_value(account_value.clone()).expect("Failed to parse oauthAccount")
+pub fn get_oauth_account(config: &serde_json::Value) -> Result<OAuthAccount> {
+    let account_value = config.get("oauthAccount").ok_or_else(|| {
+        Error::Parse("oauthAccount field is missing from claude.json".to_string())
+    })?;
+    serde_json::from_value(account_value.clone())
+        .map_err(|e| Error::parse("Failed to parse oauthAccount", e))
+}
+
+/// Config key holding the API key of API-key-only accounts
+pub const API_KEY_FIELD: &str = "primaryApiKey";
+
+/// Non-empty API key of a config, if it has one
+pub fn get_api_key(config: &serde_json::Value) -> Option<&str> {
+    config
+        .get(API_KEY_FIELD)?
+        .as_str()
+        .filter(|key| !key.is_empty())
+}
+
+/// Short, non-secret label for an API key: `API key (…last4)`
+pub fn api_key_label(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    let last4: String = chars[chars.len().saturating_sub(4)..].iter().collect();
+    format!("API key (…{})", last4)
+}
+
+/// Display name and organization of an account. API-key-only configs (no
+/// oauthAccount) are shown as `API key (…last4)` with no organization.
+pub fn account_names(config: &serde_json::Value) -> Result<(String, Option<String>)> {
+    if config.get("oauthAccount").is_none() {
+        if let Some(key) = get_api_key(config) {
+            return Ok((api_key_label(key), None));
+        }
+    }
+
+    let account = get_oauth_account(config)?;
+    Ok((account.display_name, Some(account.organization_name)))
+}
+
+/// Non-empty email address of a config's account, if it has one
+pub fn account_email(config: &serde_json::Value) -> Option<&str> {
+    config
+        .pointer("/oauthAccount/emailAddress")
+        .and_then(|email| email.as_str())
+        .filter(|email| !email.is_empty())
+}
+
+/// Profile name suggested by an account: the local part of its email
+/// address, or else its organization name (`save --name-from-account`)
+pub fn account_profile_name(config: &serde_json::Value) -> Option<String> {
+    let organization = config
+        .pointer("/oauthAccount/organizationName")
+        .and_then(|name| name.as_str());
+    account_email(config)
+        .and_then(|email| email.split('@').next())
+        .into_iter()
+        .chain(organization)
+        .map(slugify)
+        .find(|slug| !slug.is_empty())
+}
+
+/// One-line account description: `Display @ Organization`, or
+/// `API key (…last4)` for API-key-only configs
+pub fn describe_account(config: &serde_json::Value) -> Result<String> {
+    Ok(match account_names(config)? {
+        (display_name, Some(organization_name)) => {
+            format!("{} @ {}", display_name, organization_name)
+        }
+        (display_name, None) => display_name,
+    })
+}
+
+/// Account identity with email: `Display <email> @ Organization`, or
+/// `API key (…last4)` for API-key-only configs
+pub fn describe_identity(config: &serde_json::Value) -> Result<String> {
+    match get_oauth_account(config) {
+        Ok(account) => Ok(format!(
+            "{} <{}> @ {}",
+            account.display_name, account.email_address, account.organization_name
+        )),
+        Err(_) => describe_account(config),
+    }
+}
+
+/// Account of a config as JSON with stable snake_case keys, for `--json`
+/// output. API-key-only configs only carry the masked `api_key`.
+pub fn account_json(config: &serde_json::Value) -> Result<serde_json::Value> {
+    if config.get("oauthAccount").is_none() {
+        if let Some(key) = get_api_key(config) {
+            return Ok(serde_json::json!({ "api_key": api_key_label(key) }));
+        }
+    }
+
+    let account = get_oauth_account(config)?;
+    Ok(serde_json::json!({
+        "account_uuid": account.account_uuid,
+        "email_address": account.email_address,
+        "organization_uuid": account.organization_uuid,
+        "display_name": account.display_name,
+        "organization_role": account.organization_role,
+        "organization_name": account.organization_name,
+        "has_extra_usage_enabled": account.has_extra_usage_enabled,
+        "workspace_role": account.workspace_role,
+    }))
 }
@@ -0,0 +1,116 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::archive::StagingDir;
+use crate::config::strip_bom;
+use crate::error::{Error, Result};
+use crate::launcher::find_executable;
+
+/// Editors tried, in order, when neither $VISUAL nor $EDITOR is set
+#[cfg(windows)]
+const FALLBACK_EDITORS: &[&str] = &["notepad"];
+#[cfg(not(windows))]
+const FALLBACK_EDITORS: &[&str] = &["vi", "nano"];
+
+/// Resolve the editor command line: $VISUAL, then $EDITOR, then the first
+/// platform default found on PATH. Values may include arguments ("code --wait").
+pub fn resolve_editor() -> Option<Vec<String>> {
+    for var in ["VISUAL", "EDITOR"] {
+        if let Ok(value) = std::env::var(var) {
+            let parts: Vec<String> = value.split_whitespace().map(String::from).collect();
+            if !parts.is_empty() {
+                return Some(parts);
+            }
+        }
+    }
+
+    FALLBACK_EDITORS
+        .iter()
+        .find(|editor| find_executable(Path::new(editor)).is_some())
+        .map(|editor| vec![editor.to_string()])
+}
+
+/// Program that opens files and directories with their default application
+#[cfg(target_os = "macos")]
+const OPEN_HANDLER: &str = "open";
+#[cfg(windows)]
+const OPEN_HANDLER: &str = "explorer";
+#[cfg(not(any(target_os = "macos", windows)))]
+const OPEN_HANDLER: &str = "xdg-open";
+
+/// Open `path` in the OS file manager or default application
+pub fn open_with_default_handler(path: &Path) -> Result<()> {
+    let Some(handler) = find_executable(Path::new(OPEN_HANDLER)) else {
+        return Err(Error::Other(format!(
+            "No file opener found ('{}' is not on PATH) - use --print-path to print the path instead",
+            OPEN_HANDLER
+        )));
+    };
+
+    let status = Command::new(&handler)
+        .arg(path)
+        .status()
+        .map_err(|e| Error::Other(format!("Failed to launch '{}': {}", handler.display(), e)))?;
+
+    // explorer exits with 1 even when it opened the path
+    if !status.success() && !cfg!(windows) {
+        return Err(Error::Other(format!(
+            "'{}' exited with status: {}",
+            OPEN_HANDLER, status
+        )));
+    }
+    Ok(())
+}
+
+/// Open `path` in the user's editor and wait for it to exit
+fn run_editor(path: &Path) -> Result<()> {
+    let Some(editor) = resolve_editor() else {
+        return Err(Error::Other(
+            "No editor found - set $EDITOR (or $VISUAL) to your preferred editor".to_string(),
+        ));
+    };
+
+    let status = Command::new(&editor[0])
+        .args(&editor[1..])
+        .arg(path)
+        .status()
+        .map_err(|e| {
+            Error::Other(format!(
+                "Failed to launch editor '{}': {} - set $EDITOR to a working editor",
+                editor[0], e
+            ))
+        })?;
+
+    if !status.success() {
+        return Err(Error::Other(format!(
+            "Editor '{}' exited with status: {}",
+            editor[0], status
+        )));
+    }
+    Ok(())
+}
+
+/// Edit a JSON file through a temporary copy. The file is only replaced if
+/// the edited content is still a valid JSON object.
+pub fn edit_json_file(path: &Path) -> Result<()> {
+    let staging = StagingDir::new()?;
+    let draft: PathBuf = staging
+        .path()
+        .join(path.file_name().unwrap_or_else(|| "profile.json".as_ref()));
+    fs::copy(path, &draft).map_err(|e| Error::io("Failed to prepare edit", e))?;
+
+    run_editor(&draft)?;
+
+    let content = fs::read_to_string(&draft).map_err(|e| Error::io("Failed to read edit", e))?;
+    let content = strip_bom(&content);
+    let value: serde_json::Value = serde_json::from_str(content)
+        .map_err(|e| Error::parse("Edited profile is not valid JSON (left unchanged)", e))?;
+    if !value.is_object() {
+        return Err(Error::Parse(
+            "Edited profile is not a JSON object (left unchanged)".to_string(),
+        ));
+    }
+
+    fs::write(path, content).map_err(|e| Error::io("Failed to save edited profile", e))
+}
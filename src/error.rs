@@ -0,0 +1,90 @@
+use std::fmt;
+
+/// Errors surfaced to the user. Each variant maps to a stable exit code
+/// (see `Error::code`) so wrappers can branch on the failure category.
+#[derive(Debug)]
+pub enum Error {
+    /// ~/.claude.json (or another required file) is missing or unreadable
+    MissingConfig(String),
+    /// A config, profile or settings file is not valid JSON or lacks a required field
+    Parse(String),
+    /// The requested profile doesn't exist
+    ProfileNotFound(String),
+    /// Filesystem failure while writing, renaming or deleting
+    Io(String),
+    /// The profiles directory (or ~/.claudectx) can't be written to, e.g. a
+    /// read-only mount
+    NotWritable(String),
+    /// Interactive prompt failed (e.g. no TTY)
+    Prompt(String),
+    /// claude could not be launched
+    Launch(String),
+    /// Anything else (cancelled operations, invalid input, ...)
+    Other(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Stable numeric code for this error category, used as the process exit code
+    pub fn code(&self) -> i32 {
+        match self {
+            Error::Other(_) => 1,
+            Error::MissingConfig(_) | Error::NotWritable(_) => 2,
+            Error::Parse(_) => 3,
+            Error::ProfileNotFound(_) => 4,
+            Error::Io(_) => 5,
+            Error::Prompt(_) => 6,
+            Error::Launch(_) => 7,
+        }
+    }
+
+    /// Build an `Error::Io` from a context message and the underlying error
+    pub fn io(context: &str, err: impl fmt::Display) -> Self {
+        Error::Io(format!("{}: {}", context, err))
+    }
+
+    /// Build an `Error::Parse` from a context message and the underlying error
+    pub fn parse(context: &str, err: impl fmt::Display) -> Self {
+        Error::Parse(format!("{}: {}", context, err))
+    }
+
+    /// Build an `Error::Prompt` from the underlying dialoguer error
+    pub fn prompt(err: impl fmt::Display) -> Self {
+        Error::Prompt(format!("Failed to prompt: {}", err))
+    }
+
+    /// `Error::ProfileNotFound` for the given (already slugified) profile name
+    pub fn profile_not_found(slug: &str) -> Self {
+        Error::ProfileNotFound(format!("Profile '{}' not found", slug))
+    }
+
+    /// Print the error to stderr, either as text or as `{"error":"...","code":N}`
+    pub fn report(&self, as_json: bool) {
+        if as_json {
+            eprintln!(
+                "{}",
+                serde_json::json!({ "error": self.to_string(), "code": self.code() })
+            );
+        } else {
+            eprintln!("Error: {}", self);
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MissingConfig(msg)
+            | Error::Parse(msg)
+            | Error::ProfileNotFound(msg)
+            | Error::Io(msg)
+            | Error::NotWritable(msg)
+            | Error::Prompt(msg)
+            | Error::Launch(msg)
+            | Error::Other(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
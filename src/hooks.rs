@@ -0,0 +1,36 @@
+use std::process::Command;
+
+use crate::error::{Error, Result};
+
+/// Shell used to run hook command lines
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").arg(command);
+    cmd
+}
+
+/// Shell used to run hook command lines
+#[cfg(not(windows))]
+fn shell_command(command: &str) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(command);
+    cmd
+}
+
+/// Run a user hook through the shell with `vars` added to its environment
+/// (e.g. CLAUDECTX_PROFILE). A failing hook only prints a warning unless
+/// `strict` is set.
+pub fn run_hook(name: &str, command: &str, vars: &[(&str, &str)], strict: bool) -> Result<()> {
+    let failure = match shell_command(command).envs(vars.iter().copied()).status() {
+        Ok(status) if status.success() => return Ok(()),
+        Ok(status) => format!("{} hook '{}' exited with status: {}", name, command, status),
+        Err(e) => format!("Failed to run {} hook '{}': {}", name, command, e),
+    };
+
+    if strict {
+        return Err(Error::Other(failure));
+    }
+    eprintln!("Warning: {}", failure);
+    Ok(())
+}
@@ -1,30 +1,351 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use crate::profiles::switch_to_profile;
+use crate::config::{claude_config_path, read_claude_config, strip_bom};
+use crate::error::{Error, Result};
+use crate::hooks::run_hook;
+use crate::profiles::{
+    ensure_canonical_profile, get_current_profile, slugify, switch_to_config, switch_to_profile,
+    SwitchOptions, SwitchSummary,
+};
+use crate::settings::load_settings;
+use crate::ui::print_account_banner;
+
+/// Options controlling how a profile is applied before launching claude
+#[derive(Debug, Clone, Default)]
+pub struct LaunchOptions {
+    /// How ~/.claude.json is patched
+    pub switch: SwitchOptions,
+    /// Don't print the post-switch summary
+    pub quiet: bool,
+    /// Explicit claude executable (--claude-bin)
+    pub claude_bin: Option<PathBuf>,
+    /// Refuse profiles holding keys outside the account-specific fields
+    pub strict: bool,
+    /// Command run before switching (--before), overriding the `before_switch` setting
+    pub before_hook: Option<String>,
+    /// Command run after switching (--after), overriding the `after_switch` setting
+    pub after_hook: Option<String>,
+    /// Abort the launch when a hook fails instead of warning
+    pub strict_hooks: bool,
+    /// Minimum claude version checked before switching
+    pub version_check: Option<VersionCheck>,
+    /// Print the active account as a banner right before launching claude
+    pub banner: bool,
+}
+
+/// A dotted claude version such as `1.0.30`. Trailing zero components are
+/// dropped so that `1.2` and `1.2.0` compare equal.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ClaudeVersion(Vec<u64>);
+
+impl std::str::FromStr for ClaudeVersion {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, String> {
+        let mut parts = value
+            .trim()
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| part.parse::<u64>())
+            .collect::<std::result::Result<Vec<u64>, _>>()
+            .map_err(|_| format!("invalid version '{}': expected e.g. 1.0.30", value))?;
+        while parts.len() > 1 && parts.last() == Some(&0) {
+            parts.pop();
+        }
+        Ok(ClaudeVersion(parts))
+    }
+}
+
+impl std::fmt::Display for ClaudeVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts: Vec<String> = self.0.iter().map(u64::to_string).collect();
+        write!(f, "{}", parts.join("."))
+    }
+}
+
+/// Minimum claude version (--check-claude-version / --require-claude-version)
+#[derive(Debug, Clone)]
+pub struct VersionCheck {
+    pub minimum: ClaudeVersion,
+    /// Fail instead of warning when claude is older (or its version unknown)
+    pub required: bool,
+}
+
+/// Version reported by `<program> --version` (e.g. "1.0.30 (Claude Code)"):
+/// the first whitespace-separated word that parses as a version
+fn installed_claude_version(program: &Path) -> Option<ClaudeVersion> {
+    let output = Command::new(program).arg("--version").output().ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .find_map(|word| word.parse().ok())
+}
+
+/// Compare the installed claude with the minimum version before anything is
+/// switched: warn when it's older (or its version can't be read), or fail if
+/// the version is required
+fn check_claude_version(check: &VersionCheck, options: &LaunchOptions) -> Result<()> {
+    let program = claude_program(options.claude_bin.as_deref());
+    let problem = match installed_claude_version(&program) {
+        Some(found) if found >= check.minimum => return Ok(()),
+        Some(found) => format!(
+            "claude {} is older than the required {}",
+            found, check.minimum
+        ),
+        None => format!(
+            "could not determine the claude version from '{} --version'",
+            program.display()
+        ),
+    };
+
+    if check.required {
+        Err(Error::Launch(problem))
+    } else {
+        eprintln!("Warning: {}", problem);
+        Ok(())
+    }
+}
+
+/// Candidate file names for `name` on this platform (adds PATHEXT extensions on Windows)
+fn executable_names(name: &Path) -> Vec<PathBuf> {
+    #[allow(unused_mut)]
+    let mut names = vec![name.to_path_buf()];
+
+    #[cfg(windows)]
+    if name.extension().is_none() {
+        let pathext = std::env::var("PATHEXT").unwrap_or_else(|_| ".EXE;.CMD;.BAT".to_string());
+        for ext in pathext.split(';').filter(|ext| !ext.is_empty()) {
+            let mut candidate = name.as_os_str().to_owned();
+            candidate.push(ext.to_ascii_lowercase());
+            names.push(PathBuf::from(candidate));
+        }
+    }
+
+    names
+}
+
+/// Check that `path` is a file we can execute
+fn is_executable(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        path.metadata()
+            .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    }
+
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
+/// The claude executable asked for: `--claude-bin`, then `$CLAUDE_BIN`, then `claude`
+fn requested_claude_bin(explicit: Option<&Path>) -> PathBuf {
+    explicit
+        .map(Path::to_path_buf)
+        .or_else(|| std::env::var_os("CLAUDE_BIN").map(PathBuf::from))
+        .filter(|path| !path.as_os_str().is_empty())
+        .unwrap_or_else(|| PathBuf::from("claude"))
+}
+
+/// Resolve the claude executable to an absolute path
+pub fn resolve_claude_bin(explicit: Option<&Path>) -> Option<PathBuf> {
+    find_executable(&requested_claude_bin(explicit))
+}
+
+/// Resolve an executable to an absolute path. Bare names are searched on
+/// PATH; names containing a path separator are used as-is.
+pub fn find_executable(requested: &Path) -> Option<PathBuf> {
+    if requested.components().count() > 1 || requested.is_absolute() {
+        return executable_names(requested)
+            .into_iter()
+            .find(|candidate| is_executable(candidate))
+            .and_then(|candidate| candidate.canonicalize().ok());
+    }
+
+    let path_var = std::env::var_os("PATH")?;
+    std::env::split_paths(&path_var)
+        .flat_map(|dir| {
+            executable_names(requested)
+                .into_iter()
+                .map(move |name| dir.join(name))
+        })
+        .find(|candidate| is_executable(candidate))
+}
+
+/// Program to spawn for claude: the resolved binary, or the requested name so
+/// the OS reports the launch failure
+pub fn claude_program(explicit: Option<&Path>) -> PathBuf {
+    resolve_claude_bin(explicit).unwrap_or_else(|| requested_claude_bin(explicit))
+}
 
 /// Switch to profile (patch config in-place) and launch claude.
 /// On Unix, this replaces the current process with claude.
 /// On Windows, this spawns claude and waits for it to exit.
-pub fn switch_and_launch_claude(profile_name: &str, extra_args: &[String]) -> ! {
+/// Only returns if switching or launching failed.
+pub fn switch_and_launch_claude(
+    profile_name: &str,
+    extra_args: &[String],
+    options: &LaunchOptions,
+) -> Result<()> {
+    if options.strict {
+        ensure_canonical_profile(profile_name)?;
+    }
+    if let Some(check) = &options.version_check {
+        check_claude_version(check, options)?;
+    }
+    run_before_hook(&slugify(profile_name), options)?;
+
     // First, patch ~/.claude.json with the profile's account fields
-    switch_to_profile(profile_name);
+    let summary = switch_to_profile(profile_name, &options.switch)?;
+    if !options.quiet {
+        println!("{}", summary);
+    }
+    if let Some(warning) = &summary.identity_warning {
+        eprintln!("Warning: {}", warning);
+    }
+    run_after_hook(&summary.slug, options)?;
 
-    // Then launch claude (it will read from the patched ~/.claude.json)
+    launch_claude(&summary, extra_args, options)
+}
+
+/// Like `switch_and_launch_claude`, but with a one-off config read from stdin
+/// instead of a saved profile. Nothing is written to ~/.claudectx.
+pub fn switch_from_stdin_and_launch_claude(
+    extra_args: &[String],
+    options: &LaunchOptions,
+) -> Result<()> {
+    let mut content = String::new();
+    std::io::stdin()
+        .read_to_string(&mut content)
+        .map_err(|e| Error::io("Failed to read config from stdin", e))?;
+    let config: serde_json::Value = serde_json::from_str(strip_bom(&content))
+        .map_err(|e| Error::parse("Failed to parse config from stdin", e))?;
+
+    if let Some(check) = &options.version_check {
+        check_claude_version(check, options)?;
+    }
+    run_before_hook("stdin", options)?;
+    let summary = switch_to_config("stdin", &config, &options.switch)?;
+    if !options.quiet {
+        println!("{}", summary);
+    }
+    run_after_hook(&summary.slug, options)?;
+
+    launch_claude(&summary, extra_args, options)
+}
+
+/// Run the before-switch hook (--before, else the `before_switch` setting), if
+/// any. With --strict-hooks a failing hook vetoes the switch.
+fn run_before_hook(profile: &str, options: &LaunchOptions) -> Result<()> {
+    let command = match &options.before_hook {
+        Some(command) => Some(command.clone()),
+        None => load_settings()?.before_switch,
+    };
+    let Some(command) = command else {
+        return Ok(());
+    };
+
+    let previous = get_current_profile().unwrap_or_default();
+    let vars = [
+        ("CLAUDECTX_PROFILE", profile),
+        ("CLAUDECTX_PREV_PROFILE", previous.as_str()),
+    ];
+    run_hook("before-switch", &command, &vars, options.strict_hooks)
+}
+
+/// Run the after-switch hook (--after, else the `after_switch` setting), if any
+fn run_after_hook(profile: &str, options: &LaunchOptions) -> Result<()> {
+    let command = match &options.after_hook {
+        Some(command) => Some(command.clone()),
+        None => load_settings()?.after_switch,
+    };
+    let Some(command) = command else {
+        return Ok(());
+    };
+
+    let vars = [("CLAUDECTX_PROFILE", profile)];
+    run_hook("after-switch", &command, &vars, options.strict_hooks)
+}
+
+/// Whether a failed launch is worth retrying once (e.g. the binary was
+/// being replaced by an update at that moment)
+fn is_transient_launch_error(err: &std::io::Error) -> bool {
+    matches!(
+        err.kind(),
+        std::io::ErrorKind::Interrupted
+            | std::io::ErrorKind::WouldBlock
+            | std::io::ErrorKind::ResourceBusy
+            | std::io::ErrorKind::ExecutableFileBusy
+    )
+}
+
+/// Turn a failed launch of `program` into an actionable error. The switch to
+/// `profile` has already been applied by then, so say so.
+fn launch_error(err: std::io::Error, program: &Path, profile: &str) -> Error {
+    let message = if err.kind() == std::io::ErrorKind::NotFound {
+        format!(
+            "Failed to launch claude: {:?} not found. Install Claude Code or point \
+             --claude-bin / $CLAUDE_BIN at it",
+            program
+        )
+    } else {
+        format!("Failed to launch claude: {}", err)
+    };
+    Error::Launch(format!(
+        "{}\n{} is already switched to '{}'",
+        message,
+        claude_config_path().display(),
+        profile
+    ))
+}
+
+/// Launch claude (it reads from the patched ~/.claude.json), retrying once
+/// after a transient failure.
+/// On Unix, this replaces the current process with claude.
+/// On Windows, this spawns claude and waits for it to exit.
+fn launch_claude(
+    summary: &SwitchSummary,
+    extra_args: &[String],
+    options: &LaunchOptions,
+) -> Result<()> {
+    if options.banner {
+        print_account_banner(&read_claude_config()?)?;
+    }
+
+    let program = claude_program(options.claude_bin.as_deref());
+    let mut command = Command::new(&program);
+    command.args(extra_args).envs(summary.env.iter().cloned());
+
+    let mut retried = false;
+    loop {
+        let err = match spawn_claude(&mut command) {
+            Ok(code) => std::process::exit(code),
+            Err(err) => err,
+        };
+        if retried || !is_transient_launch_error(&err) {
+            return Err(launch_error(err, &program, &summary.slug));
+        }
+        eprintln!("Warning: failed to launch claude ({}), retrying", err);
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        retried = true;
+    }
+}
+
+/// Run claude. On Unix this only returns if exec failed; on Windows it
+/// returns claude's exit code.
+fn spawn_claude(command: &mut Command) -> std::io::Result<i32> {
     #[cfg(unix)]
     {
         use std::os::unix::process::CommandExt;
-        let err = Command::new("claude").args(extra_args).exec();
-
-        panic!("Failed to launch claude: {}", err);
+        Err(command.exec())
     }
 
     #[cfg(windows)]
     {
-        let status = Command::new("claude")
-            .args(extra_args)
-            .status()
-            .expect("Failed to launch claude");
-
-        std::process::exit(status.code().unwrap_or(1));
+        let status = command.status()?;
+        Ok(status.code().unwrap_or(1))
     }
 }
@@ -1,128 +1,199 @@
+use std::path::PathBuf;
 use std::process::Command;
 
-use dialoguer::{Confirm, Input};
-
-use crate::config::get_oauth_account;
-use crate::launcher::switch_and_launch_claude;
+use crate::config::{get_oauth_account, read_claude_config};
+use crate::error::{Error, Result};
+use crate::launcher::{claude_program, switch_and_launch_claude, LaunchOptions};
 use crate::profiles::{
-    backup_claude_config, claude_config_exists, list_profiles, profile_exists,
-    restore_claude_config, save_profile, slugify,
+    backup_claude_config, claude_config_exists, clone_portable_settings, list_profiles,
+    profile_exists, restore_claude_config, save_profile, slugify, SaveOptions,
 };
-use crate::ui::select_profile;
+use crate::ui::{confirm, input_text, require_input, select_profile};
+
+/// Options for the login workflow
+#[derive(Debug, Clone, Default)]
+pub struct LoginOptions {
+    /// Explicit claude executable (--claude-bin)
+    pub claude_bin: Option<PathBuf>,
+    /// Don't back up and restore ~/.claude.json around the login
+    pub no_backup: bool,
+    /// Profile name to save the new account as (prompted for when None)
+    pub name: Option<String>,
+    /// Answer yes to every confirmation (--yes)
+    pub assume_yes: bool,
+    /// Start the new account with the current config's portable settings
+    pub clone_from_current: bool,
+    /// Back up and restore the config without running `claude /login`
+    pub dry_run: bool,
+}
+
+/// Put the original config back after login. `backup` is None when the
+/// workflow runs with --no-backup, in which case the config is left alone,
+/// and Some(None) when there was no config to back up.
+fn restore_after_login(backup: &Option<Option<PathBuf>>) -> Result<()> {
+    let Some(backup_path) = backup else {
+        return Ok(());
+    };
+
+    restore_claude_config(backup_path.as_deref())?;
+    if backup_path.is_some() {
+        println!("Restored original config.");
+    }
+    Ok(())
+}
 
 /// Run the login workflow:
-/// 1. Backup existing ~/.claude.json (if any)
+/// 1. Backup existing ~/.claude.json (if any, unless --no-backup), seeding
+///    the fresh config with its portable settings with --clone-from-current
 /// 2. Run `claude /login`
 /// 3. Prompt for profile name
 /// 4. Save new config as profile
 /// 5. Restore original config (or clean up if none existed)
 /// 6. Offer to launch with new profile or select another
-pub fn run_login_workflow() {
+///
+/// With `dry_run`, only step 1 and the restore run, to check that the config
+/// survives the round trip.
+pub fn run_login_workflow(options: &LoginOptions) -> Result<()> {
+    // The profile name is prompted for after login; fail before touching anything
+    if options.name.is_none() && !options.dry_run {
+        require_input("login", "--name")?;
+    }
+
     println!("Starting Claude login workflow...\n");
 
     // Step 1: Backup existing config
-    let had_backup = backup_claude_config();
-    if had_backup {
-        println!("Backed up existing config to ~/.claude.json.bak");
+    let backup = if options.no_backup {
+        None
+    } else {
+        Some(backup_claude_config()?)
+    };
+    if let Some(Some(backup_path)) = &backup {
+        println!("Backed up existing config to {}", backup_path.display());
+        if options.clone_from_current {
+            clone_portable_settings(backup_path)?;
+            println!("Carried over current settings for the new account.");
+        }
+    }
+
+    let program = claude_program(options.claude_bin.as_deref());
+    if options.dry_run {
+        println!("Dry run: would run '{} /login'", program.display());
+        match &options.name {
+            Some(name) => println!("Dry run: would save the new account as '{}'", slugify(name)),
+            None => println!("Dry run: would prompt for a profile name"),
+        }
+        restore_after_login(&backup)?;
+        println!("Dry run complete; nothing was changed.");
+        return Ok(());
     }
 
     // Step 2: Run claude /login
     println!("Launching Claude login...\n");
-    let status = Command::new("claude")
-        .arg("/login")
-        .status()
-        .expect("Failed to launch 'claude /login' - is Claude Code installed?");
+    let status = Command::new(program).arg("/login").status().map_err(|e| {
+        Error::Launch(format!(
+            "Failed to launch 'claude /login' - is Claude Code installed?: {}",
+            e
+        ))
+    })?;
 
     if !status.success() {
         eprintln!("\nClaude login failed or was cancelled.");
-        restore_claude_config(had_backup);
-        if had_backup {
-            println!("Restored original config.");
-        }
-        panic!("Login process exited with status: {}", status);
+        restore_after_login(&backup)?;
+        return Err(Error::Launch(format!(
+            "Login process exited with status: {}",
+            status
+        )));
     }
 
     // Check that login created a new config
     if !claude_config_exists() {
         eprintln!("\nNo config file created after login.");
-        restore_claude_config(had_backup);
-        if had_backup {
-            println!("Restored original config.");
-        }
-        panic!("Login did not create a config file");
+        restore_after_login(&backup)?;
+        return Err(Error::MissingConfig(
+            "Login did not create a config file".to_string(),
+        ));
+    }
+
+    // claude may have rewritten the config; put back settings it dropped
+    if let (true, Some(Some(backup_path))) = (options.clone_from_current, &backup) {
+        clone_portable_settings(backup_path)?;
     }
 
     // Show the new account info
-    let new_config = crate::config::read_claude_config();
-    let new_account = get_oauth_account(&new_config);
+    let new_config = read_claude_config()?;
+    let new_account = get_oauth_account(&new_config)?;
     println!(
         "\nLogged in as: {} @ {}",
         new_account.display_name, new_account.organization_name
     );
 
     // Step 3: Prompt for profile name
-    let profile_name: String = Input::new()
-        .with_prompt("Enter a name for this profile")
-        .interact_text()
-        .expect("Failed to read profile name");
+    let profile_name = match &options.name {
+        Some(name) => name.clone(),
+        None => input_text("login", "Enter a name for this profile", "--name")?,
+    };
 
     let slug = slugify(&profile_name);
 
     // Check if profile exists and ask for confirmation
-    if profile_exists(&profile_name) {
-        let overwrite = Confirm::new()
-            .with_prompt(format!("Profile '{}' already exists. Overwrite?", slug))
-            .interact()
-            .expect("Failed to prompt");
+    if profile_exists(&profile_name)? {
+        let overwrite = confirm(
+            "login",
+            &format!("Profile '{}' already exists. Overwrite?", slug),
+            false,
+            options.assume_yes,
+        )?;
 
         if !overwrite {
             println!("Cancelled. Cleaning up...");
-            restore_claude_config(had_backup);
-            if had_backup {
-                println!("Restored original config.");
-            }
-            return;
+            restore_after_login(&backup)?;
+            return Ok(());
         }
     }
 
     // Step 4: Save new config as profile
-    save_profile(&profile_name);
+    save_profile(&profile_name, &SaveOptions::default())?;
     println!("Saved profile '{}'", slug);
 
     // Step 5: Restore original config
-    restore_claude_config(had_backup);
-    if had_backup {
-        println!("Restored original config.");
-    } else {
+    restore_after_login(&backup)?;
+    if matches!(backup, Some(None)) {
         println!("Cleaned up temporary config.");
     }
 
     // Step 6: Offer to launch
-    let launch_new = Confirm::new()
-        .with_prompt(format!("Launch Claude with profile '{}'?", slug))
-        .default(true)
-        .interact()
-        .expect("Failed to prompt");
+    let launch_options = LaunchOptions {
+        claude_bin: options.claude_bin.clone(),
+        ..LaunchOptions::default()
+    };
+    let launch_new = confirm(
+        "login",
+        &format!("Launch Claude with profile '{}'?", slug),
+        true,
+        options.assume_yes,
+    )?;
 
     if launch_new {
-        switch_and_launch_claude(&profile_name, &[]);
+        return switch_and_launch_claude(&profile_name, &[], &launch_options);
     }
 
     // If not launching the new profile, offer to select another
-    let profiles = list_profiles();
+    let profiles = list_profiles()?;
     if !profiles.is_empty() {
-        let select_other = Confirm::new()
-            .with_prompt("Select a different profile to launch?")
-            .default(false)
-            .interact()
-            .expect("Failed to prompt");
+        let select_other = confirm(
+            "login",
+            "Select a different profile to launch?",
+            false,
+            options.assume_yes,
+        )?;
 
         if select_other {
-            if let Some(selected) = select_profile(&profiles, Some(&slug)) {
-                switch_and_launch_claude(&selected, &[]);
+            if let Some(selected) = select_profile(&profiles, Some(&slug), false, false)? {
+                return switch_and_launch_claude(&selected, &[], &launch_options);
             }
         }
     }
 
     println!("\nDone. Use 'claudectx' to launch with any profile.");
+    Ok(())
 }
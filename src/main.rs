@@ -1,31 +1,198 @@
+mod aliases;
+mod archive;
 mod config;
+mod editor;
+mod error;
+mod hooks;
 mod launcher;
 mod login;
 mod profiles;
+mod settings;
 mod ui;
+mod usage;
+#[cfg(feature = "watch")]
+mod watch;
+
+use std::fs;
 
 use clap::{Parser, Subcommand};
-use dialoguer::Confirm;
 
-use config::{get_oauth_account, read_claude_config};
-use launcher::switch_and_launch_claude;
-use login::run_login_workflow;
+use aliases::{load_aliases, remove_alias, resolve_alias, set_alias};
+use config::{
+    account_email, account_json, account_names, account_profile_name, claude_config_path,
+    describe_account, describe_identity, discovered_config_paths, ensure_home_dir,
+    read_claude_config, set_config_path_override,
+};
+use editor::{edit_json_file, open_with_default_handler};
+use error::{Error, Result};
+use launcher::{
+    resolve_claude_bin, switch_and_launch_claude, switch_from_stdin_and_launch_claude,
+    ClaudeVersion, LaunchOptions, VersionCheck,
+};
+use login::{run_login_workflow, LoginOptions};
 use profiles::{
-    delete_profile, get_current_profile, get_profile_path, list_profiles, migrate_if_needed,
-    profile_exists, save_profile, slugify,
+    account_fields, backup_problem, backups_by_age, backups_dir, copy_profile, delete_profile,
+    ensure_profiles_dir, export_all, get_current_profile, get_default_profile, get_profile_path,
+    glob_match, has_account_identity, import_all, import_file_name, import_full_export,
+    import_profile, invalid_profiles, is_full_profile, is_glob_pattern, list_backups,
+    list_profiles, materialize_config, migrate, migrate_if_needed, missing_account_keys,
+    plan_migration, plan_repairs, portable_settings, profile_base_url, profile_env, profile_exists,
+    profiles_dir, read_profile, read_profile_summary, redact_secrets, refresh_profile,
+    rename_profile, resulting_config, save_profile, set_backup_dir_override, set_default_profile,
+    set_profiles_dir_override, set_sort_keys, slugify, trash_dir, trash_profile, undelete_profile,
+    unexpected_keys, SaveOptions, SwitchMode, SwitchOptions,
+};
+use settings::{load_settings, save_settings, ProfileSort, SETTING_KEYS};
+use ui::{
+    confirm, format_size, is_interactive, print_profile_table, select_profile, set_plain_mode,
+    ProfileRow,
+};
+use usage::{
+    format_rfc3339, format_usage_time, load_history, load_usage, now_secs, parse_window,
+    previous_profile, record_usage, sort_profiles, used_within, TimeFormat,
 };
-use ui::select_profile;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Launch Claude Code with different profiles", long_about = None)]
 struct Args {
-    /// Profile name to use (interactive selection if omitted)
+    /// Profile name to use (interactive selection if omitted). Subcommand
+    /// names take precedence; use --profile for a profile named like one.
     profile: Option<String>,
 
+    /// Profile to launch, even one named like a subcommand (e.g. `--profile list`)
+    #[arg(long = "profile", value_name = "NAME", conflicts_with = "profile")]
+    profile_flag: Option<String>,
+
+    /// Extra argument passed to claude (repeatable, placed before any `--` arguments)
+    #[arg(long = "claude-arg", value_name = "ARG", allow_hyphen_values = true)]
+    claude_arg: Vec<String>,
+
     /// Extra arguments passed to claude (after --)
     #[arg(last = true, num_args = 0..)]
     claude_args: Vec<String>,
 
+    /// Keep account fields that are absent from the profile instead of removing them
+    #[arg(long)]
+    merge: bool,
+
+    /// Replace ~/.claude.json with only the profile's account fields (after a
+    /// backup), dropping every other setting
+    #[arg(long, conflicts_with = "merge")]
+    no_portable: bool,
+
+    /// Refuse to switch to a profile with keys outside the account-specific fields
+    #[arg(long)]
+    strict: bool,
+
+    /// Launch the default profile instead of prompting (implied without a TTY)
+    #[arg(long)]
+    use_default: bool,
+
+    /// Save the current config as the profile first if it doesn't exist (no prompt)
+    #[arg(long)]
+    create: bool,
+
+    /// Switch using a JSON config read from stdin instead of a saved profile (nothing is saved)
+    #[arg(long, conflicts_with_all = ["profile", "profile_flag", "use_default", "strict", "verify"])]
+    from_stdin: bool,
+
+    /// Print the ~/.claude.json the switch would write (pretty JSON) and exit
+    /// without writing anything or launching claude
+    #[arg(long, conflicts_with = "from_stdin")]
+    print_resulting_config: bool,
+
+    /// Show each profile's organization role and last use in the interactive picker
+    #[arg(long, alias = "profile-info")]
+    details: bool,
+
+    /// Use the fuzzy finder in the interactive picker (defaults to the `picker` setting)
+    #[arg(long)]
+    fuzzy: bool,
+
+    /// Warn if the profile's email differs from the one last seen for its account UUID
+    #[arg(long)]
+    verify: bool,
+
+    /// Don't print the summary line after switching
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Print the active account as a banner right before launching claude
+    /// (defaults to the `banner` setting)
+    #[arg(long, overrides_with = "no_banner")]
+    banner: bool,
+
+    /// Don't print the account banner, even if the `banner` setting is on
+    #[arg(long, overrides_with = "banner")]
+    no_banner: bool,
+
+    /// Shell command run before switching (overrides the `before_switch`
+    /// setting; $CLAUDECTX_PROFILE is the target, $CLAUDECTX_PREV_PROFILE the current one)
+    #[arg(long, value_name = "CMD")]
+    before: Option<String>,
+
+    /// Shell command run after switching, before claude starts (overrides the
+    /// `after_switch` setting; the profile name is in $CLAUDECTX_PROFILE)
+    #[arg(long, value_name = "CMD")]
+    after: Option<String>,
+
+    /// Abort the switch or launch when a hook fails instead of only warning
+    #[arg(long)]
+    strict_hooks: bool,
+
+    /// Warn before switching if `claude --version` is older than this
+    #[arg(long, value_name = "VERSION")]
+    check_claude_version: Option<ClaudeVersion>,
+
+    /// Refuse to switch if `claude --version` is older than this (or unknown)
+    #[arg(long, value_name = "VERSION", conflicts_with = "check_claude_version")]
+    require_claude_version: Option<ClaudeVersion>,
+
+    /// claude executable to launch (defaults to $CLAUDE_BIN, then `claude` on PATH)
+    #[arg(long, global = true, value_name = "PATH")]
+    claude_bin: Option<std::path::PathBuf>,
+
+    /// Treat a symlinked ~/.claude.json as intentional: skip the migration and write through it
+    #[arg(long, global = true)]
+    follow_symlinks: bool,
+
+    /// Skip the automatic startup migration (e.g. to inspect a symlinked config first)
+    #[arg(long, global = true)]
+    no_migrate: bool,
+
+    /// Write backups to this directory instead of ~/.claudectx/backups
+    #[arg(long, global = true, value_name = "DIR")]
+    backup_dir: Option<std::path::PathBuf>,
+
+    /// Use this Claude config file instead of ~/.claude.json (overrides
+    /// $CLAUDE_CONFIG_PATH and $CLAUDE_CONFIG_DIR)
+    #[arg(long = "config", global = true, value_name = "PATH")]
+    config_path: Option<std::path::PathBuf>,
+
+    /// Answer yes to confirmation prompts (required when not running in a terminal)
+    #[arg(short, long, global = true)]
+    yes: bool,
+
+    /// Never prompt: selections pick the current profile and confirmations their default
+    #[arg(long, global = true)]
+    plain: bool,
+
+    /// Report errors on stderr as JSON ({"error":"...","code":N})
+    #[arg(long, global = true)]
+    error_json: bool,
+
+    /// Print JSON output (--json, show, export) on a single line instead of pretty-printed
+    #[arg(long, global = true)]
+    compact: bool,
+
+    /// Sort object keys when writing ~/.claude.json and profiles (defaults to the `sort_keys` setting)
+    #[arg(long, global = true, overrides_with = "no_sort_keys")]
+    sort_keys: bool,
+
+    /// Keep keys in their source order when writing (the default)
+    #[arg(long, global = true, overrides_with = "sort_keys")]
+    no_sort_keys: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -33,134 +200,1663 @@ struct Args {
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// List all saved profiles
-    List,
+    List {
+        /// Only show profiles missing accountUuid or emailAddress
+        #[arg(long)]
+        stale: bool,
+
+        /// Print only the number of profiles
+        #[arg(long, conflicts_with = "stale")]
+        count: bool,
+
+        /// Read profiles from this directory instead of ~/.claudectx
+        #[arg(long, value_name = "DIR")]
+        profiles_from: Option<std::path::PathBuf>,
+
+        /// Profile order (defaults to the `sort` setting, then name)
+        #[arg(long, value_enum)]
+        sort: Option<ProfileSort>,
+
+        /// Show aligned columns (name, display name, organization, current)
+        #[arg(long)]
+        wide: bool,
+
+        /// Only show profiles used within this window (e.g. 12h, 7d, 2w)
+        #[arg(long, value_name = "WINDOW", value_parser = parse_window)]
+        since: Option<u64>,
+
+        /// Only show profiles whose organization name contains this text (case-insensitive)
+        #[arg(long, value_name = "NAME")]
+        org: Option<String>,
+
+        /// Also show when each profile was last used
+        #[arg(short, long, conflicts_with_all = ["count", "stale", "wide"])]
+        verbose: bool,
+
+        /// Print only the email address of each profile, one per line
+        #[arg(long, conflicts_with_all = ["count", "stale", "wide", "verbose"])]
+        emails: bool,
+
+        /// How last-used times are printed
+        #[arg(long, value_enum, default_value_t = TimeFormat::Relative)]
+        time_format: TimeFormat,
+    },
 
     /// Save current config as a new profile
     Save {
         /// Profile name
-        name: String,
+        #[arg(required_unless_present = "name_from_account")]
+        name: Option<String>,
+
+        /// Without a name, name the profile after the account's email local part
+        /// (or its organization)
+        #[arg(long)]
+        name_from_account: bool,
+
+        /// Snapshot the entire config instead of the account-specific fields
+        /// (switching to it replaces the whole ~/.claude.json)
+        #[arg(long)]
+        all_fields: bool,
+
+        /// Update the account fields of an existing profile, keeping its other keys
+        /// (no overwrite prompt)
+        #[arg(long, conflicts_with = "all_fields")]
+        merge: bool,
+
+        /// Store this display name instead of the detected one
+        #[arg(long, value_name = "NAME")]
+        display_name: Option<String>,
+
+        /// Store this email address instead of the detected one
+        #[arg(long)]
+        email: Option<String>,
+
+        /// Store this organization name instead of the detected one
+        #[arg(long, value_name = "NAME")]
+        org: Option<String>,
+
+        /// Launch claude with ANTHROPIC_BASE_URL set to this gateway for the profile
+        #[arg(long, value_name = "URL")]
+        base_url: Option<String>,
+
+        /// Sync this directory into $CLAUDE_CONFIG_DIR when switching to the
+        /// profile (needs the config_dirs setting)
+        #[arg(long, value_name = "DIR")]
+        config_dir: Option<std::path::PathBuf>,
+
+        /// Don't print the success message
+        #[arg(short, long)]
+        quiet: bool,
+
+        /// Print only the absolute path of the saved profile
+        #[arg(long)]
+        print_path: bool,
     },
 
     /// Delete a profile
     Delete {
+        /// Profile name, or a pattern with `*` / `?` wildcards (asks for confirmation unless --yes)
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        name: Option<String>,
+
+        /// Delete every saved profile (asks for confirmation unless --yes)
+        #[arg(long)]
+        all: bool,
+
+        /// Move profiles to ~/.claudectx/trash instead of removing them (see `undelete`)
+        #[arg(long)]
+        trash: bool,
+    },
+
+    /// Restore the most recently trashed copy of a profile
+    Undelete {
+        /// Profile name
+        name: String,
+    },
+
+    /// Open the profiles directory (or a profile's file) in the file manager
+    Open {
+        /// Profile to open instead of the directory
+        name: Option<String>,
+
+        /// Print the path instead of opening it (e.g. on headless machines)
+        #[arg(long)]
+        print_path: bool,
+    },
+
+    /// Rename a profile
+    Rename {
+        /// Current profile name
+        old: String,
+
+        /// New profile name
+        new: String,
+    },
+
+    /// Print shell commands setting a profile's environment (e.g. ANTHROPIC_BASE_URL),
+    /// for `eval "$(claudectx env work)"`
+    Env {
+        /// Profile name
+        name: String,
+
+        /// Syntax of the printed commands
+        #[arg(long, value_enum, default_value_t = Shell::Sh)]
+        shell: Shell,
+    },
+
+    /// Duplicate a profile under a new name
+    Copy {
+        /// Profile to copy
+        source: String,
+
+        /// New profile name
+        dest: String,
+
+        /// Take the account from the current ~/.claude.json instead, keeping only
+        /// the source profile's claudectx settings (e.g. --base-url)
+        #[arg(long)]
+        from_current: bool,
+    },
+
+    /// Rename the profile matching the current ~/.claude.json
+    RenameCurrent {
+        /// New profile name
+        new: String,
+    },
+
+    /// Check profiles for missing account fields (and unexpected keys with --strict)
+    Validate {
+        /// Profile to check (all profiles if omitted)
+        name: Option<String>,
+
+        /// Also fail on keys outside the account-specific fields
+        #[arg(long)]
+        strict: bool,
+    },
+
+    /// Print a profile's contents
+    Show {
+        /// Profile name
+        name: String,
+
+        /// Don't redact values whose key looks like a secret (API keys, tokens)
+        #[arg(long)]
+        include_secrets: bool,
+
+        /// Read profiles from this directory instead of ~/.claudectx
+        #[arg(long, value_name = "DIR")]
+        profiles_from: Option<std::path::PathBuf>,
+
+        /// Print the account as JSON with snake_case keys (plus `profile` and `current`)
+        #[arg(long, conflicts_with = "include_secrets")]
+        json: bool,
+    },
+
+    /// Edit a profile in $VISUAL / $EDITOR
+    Edit {
+        /// Profile name
+        name: String,
+    },
+
+    /// Export a profile as JSON (to stdout, or to a file with --output)
+    Export {
+        /// Profile name
+        name: String,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<std::path::PathBuf>,
+
+        /// Don't redact values whose key looks like a secret (API keys, tokens)
+        #[arg(long)]
+        include_secrets: bool,
+
+        /// Bundle the current portable settings of ~/.claude.json with the
+        /// profile: {"account": <profile>, "portable": <settings>}
+        #[arg(long)]
+        full: bool,
+    },
+
+    /// Write a complete Claude config for a profile to a file (e.g. for a
+    /// container): the current portable settings plus the profile's account
+    CpConfig {
         /// Profile name
         name: String,
+
+        /// File to write
+        dest: std::path::PathBuf,
+    },
+
+    /// Import a profile file written by `export`
+    Import {
+        /// Profile file to import
+        file: std::path::PathBuf,
+
+        /// Profile name (defaults to the file name without its extension)
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Fail if the profile already exists instead of asking to overwrite it
+        #[arg(long)]
+        no_clobber: bool,
+
+        /// Import an `export --full` file, also restoring its portable settings
+        /// into ~/.claude.json (after a backup; account fields are kept)
+        #[arg(long)]
+        full: bool,
+    },
+
+    /// Bundle every profile into a single .tar.gz archive
+    ExportAll {
+        /// Archive to create
+        out: std::path::PathBuf,
+    },
+
+    /// Import every profile from a .tar.gz archive
+    ImportAll {
+        /// Archive to import
+        archive: std::path::PathBuf,
+
+        /// Overwrite existing profiles instead of skipping them
+        #[arg(long)]
+        overwrite: bool,
     },
 
     /// Login to a new Claude account and save it as a profile
-    Login,
+    Login {
+        /// Save the new account under this name instead of prompting
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Don't back up ~/.claude.json before logging in (nor restore it afterwards)
+        #[arg(long)]
+        no_backup: bool,
+
+        /// Start the new account with the current config's portable settings
+        /// (preferences, MCP servers, ...) instead of an empty config
+        #[arg(long, conflicts_with = "no_backup")]
+        clone_from_current: bool,
+
+        /// Back up and restore ~/.claude.json, printing what would happen, without
+        /// running `claude /login`
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Print the profile that was active before the latest switch (exit 1 if none)
+    Last,
+
+    /// Re-extract a profile's account fields from ~/.claude.json (only when it
+    /// holds the same account)
+    Refresh {
+        /// Profile name
+        name: String,
+    },
+
+    /// Mark a profile as used now without switching to it
+    Touch {
+        /// Profile name
+        name: String,
+    },
+
+    /// Define a short alias for a profile (`claudectx <alias>` launches it).
+    /// Without arguments (or with --list), list aliases
+    Alias {
+        /// Alias name (alone, print the profile it resolves to)
+        alias: Option<String>,
+
+        /// Profile the alias resolves to
+        name: Option<String>,
+
+        /// List aliases as `alias -> profile`
+        #[arg(long, conflicts_with_all = ["alias", "name", "remove"])]
+        list: bool,
+
+        /// Remove the alias
+        #[arg(long, requires = "alias", conflicts_with = "name")]
+        remove: bool,
+    },
+
+    /// Set the default profile (pre-selected in the picker, launched when no prompt is possible)
+    #[command(alias = "default")]
+    SetDefault {
+        /// Profile name
+        name: String,
+    },
+
+    /// View or change claudectx settings (~/.claudectx/.settings.json)
+    Config {
+        /// Setting to print or change (prints every setting when omitted)
+        key: Option<String>,
+
+        /// New value (an empty string resets the setting to its default)
+        value: Option<String>,
+    },
+
+    /// Run the symlink/slim-profile migration (normally automatic at startup)
+    Migrate {
+        /// Only report what would be migrated (exit 1 if a migration is pending)
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Check that each backup is a restorable config (fails if the most recent isn't)
+    VerifyBackup,
+
+    /// Delete backup files from the backups directory (asks for confirmation unless --yes)
+    Prune {
+        /// Only list the backups that would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Show the most recent profile switches, newest first
+    History {
+        /// Number of switches to show
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+
+        /// Print [{"profile":"name","time":"RFC 3339"}] instead of text
+        #[arg(long)]
+        json: bool,
+
+        /// How switch times are printed
+        #[arg(long, value_enum, default_value_t = TimeFormat::Relative)]
+        time_format: TimeFormat,
+    },
+
+    /// Summarize saved profiles: total, current profile and count per organization
+    Stats {
+        /// Print {"total":N,"current":"name","orgs":{"Org":N}} instead of text
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show the account logged in to ~/.claude.json, whether or not it is saved as a profile
+    Whoami {
+        /// Print the account as JSON with snake_case keys
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print the path of the claude executable that would be launched
+    Which,
+
+    /// Show the active account and its matching profile
+    #[command(alias = "active")]
+    Status {
+        /// Print the account and matching profile as JSON with snake_case keys
+        #[arg(long)]
+        json: bool,
+
+        /// How the profile's last-used time is printed
+        #[arg(long, value_enum, default_value_t = TimeFormat::Relative)]
+        time_format: TimeFormat,
+    },
+
+    /// Check the claudectx setup: list the discovered Claude config files,
+    /// which one is used, and problems `--fix` can repair
+    Doctor {
+        /// Repair the problems found (missing ~/.claudectx, dangling symlinks,
+        /// pending migration, stale .current/.default markers), confirming each
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Experimental: keep a profile applied, re-patching ~/.claude.json when
+    /// another tool changes its account fields, until Ctrl-C
+    #[cfg(feature = "watch")]
+    Watch {
+        /// Profile name
+        name: String,
+    },
 }
 
 fn main() {
-    migrate_if_needed();
-
     let args = Args::parse();
+    let error_json = args.error_json;
+
+    if let Err(err) = run(args) {
+        err.report(error_json);
+        std::process::exit(err.code());
+    }
+}
+
+fn run(args: Args) -> Result<()> {
+    ensure_home_dir()?;
+
+    if let Some(dir) = &args.backup_dir {
+        set_backup_dir_override(dir.clone());
+    }
+    if let Some(path) = &args.config_path {
+        set_config_path_override(path.clone());
+    }
+    if args.plain {
+        set_plain_mode();
+    }
+    let sort_keys = if args.sort_keys || args.no_sort_keys {
+        args.sort_keys
+    } else {
+        load_settings()?.sort_keys.unwrap_or(false)
+    };
+    if sort_keys {
+        set_sort_keys();
+    }
+
+    // doctor reports a pending migration (and a dangling symlink would make it fail)
+    let migrating = matches!(
+        args.command,
+        Some(Commands::Migrate { .. } | Commands::Doctor { .. })
+    );
+    if !args.follow_symlinks && !args.no_migrate && !migrating {
+        migrate_if_needed()?;
+    }
+
+    if args.profile_flag.is_some() && args.command.is_some() {
+        return Err(Error::Other(
+            "--profile selects launch mode and can't be combined with a subcommand".to_string(),
+        ));
+    }
 
     match args.command {
         None => {
             // Launch mode
-            let profile_name = args.profile.unwrap_or_else(|| {
-                // Interactive selection
-                let profiles = list_profiles();
 
-                if profiles.is_empty() {
-                    let current_config = read_claude_config();
-                    let current_account = get_oauth_account(&current_config);
-                    println!(
-                        "Current account: {} @ {}",
-                        current_account.display_name, current_account.organization_name
-                    );
-                    println!(
-                        "\nNo profiles saved yet. Use 'claudectx save <name>' to save this profile."
-                    );
-                    std::process::exit(0);
-                }
+            // --claude-arg values come first, then everything after --
+            let claude_args: Vec<String> = args
+                .claude_arg
+                .into_iter()
+                .chain(args.claude_args)
+                .collect();
 
-                let current_profile = get_current_profile();
-                select_profile(&profiles, current_profile.as_deref()).expect("No profile selected")
-            });
+            let options = LaunchOptions {
+                switch: SwitchOptions {
+                    mode: if args.merge {
+                        SwitchMode::Merge
+                    } else if args.no_portable {
+                        SwitchMode::AccountOnly
+                    } else {
+                        SwitchMode::Replace
+                    },
+                    follow_symlinks: args.follow_symlinks,
+                    verify: args.verify,
+                },
+                quiet: args.quiet,
+                claude_bin: args.claude_bin,
+                strict: args.strict,
+                before_hook: args.before,
+                after_hook: args.after,
+                strict_hooks: args.strict_hooks,
+                version_check: args
+                    .require_claude_version
+                    .map(|minimum| VersionCheck {
+                        minimum,
+                        required: true,
+                    })
+                    .or(args.check_claude_version.map(|minimum| VersionCheck {
+                        minimum,
+                        required: false,
+                    })),
+                banner: if args.banner || args.no_banner {
+                    args.banner
+                } else {
+                    load_settings()?.banner.unwrap_or(false)
+                },
+            };
 
-            let path = get_profile_path(&profile_name);
+            if args.from_stdin {
+                return switch_from_stdin_and_launch_claude(&claude_args, &options);
+            }
 
-            if !path.exists() {
+            let profile_name = match args.profile_flag.or(args.profile) {
+                Some(name) => resolve_alias(&name)?,
+                None => {
+                    // Interactive selection
+                    let profiles = list_profiles()?;
+
+                    if profiles.is_empty() {
+                        let current_config = read_claude_config()?;
+                        println!("Current account: {}", describe_account(&current_config)?);
+                        println!(
+                            "\nNo profiles saved yet. Use 'claudectx save <name>' to save this profile."
+                        );
+                        return Ok(());
+                    }
+
+                    let interactive = is_interactive();
+                    match get_default_profile() {
+                        Some(default) if args.use_default || !interactive => default,
+                        // Fail up front instead of surfacing dialoguer's error
+                        None if !interactive && !args.plain => {
+                            return Err(Error::Other(
+                                "No default profile set; pass a profile name, set one with 'claudectx default <name>', or use --plain"
+                                    .to_string(),
+                            ));
+                        }
+                        _ => {
+                            let current_profile = get_current_profile();
+                            select_profile(
+                                &profiles,
+                                current_profile.as_deref(),
+                                args.details,
+                                args.fuzzy,
+                            )?
+                            .ok_or_else(|| Error::Other("No profile selected".to_string()))?
+                        }
+                    }
+                }
+            };
+
+            if args.print_resulting_config {
+                print_json(
+                    &resulting_config(&profile_name, &options.switch)?,
+                    args.compact,
+                );
+                return Ok(());
+            }
+
+            if !profile_exists(&profile_name)? {
                 // Profile doesn't exist - offer to create it
                 let slug = slugify(&profile_name);
-                let create = Confirm::new()
-                    .with_prompt(format!(
-                        "Profile '{}' not found. Save current config as this profile?",
+                if !args.create && !args.yes && !args.plain && !is_interactive() {
+                    return Err(Error::Other(format!(
+                        "Profile '{}' not found; pass --create to save the current config as it",
                         slug
-                    ))
-                    .interact()
-                    .expect("Failed to prompt");
+                    )));
+                }
+                let create = args.create
+                    || confirm(
+                        "launch",
+                        &format!(
+                            "Profile '{}' not found. Save current config as this profile?",
+                            slug
+                        ),
+                        false,
+                        args.yes,
+                    )?;
 
                 if create {
-                    save_profile(&profile_name);
+                    save_profile(&profile_name, &SaveOptions::default())?;
                     println!("Profile '{}' saved.", slug);
                 } else {
-                    panic!("Profile '{}' not found", slug);
+                    return Err(Error::profile_not_found(&slug));
                 }
             }
 
             // Patch config and launch claude
-            switch_and_launch_claude(&profile_name, &args.claude_args);
+            switch_and_launch_claude(&profile_name, &claude_args, &options)
         }
-        Some(Commands::List) => {
-            let profiles = list_profiles();
+        Some(Commands::List {
+            stale,
+            count,
+            profiles_from,
+            sort,
+            wide,
+            since,
+            verbose,
+            time_format,
+            org,
+            emails,
+        }) => {
+            use_profiles_from(profiles_from)?;
+            let mut profiles = list_profiles()?;
+            let usage = load_usage()?;
+
+            if let Some(window) = since {
+                let now = now_secs();
+                profiles.retain(|name| used_within(&usage, name, window, now));
+            }
+
+            if let Some(org) = org {
+                let org = org.to_lowercase();
+                let mut matching = Vec::new();
+                for name in profiles {
+                    let names =
+                        read_profile_summary(&name).and_then(|config| account_names(&config));
+                    match names {
+                        Ok((_, organization_name)) => {
+                            if organization_name.is_some_and(|o| o.to_lowercase().contains(&org)) {
+                                matching.push(name);
+                            }
+                        }
+                        Err(err) => eprintln!("Warning: skipped profile '{}' ({})", name, err),
+                    }
+                }
+                profiles = matching;
+            }
+
+            if count {
+                println!("{}", profiles.len());
+                return Ok(());
+            }
+
+            let sort = match sort {
+                Some(sort) => sort,
+                None => load_settings()?.sort.unwrap_or_default(),
+            };
+            sort_profiles(&mut profiles, sort, &usage);
+
+            // Bare output for scripts: no "No profiles found." either
+            if emails {
+                for name in profiles {
+                    match read_profile_summary(&name) {
+                        Ok(config) => {
+                            if let Some(email) = account_email(&config) {
+                                println!("{}", email);
+                            }
+                        }
+                        Err(err) => eprintln!("Warning: skipped profile '{}' ({})", name, err),
+                    }
+                }
+                return Ok(());
+            }
 
             if profiles.is_empty() {
                 println!("No profiles found.");
-                return;
+                return Ok(());
+            }
+
+            if stale {
+                let mut found = false;
+                for name in profiles {
+                    let missing = match read_profile_summary(&name) {
+                        Ok(config) => missing_account_keys(&config),
+                        // A profile that can't be read is as broken as it gets
+                        Err(err) => {
+                            found = true;
+                            println!("{} - unreadable ({})", name, err);
+                            continue;
+                        }
+                    };
+                    if !missing.is_empty() {
+                        found = true;
+                        println!("{} - missing {}", name, missing.join(", "));
+                    }
+                }
+                if !found {
+                    println!("No stale profiles found.");
+                }
+                return Ok(());
             }
 
             let current_profile = get_current_profile();
 
+            let mut skipped = Vec::new();
+
+            if wide {
+                let mut rows = Vec::new();
+                for name in profiles {
+                    let names = listable_summary(&name)
+                        .and_then(|config| account_names(&config).map_err(|_| "parse error"));
+                    let (display_name, organization_name) = match names {
+                        Ok(names) => names,
+                        Err(problem) => {
+                            skipped.push(problem);
+                            continue;
+                        }
+                    };
+                    rows.push(ProfileRow {
+                        current: current_profile.as_ref() == Some(&name),
+                        name,
+                        display_name,
+                        organization_name: organization_name.unwrap_or_default(),
+                    });
+                }
+                print_profile_table(&rows);
+                print_skipped_footer(&skipped);
+                return Ok(());
+            }
+
+            let now = now_secs();
             for name in profiles {
-                let path = get_profile_path(&name);
-                let config: serde_json::Value = serde_json::from_str(
-                    &std::fs::read_to_string(&path).expect("Failed to read profile"),
-                )
-                .expect("Failed to parse profile");
+                let described = listable_summary(&name).and_then(|config| {
+                    let description = describe_account(&config).map_err(|_| "parse error")?;
+                    Ok((config, description))
+                });
+                let (config, description) = match described {
+                    Ok(described) => described,
+                    Err(problem) => {
+                        skipped.push(problem);
+                        continue;
+                    }
+                };
 
-                let account = get_oauth_account(&config);
                 let marker = if current_profile.as_ref() == Some(&name) {
                     " *"
                 } else {
                     ""
                 };
+                let full = if is_full_profile(&config) {
+                    " [full]"
+                } else {
+                    ""
+                };
+                let last_used = if verbose {
+                    format!(
+                        " ({})",
+                        format_usage_time(usage.get(&name).copied(), now, time_format)
+                    )
+                } else {
+                    String::new()
+                };
+                println!("{} - {}{}{}{}", name, description, full, last_used, marker);
+            }
+            print_skipped_footer(&skipped);
+            Ok(())
+        }
+        Some(Commands::Save {
+            name,
+            name_from_account: _,
+            all_fields,
+            merge,
+            display_name,
+            email,
+            org,
+            base_url,
+            config_dir,
+            quiet,
+            print_path,
+        }) => {
+            // clap requires a name unless --name-from-account is passed
+            let name = match name {
+                Some(name) => name,
+                None => {
+                    account_profile_name(&read_claude_config()?).ok_or_else(|| {
+                        Error::Other(
+                            "The current account has no email or organization to name the profile after; pass a name"
+                                .to_string(),
+                        )
+                    })?
+                }
+            };
+            let slug = slugify(&name);
+
+            if !merge && profile_exists(&name)? {
+                let overwrite = confirm(
+                    "save",
+                    &format!("Profile '{}' already exists. Overwrite?", slug),
+                    false,
+                    args.yes,
+                )?;
+
+                if !overwrite {
+                    println!("Cancelled.");
+                    return Ok(());
+                }
+            }
+
+            save_profile(
+                &name,
+                &SaveOptions {
+                    all_fields,
+                    merge,
+                    display_name,
+                    email,
+                    organization_name: org,
+                    base_url,
+                    config_dir,
+                },
+            )?;
+            if print_path {
+                let path = get_profile_path(&name)?;
+                let path = path
+                    .canonicalize()
+                    .map_err(|e| Error::io("Failed to resolve profile path", e))?;
+                println!("{}", path.display());
+            } else if !quiet {
+                println!("Saved current config as '{}'", slug);
+            }
+            Ok(())
+        }
+        Some(Commands::Delete {
+            all: true, trash, ..
+        }) => delete_profiles(list_profiles()?, "delete --all", trash, args.yes),
+        Some(Commands::Delete { name, trash, .. }) => {
+            let name = name.unwrap_or_default();
+            if is_glob_pattern(&name) {
+                let pattern = name.to_lowercase();
+                let matched: Vec<String> = list_profiles()?
+                    .into_iter()
+                    .filter(|profile| glob_match(&pattern, profile))
+                    .collect();
+                if matched.is_empty() {
+                    return Err(Error::ProfileNotFound(format!(
+                        "No profiles match '{}'",
+                        name
+                    )));
+                }
+                return delete_profiles(matched, "delete", trash, args.yes);
+            }
+
+            if !profile_exists(&name)? {
+                return Err(Error::profile_not_found(&slugify(&name)));
+            }
+
+            if trash {
+                let trashed = trash_profile(&name)?;
                 println!(
-                    "{} - {} @ {}{}",
-                    name, account.display_name, account.organization_name, marker
+                    "Moved profile '{}' to {}",
+                    slugify(&name),
+                    trashed.display()
                 );
+            } else {
+                delete_profile(&name)?;
+                println!("Deleted profile '{}'", slugify(&name));
             }
+            Ok(())
         }
-        Some(Commands::Save { name }) => {
-            let slug = slugify(&name);
+        Some(Commands::Undelete { name }) => {
+            undelete_profile(&name)?;
+            println!("Restored profile '{}'", slugify(&name));
+            Ok(())
+        }
+        Some(Commands::Open { name, print_path }) => {
+            let path = match name {
+                Some(name) => {
+                    let path = get_profile_path(&name)?;
+                    if !path.exists() {
+                        return Err(Error::profile_not_found(&slugify(&name)));
+                    }
+                    path
+                }
+                None => {
+                    ensure_profiles_dir()?;
+                    profiles_dir()
+                }
+            };
 
-            if profile_exists(&name) {
-                let overwrite = Confirm::new()
-                    .with_prompt(format!("Profile '{}' already exists. Overwrite?", slug))
-                    .interact()
-                    .expect("Failed to prompt");
+            if print_path {
+                println!("{}", path.display());
+                return Ok(());
+            }
+            open_with_default_handler(&path)
+        }
+        Some(Commands::Rename { old, new }) => {
+            rename_profile(&old, &new)?;
+            println!("Renamed profile '{}' to '{}'", slugify(&old), slugify(&new));
+            Ok(())
+        }
+        Some(Commands::Env { name, shell }) => {
+            if !profile_exists(&name)? {
+                return Err(Error::profile_not_found(&slugify(&name)));
+            }
+            for (key, value) in profile_env(&read_profile(&name)?) {
+                println!("{}", shell.export(key, &value));
+            }
+            Ok(())
+        }
+        Some(Commands::Copy {
+            source,
+            dest,
+            from_current,
+        }) => {
+            copy_profile(&source, &dest, from_current)?;
+            if from_current {
+                println!(
+                    "Saved current config as '{}' with the settings of '{}'",
+                    slugify(&dest),
+                    slugify(&source)
+                );
+            } else {
+                println!(
+                    "Copied profile '{}' to '{}'",
+                    slugify(&source),
+                    slugify(&dest)
+                );
+            }
+            Ok(())
+        }
+        Some(Commands::RenameCurrent { new }) => {
+            let old = get_current_profile().ok_or_else(|| {
+                Error::Other(
+                    "No saved profile matches the current config; use 'claudectx rename <old> <new>'"
+                        .to_string(),
+                )
+            })?;
+            rename_profile(&old, &new)?;
+            println!("Renamed profile '{}' to '{}'", old, slugify(&new));
+            Ok(())
+        }
+        Some(Commands::Validate { name, strict }) => {
+            let profiles = match name {
+                Some(name) => {
+                    if !profile_exists(&name)? {
+                        return Err(Error::profile_not_found(&slugify(&name)));
+                    }
+                    vec![slugify(&name)]
+                }
+                None => {
+                    let mut profiles = list_profiles()?;
+                    profiles.sort();
+                    profiles
+                }
+            };
+
+            let fields = account_fields()?;
+            let mut invalid = 0;
+            for name in &profiles {
+                let mut problems = vec![];
+                match read_profile(name) {
+                    Ok(profile) => {
+                        let missing = missing_account_keys(&profile);
+                        if !missing.is_empty() {
+                            problems.push(format!("missing {}", missing.join(", ")));
+                        }
+                        let unexpected = unexpected_keys(&profile, &fields);
+                        if strict && !unexpected.is_empty() {
+                            problems.push(format!("unexpected keys {}", unexpected.join(", ")));
+                        }
+                    }
+                    Err(err) => problems.push(err.to_string()),
+                }
+
+                if problems.is_empty() {
+                    println!("{}: ok", name);
+                } else {
+                    invalid += 1;
+                    println!("{}: {}", name, problems.join("; "));
+                }
+            }
+
+            if invalid > 0 {
+                return Err(Error::Parse(format!(
+                    "{} of {} profiles failed validation",
+                    invalid,
+                    profiles.len()
+                )));
+            }
+            Ok(())
+        }
+        Some(Commands::Show {
+            name,
+            include_secrets,
+            profiles_from,
+            json,
+        }) => {
+            use_profiles_from(profiles_from)?;
+            if json {
+                if !profile_exists(&name)? {
+                    return Err(Error::profile_not_found(&slugify(&name)));
+                }
+                let slug = slugify(&name);
+                let profile = read_profile(&name)?;
+                let output = serde_json::json!({
+                    "profile": slug,
+                    "current": get_current_profile().as_deref() == Some(slug.as_str()),
+                    "account": account_json(&profile)?,
+                    "base_url": profile_base_url(&profile),
+                });
+                print_json(&output, args.compact);
+            } else {
+                println!(
+                    "{}",
+                    render_profile(&name, include_secrets, false, args.compact)?
+                );
+            }
+            Ok(())
+        }
+        Some(Commands::Edit { name }) => {
+            if !profile_exists(&name)? {
+                return Err(Error::profile_not_found(&slugify(&name)));
+            }
+
+            edit_json_file(&get_profile_path(&name)?)?;
+            println!("Saved profile '{}'", slugify(&name));
+            Ok(())
+        }
+        Some(Commands::Export {
+            name,
+            output,
+            include_secrets,
+            full,
+        }) => {
+            let json = render_profile(&name, include_secrets, full, args.compact)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, json)
+                        .map_err(|e| Error::io("Failed to write export", e))?;
+                    println!(
+                        "Exported profile '{}' to {}",
+                        slugify(&name),
+                        path.display()
+                    );
+                }
+                None => println!("{}", json),
+            }
+            Ok(())
+        }
+        Some(Commands::CpConfig { name, dest }) => {
+            if dest.exists() {
+                let overwrite = confirm(
+                    "cp-config",
+                    &format!("{} already exists. Overwrite?", dest.display()),
+                    false,
+                    args.yes,
+                )?;
+                if !overwrite {
+                    println!("Cancelled.");
+                    return Ok(());
+                }
+            }
+            materialize_config(&name, &dest)?;
+            println!(
+                "Wrote config for profile '{}' to {}",
+                slugify(&name),
+                dest.display()
+            );
+            Ok(())
+        }
+        Some(Commands::ExportAll { out }) => {
+            let exported = export_all(&out)?;
+            println!(
+                "Exported {} profile{} to {}",
+                exported.len(),
+                if exported.len() == 1 { "" } else { "s" },
+                out.display()
+            );
+            Ok(())
+        }
+        Some(Commands::Import {
+            file,
+            name,
+            no_clobber,
+            full,
+        }) => {
+            let name = match name {
+                Some(name) => name,
+                None => import_file_name(&file)?,
+            };
+            let slug = slugify(&name);
 
+            if profile_exists(&slug)? {
+                if no_clobber {
+                    return Err(Error::Other(format!(
+                        "Profile '{}' already exists (--no-clobber)",
+                        slug
+                    )));
+                }
+                let overwrite = confirm(
+                    "import",
+                    &format!("Profile '{}' already exists. Overwrite?", slug),
+                    false,
+                    args.yes,
+                )?;
                 if !overwrite {
                     println!("Cancelled.");
-                    return;
+                    return Ok(());
+                }
+            }
+
+            if full {
+                let restore = confirm(
+                    "import",
+                    "Replace the portable settings of ~/.claude.json with the exported ones?",
+                    false,
+                    args.yes,
+                )?;
+                let slug = import_full_export(&file, &name, restore)?;
+                println!("Imported '{}'", slug);
+                if restore {
+                    println!("Restored portable settings.");
                 }
+                return Ok(());
             }
 
-            save_profile(&name);
-            println!("Saved current config as '{}'", slug);
+            let slug = import_profile(&file, &name)?;
+            println!("Imported '{}'", slug);
+            Ok(())
         }
-        Some(Commands::Delete { name }) => {
-            if !profile_exists(&name) {
-                panic!("Profile '{}' not found", slugify(&name));
+        Some(Commands::ImportAll { archive, overwrite }) => {
+            let summary = import_all(&archive, overwrite)?;
+            for slug in &summary.imported {
+                println!("Imported '{}'", slug);
+            }
+            for (slug, reason) in &summary.skipped {
+                println!("Skipped '{}': {}", slug, reason);
             }
+            println!(
+                "{} imported, {} skipped",
+                summary.imported.len(),
+                summary.skipped.len()
+            );
+            Ok(())
+        }
+        Some(Commands::Login {
+            name,
+            no_backup,
+            clone_from_current,
+            dry_run,
+        }) => run_login_workflow(&LoginOptions {
+            claude_bin: args.claude_bin,
+            no_backup,
+            name,
+            assume_yes: args.yes,
+            clone_from_current,
+            dry_run,
+        }),
+        Some(Commands::Migrate { check: true }) => {
+            let plan = plan_migration()?;
+            println!(
+                "Claude config is a symlink: {}",
+                if plan.config_is_symlink { "yes" } else { "no" }
+            );
+            println!("Profiles to slim: {}", plan.profiles_to_slim.len());
+            for name in &plan.profiles_to_slim {
+                println!("  {}", name);
+            }
+
+            if plan.is_pending() {
+                Err(Error::Other("Migration pending".to_string()))
+            } else {
+                println!("Nothing to migrate.");
+                Ok(())
+            }
+        }
+        Some(Commands::Migrate { check: false }) => migrate().map(|_| ()),
+        Some(Commands::Prune { dry_run }) => prune_backups(dry_run, args.yes),
+        Some(Commands::VerifyBackup) => verify_backups(),
+        Some(Commands::Which) => {
+            let path = resolve_claude_bin(args.claude_bin.as_deref())
+                .ok_or_else(|| Error::Other("claude executable not found".to_string()))?;
+            println!("{}", path.display());
+            Ok(())
+        }
+        Some(Commands::History {
+            limit,
+            json,
+            time_format,
+        }) => {
+            let history = load_history()?;
+            let recent = history.iter().rev().take(limit);
 
-            delete_profile(&name);
-            println!("Deleted profile '{}'", slugify(&name));
+            if json {
+                let entries: Vec<serde_json::Value> = recent
+                    .map(|(secs, slug)| {
+                        serde_json::json!({ "profile": slug, "time": format_rfc3339(*secs) })
+                    })
+                    .collect();
+                print_json(&serde_json::Value::Array(entries), args.compact);
+                return Ok(());
+            }
+
+            if history.is_empty() {
+                println!("No switches recorded yet.");
+                return Ok(());
+            }
+            let now = now_secs();
+            for (secs, slug) in recent {
+                println!(
+                    "{}  {}",
+                    format_usage_time(Some(*secs), now, time_format),
+                    slug
+                );
+            }
+            Ok(())
         }
-        Some(Commands::Login) => {
-            run_login_workflow();
+        Some(Commands::Last) => {
+            let history = load_history()?;
+            let previous = previous_profile(&history)
+                .ok_or_else(|| Error::Other("No previous profile recorded".to_string()))?;
+            println!("{}", previous);
+            Ok(())
         }
+        Some(Commands::Refresh { name }) => {
+            refresh_profile(&name)?;
+            println!(
+                "Refreshed profile '{}' from the current config",
+                slugify(&name)
+            );
+            Ok(())
+        }
+        Some(Commands::Touch { name }) => {
+            let slug = slugify(&name);
+            if !profile_exists(&slug)? {
+                return Err(Error::profile_not_found(&slug));
+            }
+            record_usage(&slug)?;
+            println!("Marked '{}' as used now", slug);
+            Ok(())
+        }
+        Some(Commands::Alias {
+            alias,
+            name,
+            list: _,
+            remove,
+        }) => match (alias, name) {
+            (Some(alias), _) if remove => {
+                let slug = remove_alias(&alias)?;
+                println!("Removed alias '{}' (was '{}')", slugify(&alias), slug);
+                Ok(())
+            }
+            (Some(alias), Some(name)) => {
+                set_alias(&alias, &name)?;
+                println!(
+                    "Alias '{}' now resolves to '{}'",
+                    slugify(&alias),
+                    slugify(&name)
+                );
+                Ok(())
+            }
+            (Some(alias), None) => {
+                let alias = slugify(&alias);
+                let slug = load_aliases()?
+                    .remove(&alias)
+                    .ok_or_else(|| Error::Other(format!("No alias named '{}'", alias)))?;
+                println!("{} -> {}", alias, slug);
+                Ok(())
+            }
+            (None, _) => {
+                for (alias, slug) in load_aliases()? {
+                    println!("{} -> {}", alias, slug);
+                }
+                Ok(())
+            }
+        },
+        Some(Commands::SetDefault { name }) => {
+            set_default_profile(&name)?;
+            println!("Default profile set to '{}'", slugify(&name));
+            Ok(())
+        }
+        Some(Commands::Config { key, value }) => {
+            let mut settings = load_settings()?;
+            match (key, value) {
+                (None, _) => {
+                    for key in SETTING_KEYS {
+                        println!("{} = {}", key, settings.get(key)?);
+                    }
+                }
+                (Some(key), None) => println!("{}", settings.get(&key)?),
+                (Some(key), Some(value)) => {
+                    let old_extension = settings.profile_extension().to_string();
+                    settings.set(&key, &value)?;
+                    // Profiles saved with the old suffix are no longer listed
+                    let hidden = if settings.profile_extension() != old_extension {
+                        list_profiles()?.len()
+                    } else {
+                        0
+                    };
+                    save_settings(&settings)?;
+                    println!("{} = {}", key, settings.get(&key)?);
+                    if hidden > 0 {
+                        eprintln!(
+                            "Warning: {} profile(s) saved as *{} are hidden until renamed to *{} (or the setting is reset)",
+                            hidden,
+                            old_extension,
+                            settings.profile_extension()
+                        );
+                    }
+                }
+            }
+            Ok(())
+        }
+        Some(Commands::Stats { json }) => {
+            let mut profiles = list_profiles()?;
+            let mut orgs = std::collections::BTreeMap::<String, usize>::new();
+            profiles.retain(|name| match read_profile_summary(name) {
+                Ok(summary) => {
+                    if let Ok((_, Some(organization_name))) = account_names(&summary) {
+                        *orgs.entry(organization_name).or_default() += 1;
+                    }
+                    true
+                }
+                Err(err) => {
+                    eprintln!("Warning: skipped profile '{}' ({})", name, err);
+                    false
+                }
+            });
+            let current = get_current_profile();
+
+            if json {
+                let output = serde_json::json!({
+                    "total": profiles.len(),
+                    "current": current,
+                    "orgs": orgs,
+                });
+                print_json(&output, args.compact);
+                return Ok(());
+            }
+
+            println!("Profiles: {}", profiles.len());
+            println!("Current: {}", current.as_deref().unwrap_or("none"));
+            if !orgs.is_empty() {
+                println!("Organizations:");
+            }
+            for (organization_name, count) in &orgs {
+                println!("  {}: {}", organization_name, count);
+            }
+            Ok(())
+        }
+        Some(Commands::Whoami { json }) => {
+            let config = read_claude_config()?;
+            if json {
+                print_json(&account_json(&config)?, args.compact);
+            } else {
+                println!("{}", describe_identity(&config)?);
+            }
+            Ok(())
+        }
+        Some(Commands::Status { json, time_format }) => {
+            let config = read_claude_config()?;
+            let current = get_current_profile();
+            if json {
+                let output = serde_json::json!({
+                    "profile": current,
+                    "current": current.is_some(),
+                    "account": account_json(&config)?,
+                });
+                print_json(&output, args.compact);
+                return Ok(());
+            }
+
+            let usage = load_usage()?;
+            let profile = current
+                .map(|name| {
+                    let used = usage.get(&name).copied();
+                    format!(
+                        "profile '{}' ({})",
+                        name,
+                        format_usage_time(used, now_secs(), time_format)
+                    )
+                })
+                .unwrap_or_else(|| "unsaved account".to_string());
+            println!("{}, {}", describe_identity(&config)?, profile);
+            Ok(())
+        }
+        #[cfg(feature = "watch")]
+        Some(Commands::Watch { name }) => watch::watch_profile(
+            &name,
+            &SwitchOptions {
+                follow_symlinks: args.follow_symlinks,
+                ..SwitchOptions::default()
+            },
+        ),
+        Some(Commands::Doctor { fix }) => {
+            let active = claude_config_path();
+            println!("Claude config candidates (highest priority first):");
+            for (source, path) in discovered_config_paths() {
+                let marker = if path == active { "*" } else { " " };
+                let missing = if path.exists() { "" } else { ", missing" };
+                println!("{} {} ({}{})", marker, path.display(), source, missing);
+            }
+
+            let invalid = invalid_profiles()?;
+            if !fix {
+                let repairs = plan_repairs()?;
+                if repairs.is_empty() && invalid.is_empty() {
+                    println!("No problems found.");
+                }
+                if !repairs.is_empty() {
+                    println!("Problems (run 'claudectx doctor --fix' to repair):");
+                    for repair in repairs {
+                        println!("- {}", repair.describe());
+                    }
+                }
+                print_invalid_profiles(&invalid);
+                return Ok(());
+            }
+
+            // Re-plan after each repair, since one fix can reveal or settle another
+            let mut handled = Vec::new();
+            let mut fixed = 0;
+            while let Some(repair) = plan_repairs()?
+                .into_iter()
+                .find(|repair| !handled.contains(repair))
+            {
+                let description = repair.describe();
+                if confirm(
+                    "doctor --fix",
+                    &format!("{}?", description),
+                    false,
+                    args.yes,
+                )? {
+                    repair.apply()?;
+                    println!("Fixed: {}", description);
+                    fixed += 1;
+                } else {
+                    println!("Skipped: {}", description);
+                }
+                handled.push(repair);
+            }
+            if handled.is_empty() && invalid.is_empty() {
+                println!("No problems found.");
+            } else if !handled.is_empty() {
+                println!("{} of {} problem(s) fixed.", fixed, handled.len());
+            }
+            print_invalid_profiles(&invalid);
+            Ok(())
+        }
+    }
+}
+
+/// Shell syntax for `env`
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum Shell {
+    /// `export KEY='value'` (bash, zsh, sh)
+    Sh,
+    /// `$env:KEY = 'value'`
+    Powershell,
+}
+
+impl Shell {
+    /// Command setting `key` to `value`, single-quoted for this shell
+    fn export(self, key: &str, value: &str) -> String {
+        match self {
+            Shell::Sh => format!("export {}='{}'", key, value.replace('\'', "'\\''")),
+            Shell::Powershell => format!("$env:{} = '{}'", key, value.replace('\'', "''")),
+        }
+    }
+}
+
+/// Account summary of a profile for `list`, or the problem that keeps it
+/// out of the listing
+fn listable_summary(name: &str) -> std::result::Result<serde_json::Value, &'static str> {
+    let config = read_profile_summary(name).map_err(|_| "parse error")?;
+    if !has_account_identity(&config) {
+        return Err("missing accountUuid");
+    }
+    Ok(config)
+}
+
+/// List the profiles `doctor` found to be invalid JSON, which need a manual fix
+fn print_invalid_profiles(invalid: &[String]) {
+    if invalid.is_empty() {
+        return;
+    }
+    println!("Problems to fix by hand:");
+    for name in invalid {
+        println!(
+            "- Profile '{}' is not valid JSON (fix it with 'claudectx edit {}' or delete it)",
+            name, name
+        );
+    }
+}
+
+/// Print `(N profiles skipped: 1 parse error, ...)` after a listing that
+/// left out broken profiles
+fn print_skipped_footer(skipped: &[&str]) {
+    if skipped.is_empty() {
+        return;
+    }
+    let mut tally: Vec<(&str, usize)> = Vec::new();
+    for problem in skipped {
+        match tally.iter_mut().find(|(seen, _)| seen == problem) {
+            Some((_, count)) => *count += 1,
+            None => tally.push((problem, 1)),
+        }
+    }
+    let details: Vec<String> = tally
+        .iter()
+        .map(|(problem, count)| format!("{} {}", count, problem))
+        .collect();
+    println!(
+        "({} profile{} skipped: {})",
+        skipped.len(),
+        if skipped.len() == 1 { "" } else { "s" },
+        details.join(", ")
+    );
+}
+
+/// Report whether each backup is a restorable Claude config. Fails if the most
+/// recent one isn't, since that is the one a restore would use.
+fn verify_backups() -> Result<()> {
+    let backups = backups_by_age()?;
+    let Some(latest) = backups.last() else {
+        println!("No backups found.");
+        return Ok(());
+    };
+
+    let mut latest_problem = None;
+    for path in &backups {
+        let problem = backup_problem(path);
+        match &problem {
+            None => println!("ok       {}", path.display()),
+            Some(reason) => println!("corrupt  {} ({})", path.display(), reason),
+        }
+        if path == latest {
+            latest_problem = problem;
+        }
+    }
+
+    match latest_problem {
+        None => Ok(()),
+        Some(reason) => Err(Error::Parse(format!(
+            "Most recent backup {:?} is not restorable: {}",
+            latest, reason
+        ))),
+    }
+}
+
+/// List backups with their sizes, confirm (unless --yes or --dry-run) and delete them
+fn prune_backups(dry_run: bool, assume_yes: bool) -> Result<()> {
+    let backups = list_backups()?;
+    if backups.is_empty() {
+        println!("No backups found.");
+        return Ok(());
+    }
+
+    println!(
+        "{} in {}:",
+        if dry_run {
+            "Backups that would be deleted"
+        } else {
+            "Backups to delete"
+        },
+        backups_dir().display()
+    );
+    for (path, size) in &backups {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        println!("  {} ({})", name, format_size(*size));
+    }
+    let total = format_size(backups.iter().map(|(_, size)| size).sum());
+    if dry_run {
+        println!("{} backup(s), {} total", backups.len(), total);
+        return Ok(());
+    }
+
+    let proceed = confirm(
+        "prune",
+        &format!("Delete {} backup(s) ({})?", backups.len(), total),
+        false,
+        assume_yes,
+    )?;
+    if !proceed {
+        println!("Cancelled.");
+        return Ok(());
+    }
+
+    for (path, _) in &backups {
+        fs::remove_file(path).map_err(|e| Error::io("Failed to delete backup", e))?;
+    }
+    println!("Deleted {} backup(s)", backups.len());
+    Ok(())
+}
+
+/// List `profiles`, confirm (unless --yes) and delete (or trash) them all
+fn delete_profiles(
+    mut profiles: Vec<String>,
+    action: &str,
+    trash: bool,
+    assume_yes: bool,
+) -> Result<()> {
+    if profiles.is_empty() {
+        println!("No profiles found.");
+        return Ok(());
+    }
+    profiles.sort();
+
+    println!("Profiles to delete:");
+    for name in &profiles {
+        println!("  {}", name);
+    }
+    let proceed = confirm(
+        action,
+        &format!("Delete {} profile(s)?", profiles.len()),
+        false,
+        assume_yes,
+    )?;
+    if !proceed {
+        println!("Cancelled.");
+        return Ok(());
+    }
+
+    for name in &profiles {
+        if trash {
+            trash_profile(name)?;
+        } else {
+            delete_profile(name)?;
+        }
+    }
+    if trash {
+        println!(
+            "Moved {} profile(s) to {}",
+            profiles.len(),
+            trash_dir().display()
+        );
+    } else {
+        println!("Deleted {} profile(s)", profiles.len());
+    }
+    Ok(())
+}
+
+/// Point profile lookups at `dir` (from --profiles-from) for this run
+fn use_profiles_from(dir: Option<std::path::PathBuf>) -> Result<()> {
+    if let Some(dir) = dir {
+        if !dir.is_dir() {
+            return Err(Error::Io(format!("Profiles directory {:?} not found", dir)));
+        }
+        set_profiles_dir_override(dir);
+    }
+    Ok(())
+}
+
+/// Print JSON output: pretty-printed, or on one line with --compact
+fn print_json(value: &serde_json::Value, compact: bool) {
+    if compact {
+        println!("{}", value);
+    } else {
+        println!("{:#}", value);
+    }
+}
+
+/// Serialize a profile (pretty-printed unless `compact`), redacting secrets
+/// unless `include_secrets` is set. With `full`, the profile is bundled with
+/// the live portable settings as `{"account": ..., "portable": ...}`.
+fn render_profile(name: &str, include_secrets: bool, full: bool, compact: bool) -> Result<String> {
+    if !profile_exists(name)? {
+        return Err(Error::profile_not_found(&slugify(name)));
+    }
+
+    let mut profile = read_profile(name)?;
+    if full {
+        profile = serde_json::json!({ "account": profile, "portable": portable_settings()? });
+    }
+    if !include_secrets {
+        redact_secrets(&mut profile);
     }
+    let json = if compact {
+        serde_json::to_string(&profile)
+    } else {
+        serde_json::to_string_pretty(&profile)
+    };
+    json.map_err(|e| Error::parse("Failed to serialize profile", e))
 }
@@ -1,7 +1,20 @@
+use serde::Deserialize;
 use std::fs;
-use std::path::PathBuf;
-
-use crate::config::{claude_config_path, home_dir};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::aliases::{ensure_not_alias, load_aliases, remove_aliases_to, retarget_aliases};
+use crate::archive::{create_archive, extract_archive, StagingDir};
+use crate::config::{
+    api_key_label, claude_config_path, describe_account, get_api_key, home_dir,
+    missing_config_error, read_claude_config, strip_bom, API_KEY_FIELD,
+};
+use crate::error::{Error, Result};
+use crate::settings::load_settings;
+use crate::usage::{
+    load_seen_accounts, now_secs, record_history, record_seen_account, record_usage, rename_usage,
+    SeenAccounts,
+};
 
 /// Fields that are account-specific and stored in slim profile files.
 /// Everything else in ~/.claude.json is portable (settings, preferences, etc.)
@@ -16,15 +29,30 @@ const ACCOUNT_SPECIFIC_FIELDS: &[&str] = &[
     "hasAvailableSubscription",
 ];
 
+/// Account-specific fields: the built-in list plus any `extra_account_fields`
+/// from the settings
+pub fn account_fields() -> Result<Vec<String>> {
+    let mut fields: Vec<String> = ACCOUNT_SPECIFIC_FIELDS
+        .iter()
+        .map(|field| field.to_string())
+        .collect();
+    for extra in load_settings()?.extra_account_fields {
+        if !fields.contains(&extra) {
+            fields.push(extra);
+        }
+    }
+    Ok(fields)
+}
+
 /// Extract only the account-specific fields from a config JSON object.
-/// Returns a new JSON object containing only the 8 account-specific keys.
-fn extract_account_fields(config: &serde_json::Value) -> serde_json::Value {
+/// Returns a new JSON object containing only the account-specific keys.
+fn extract_account_fields(config: &serde_json::Value, fields: &[String]) -> serde_json::Value {
     let Some(obj) = config.as_object() else {
         return serde_json::json!({});
     };
 
     let mut result = serde_json::Map::new();
-    for &field in ACCOUNT_SPECIFIC_FIELDS {
+    for field in fields {
         if let Some(value) = obj.get(field) {
             result.insert(field.to_string(), value.clone());
         }
@@ -35,41 +63,455 @@ fn extract_account_fields(config: &serde_json::Value) -> serde_json::Value {
 /// Overwrite account-specific keys in `config` with values from `profile`.
 /// Keys present in ACCOUNT_SPECIFIC_FIELDS but absent from `profile` are
 /// removed from `config` to prevent data leakage between accounts.
-fn patch_account_fields(config: &mut serde_json::Value, profile: &serde_json::Value) {
+fn patch_account_fields(
+    config: &mut serde_json::Value,
+    profile: &serde_json::Value,
+    fields: &[String],
+) {
     let (Some(config_obj), Some(profile_obj)) = (config.as_object_mut(), profile.as_object())
     else {
         return;
     };
 
-    for &field in ACCOUNT_SPECIFIC_FIELDS {
+    for field in fields {
         match profile_obj.get(field) {
             Some(value) => {
                 config_obj.insert(field.to_string(), value.clone());
             }
             None => {
-                config_obj.remove(field);
+                config_obj.shift_remove(field);
             }
         }
     }
 }
 
-/// Extract account UUID from a config JSON value
-fn get_account_uuid(config: &serde_json::Value) -> Option<String> {
+/// Overwrite account-specific keys in `config` with values from `profile`,
+/// leaving keys absent from `profile` as they are (non-destructive merge).
+fn merge_account_fields(
+    config: &mut serde_json::Value,
+    profile: &serde_json::Value,
+    fields: &[String],
+) {
+    let (Some(config_obj), Some(profile_obj)) = (config.as_object_mut(), profile.as_object())
+    else {
+        return;
+    };
+
+    for field in fields {
+        if let Some(value) = profile_obj.get(field) {
+            config_obj.insert(field.to_string(), value.clone());
+        }
+    }
+}
+
+/// Count account-specific keys whose value differs between two configs
+/// (added, changed or removed).
+fn count_changed_account_fields(
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+    fields: &[String],
+) -> usize {
+    fields
+        .iter()
+        .filter(|&field| before.get(field) != after.get(field))
+        .count()
+}
+
+/// What a switch did, for the post-switch confirmation line
+#[derive(Debug, Clone)]
+pub struct SwitchSummary {
+    pub slug: String,
+    pub email: Option<String>,
+    pub organization: Option<String>,
+    pub changed: usize,
+    /// Environment claude is launched with for this profile
+    pub env: Vec<(&'static str, String)>,
+    /// Set with --verify when the profile's email differs from the one last
+    /// seen for its account UUID
+    pub identity_warning: Option<String>,
+}
+
+impl std::fmt::Display for SwitchSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Switched to '{}' ({} @ {}), {} account field{} updated",
+            self.slug,
+            self.email.as_deref().unwrap_or("unknown"),
+            self.organization.as_deref().unwrap_or("unknown"),
+            self.changed,
+            if self.changed == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// How account fields from a profile are applied to ~/.claude.json
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SwitchMode {
+    /// Overwrite account fields and remove those absent from the profile
+    #[default]
+    Replace,
+    /// Overwrite account fields present in the profile, keep the others
+    Merge,
+    /// Replace the whole config with the profile's account fields, dropping
+    /// every portable setting
+    AccountOnly,
+}
+
+/// Key marking a profile as a full config snapshot (`save --all-fields`)
+const FULL_PROFILE_MARKER: &str = "claudectxFull";
+
+/// Profile key holding the gateway URL claude is launched with (as
+/// ANTHROPIC_BASE_URL). It is never written to ~/.claude.json.
+const BASE_URL_FIELD: &str = "baseUrl";
+
+/// Gateway URL stored in a profile (`save --base-url`)
+pub fn profile_base_url(profile: &serde_json::Value) -> Option<&str> {
+    profile.get(BASE_URL_FIELD).and_then(|url| url.as_str())
+}
+
+/// Profile key holding the config directory snapshot synced into
+/// $CLAUDE_CONFIG_DIR on switch (`save --config-dir`, `config_dirs` setting).
+/// Like baseUrl, it is never written to ~/.claude.json.
+const CONFIG_DIR_FIELD: &str = "configDir";
+
+/// Config directory snapshot stored in a profile (`save --config-dir`)
+pub fn profile_config_dir(profile: &serde_json::Value) -> Option<PathBuf> {
+    profile
+        .get(CONFIG_DIR_FIELD)
+        .and_then(|dir| dir.as_str())
+        .map(PathBuf::from)
+}
+
+/// Environment variables a profile sets for claude (ANTHROPIC_BASE_URL from
+/// its gateway URL)
+pub fn profile_env(profile: &serde_json::Value) -> Vec<(&'static str, String)> {
+    profile_base_url(profile)
+        .map(|url| ("ANTHROPIC_BASE_URL", url.to_string()))
+        .into_iter()
+        .collect()
+}
+
+/// Whether a profile is a full config snapshot rather than a slim profile
+pub fn is_full_profile(profile: &serde_json::Value) -> bool {
+    profile.get(FULL_PROFILE_MARKER) == Some(&serde_json::Value::Bool(true))
+}
+
+/// Top-level keys of a profile that aren't account-specific fields, sorted.
+/// A non-empty result means the profile was hand-edited or written by a
+/// version with a different field set.
+pub fn unexpected_keys(profile: &serde_json::Value, fields: &[String]) -> Vec<String> {
+    // Full snapshots hold the whole config on purpose
+    if is_full_profile(profile) {
+        return Vec::new();
+    }
+
+    let mut keys: Vec<String> = profile
+        .as_object()
+        .map(|obj| {
+            obj.keys()
+                .filter(|key| {
+                    !fields.contains(key)
+                        && key.as_str() != BASE_URL_FIELD
+                        && key.as_str() != CONFIG_DIR_FIELD
+                })
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+    keys.sort();
+    keys
+}
+
+/// Substrings (lowercase) marking a key as holding a secret
+const SECRET_KEY_MARKERS: &[&str] = &["apikey", "token", "secret"];
+
+/// Replace, recursively, every value whose key looks like a secret with "***"
+/// (`primaryApiKey` becomes `API key (…last4)`)
+pub fn redact_secrets(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(obj) => {
+            for (key, child) in obj.iter_mut() {
+                let key_lower = key.to_ascii_lowercase();
+                if key == API_KEY_FIELD && child.is_string() {
+                    *child = serde_json::Value::String(api_key_label(child.as_str().unwrap_or("")));
+                } else if SECRET_KEY_MARKERS
+                    .iter()
+                    .any(|marker| key_lower.contains(marker))
+                {
+                    *child = serde_json::Value::String("***".to_string());
+                } else {
+                    redact_secrets(child);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_secrets),
+        _ => {}
+    }
+}
+
+/// Options controlling how ~/.claude.json is patched by `switch_to_profile`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SwitchOptions {
+    /// How account fields are applied
+    pub mode: SwitchMode,
+    /// Write through ~/.claude.json when it is a symlink instead of refusing
+    pub follow_symlinks: bool,
+    /// Check the profile's email against the last one seen for its account UUID,
+    /// and re-read ~/.claude.json after writing to confirm the switch took effect
+    pub verify: bool,
+}
+
+/// oauthAccount keys a profile needs to be usable (switching, current-profile detection)
+const REQUIRED_ACCOUNT_KEYS: &[&str] = &["accountUuid", "emailAddress"];
+
+/// Extract a non-empty string field of oauthAccount from a config JSON value
+fn get_account_field(config: &serde_json::Value, key: &str) -> Option<String> {
     config
         .get("oauthAccount")?
-        .get("accountUuid")?
+        .get(key)?
         .as_str()
+        .filter(|value| !value.is_empty())
         .map(String::from)
 }
 
-/// Get the profiles directory path (~/.claudectx/)
-pub fn profiles_dir() -> PathBuf {
+/// Extract account UUID from a config JSON value
+fn get_account_uuid(config: &serde_json::Value) -> Option<String> {
+    get_account_field(config, "accountUuid")
+}
+
+/// Fingerprint of a config's API key, used to match API-key-only accounts
+/// without comparing the keys themselves
+fn api_key_fingerprint(config: &serde_json::Value) -> Option<u64> {
+    use std::hash::{Hash, Hasher};
+
+    let key = get_api_key(config)?;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    key.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// Whether a config identifies an account: an accountUuid, or for
+/// API-key-only accounts a primaryApiKey
+pub fn has_account_identity(config: &serde_json::Value) -> bool {
+    get_account_uuid(config).is_some() || api_key_fingerprint(config).is_some()
+}
+
+/// Whether two configs belong to the same account: same accountUuid, or when
+/// `expected` has none, the same API key
+fn same_account(expected: &serde_json::Value, actual: &serde_json::Value) -> bool {
+    match get_account_uuid(expected) {
+        Some(uuid) => get_account_uuid(actual) == Some(uuid),
+        None => {
+            api_key_fingerprint(expected).is_some()
+                && api_key_fingerprint(expected) == api_key_fingerprint(actual)
+        }
+    }
+}
+
+/// List the required oauthAccount keys missing from a profile.
+/// A non-empty result means the profile is stale. API-key-only profiles (no
+/// oauthAccount) have no required oauthAccount keys.
+pub fn missing_account_keys(config: &serde_json::Value) -> Vec<&'static str> {
+    if config.get("oauthAccount").is_none() && get_api_key(config).is_some() {
+        return Vec::new();
+    }
+
+    REQUIRED_ACCOUNT_KEYS
+        .iter()
+        .copied()
+        .filter(|key| get_account_field(config, key).is_none())
+        .collect()
+}
+
+/// Confirm a patched config carries the target profile's identity before it is
+/// written: `oauthAccount.accountUuid` (or, for API-key-only profiles,
+/// `primaryApiKey`) must be present and match the profile.
+fn validate_patched_config(
+    config: &serde_json::Value,
+    profile: &serde_json::Value,
+    slug: &str,
+) -> Result<()> {
+    if !has_account_identity(profile) {
+        return Err(Error::Parse(format!(
+            "Profile '{}' has no oauthAccount.accountUuid; refusing to switch",
+            slug
+        )));
+    }
+
+    if same_account(profile, config) {
+        Ok(())
+    } else {
+        Err(Error::Parse(format!(
+            "Patched config does not match profile '{}' accountUuid; config left unchanged",
+            slug
+        )))
+    }
+}
+
+/// Profiles directory override for the current invocation (--profiles-from)
+static PROFILES_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Read profiles from `dir` instead of ~/.claudectx/ for the rest of this run
+pub fn set_profiles_dir_override(dir: PathBuf) {
+    let _ = PROFILES_DIR_OVERRIDE.set(dir);
+}
+
+/// Get the claudectx state directory (~/.claudectx/), which holds settings and
+/// markers such as `.default` regardless of any profiles directory override
+pub fn claudectx_dir() -> PathBuf {
     home_dir().join(".claudectx")
 }
 
+/// Backups directory override for the current invocation (--backup-dir)
+static BACKUP_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Write backups to `dir` instead of ~/.claudectx/backups/ for the rest of this run
+pub fn set_backup_dir_override(dir: PathBuf) {
+    let _ = BACKUP_DIR_OVERRIDE.set(dir);
+}
+
+/// Get the directory every backup is written to (~/.claudectx/backups/ unless overridden)
+pub fn backups_dir() -> PathBuf {
+    BACKUP_DIR_OVERRIDE
+        .get()
+        .cloned()
+        .unwrap_or_else(|| claudectx_dir().join("backups"))
+}
+
+/// Backup files (`*.bak`) in the backups directory with their sizes in bytes,
+/// sorted by file name. The $CLAUDE_CONFIG_DIR backup is a directory and is
+/// left out: only the latest is kept, and it isn't a restorable config.
+pub fn list_backups() -> Result<Vec<(PathBuf, u64)>> {
+    let dir = backups_dir();
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(&dir).map_err(|e| Error::io("Failed to read backups directory", e))? {
+        let entry = entry.map_err(|e| Error::io("Failed to read backups directory", e))?;
+        let path = entry.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "bak") {
+            let size = entry
+                .metadata()
+                .map_err(|e| Error::io("Failed to read backup metadata", e))?
+                .len();
+            backups.push((path, size));
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+/// Creation time of a backup from its `-<unix secs>[.<n>].bak` suffix, as
+/// (seconds, sequence number) so backups from the same second keep their order
+fn backup_timestamp(path: &Path) -> Option<(u64, u64)> {
+    let stem = path.file_stem()?.to_string_lossy();
+    parse_stamp(stem.rsplit_once('-')?.1)
+}
+
+/// Parse a `<unix secs>[.<n>]` stamp written by `stamped_path`
+fn parse_stamp(stamp: &str) -> Option<(u64, u64)> {
+    match stamp.split_once('.') {
+        Some((secs, n)) => Some((secs.parse().ok()?, n.parse().ok()?)),
+        None => Some((stamp.parse().ok()?, 0)),
+    }
+}
+
+/// `<dir>/<stem>-<unix secs><ext>`, or `<stem>-<unix secs>.<n><ext>` with the
+/// first free `n` when a file was already stamped in the same second, so a
+/// quick second backup never overwrites the first
+fn stamped_path(dir: &Path, stem: &str, ext: &str) -> PathBuf {
+    let secs = now_secs();
+    let mut path = dir.join(format!("{}-{}{}", stem, secs, ext));
+    let mut n = 1;
+    while path.symlink_metadata().is_ok() {
+        path = dir.join(format!("{}-{}.{}{}", stem, secs, n, ext));
+        n += 1;
+    }
+    path
+}
+
+/// Every backup that `restore` could read, oldest first: the legacy
+/// ~/.claude.json.bak (if present), then the backups directory by timestamp
+pub fn backups_by_age() -> Result<Vec<PathBuf>> {
+    let mut backups: Vec<PathBuf> = list_backups()?.into_iter().map(|(path, _)| path).collect();
+    backups.sort_by_key(|path| backup_timestamp(path).unwrap_or_default());
+
+    let legacy = legacy_claude_config_backup_path();
+    if legacy.is_file() {
+        backups.insert(0, legacy);
+    }
+    Ok(backups)
+}
+
+/// Why the backup at `path` can't be restored as a Claude config (unreadable,
+/// not a JSON object, or without `oauthAccount`), or None if it can
+pub fn backup_problem(path: &Path) -> Option<String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => return Some(format!("unreadable: {}", e)),
+    };
+    match serde_json::from_str::<serde_json::Value>(strip_bom(&content)) {
+        Err(e) => Some(format!("invalid JSON: {}", e)),
+        Ok(value) if !value.is_object() => Some("not a JSON object".to_string()),
+        Ok(value) if value.get("oauthAccount").is_none() => {
+            Some("missing oauthAccount".to_string())
+        }
+        Ok(_) => None,
+    }
+}
+
+/// Timestamped path for a new backup of the file named `name`:
+/// `<backups_dir>/<name>-<unix secs>.bak` (see `stamped_path`). Creates the
+/// backups directory.
+fn new_backup_path(name: &str) -> Result<PathBuf> {
+    let dir = backups_dir();
+    fs::create_dir_all(&dir).map_err(|e| Error::io("Failed to create backups directory", e))?;
+    Ok(stamped_path(&dir, name, ".bak"))
+}
+
+/// Get the profiles directory path (~/.claudectx/ unless overridden)
+pub fn profiles_dir() -> PathBuf {
+    PROFILES_DIR_OVERRIDE
+        .get()
+        .cloned()
+        .unwrap_or_else(claudectx_dir)
+}
+
 /// Ensure the profiles directory exists
-pub fn ensure_profiles_dir() {
-    fs::create_dir_all(profiles_dir()).expect("Failed to create profiles directory");
+pub fn ensure_profiles_dir() -> Result<()> {
+    fs::create_dir_all(profiles_dir())
+        .map_err(|e| profile_write_error("Failed to create profiles directory", e))
+}
+
+/// Error for a failed write in the profiles directory. A permission failure
+/// (e.g. a read-only mount or home directory) or a file in the way of the
+/// directory names the directory instead of only the OS error.
+pub fn profile_write_error(context: &str, err: std::io::Error) -> Error {
+    dir_write_error(&profiles_dir(), context, err)
+}
+
+/// `profile_write_error` for claudectx's own state files (markers, usage,
+/// history, aliases, trash, settings), which live in ~/.claudectx even when the
+/// profiles directory is overridden
+pub fn state_write_error(context: &str, err: std::io::Error) -> Error {
+    dir_write_error(&claudectx_dir(), context, err)
+}
+
+fn dir_write_error(dir: &Path, context: &str, err: std::io::Error) -> Error {
+    match err.kind() {
+        std::io::ErrorKind::PermissionDenied
+        | std::io::ErrorKind::ReadOnlyFilesystem
+        | std::io::ErrorKind::NotADirectory
+        | std::io::ErrorKind::AlreadyExists => Error::NotWritable(format!(
+            "Profiles directory is not writable: {} ({})",
+            dir.display(),
+            err
+        )),
+        _ => Error::io(context, err),
+    }
 }
 
 /// Slugify profile name: lowercase, replace spaces/special chars with dashes
@@ -91,108 +533,1094 @@ pub fn slugify(name: &str) -> String {
         .join("-")
 }
 
-/// List all profile names (without .claude.json extension)
-pub fn list_profiles() -> Vec<String> {
+/// Get the configured profile filename suffix (".claude.json" by default)
+pub fn profile_extension() -> Result<String> {
+    Ok(load_settings()?.profile_extension().to_string())
+}
+
+/// List all profile names (without the profile extension)
+pub fn list_profiles() -> Result<Vec<String>> {
     let dir = profiles_dir();
     if !dir.exists() {
-        return vec![];
+        return Ok(vec![]);
     }
 
-    fs::read_dir(dir)
-        .expect("Failed to read profiles directory")
+    let extension = profile_extension()?;
+
+    let entries =
+        fs::read_dir(dir).map_err(|e| Error::io("Failed to read profiles directory", e))?;
+
+    Ok(entries
         .filter_map(|entry| {
             let entry = entry.ok()?;
             let name = entry.file_name().to_string_lossy().to_string();
-            // Exclude .bak files from listing
-            if name.ends_with(".bak") {
+            // Exclude .bak files and claudectx's own dotfiles from listing
+            if name.ends_with(".bak") || name.starts_with('.') {
                 return None;
             }
-            name.strip_suffix(".claude.json").map(String::from)
+            name.strip_suffix(extension.as_str())
+                .filter(|stem| !stem.is_empty())
+                .map(String::from)
         })
+        .collect())
+}
+
+/// Whether a profile name argument contains `*` or `?` wildcards
+pub fn is_glob_pattern(name: &str) -> bool {
+    name.contains(['*', '?'])
+}
+
+/// Match `name` against a pattern where `*` matches any run of characters
+/// and `?` matches exactly one
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` seen, and the name position it matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, n));
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_n)) => {
+                    p = star_p;
+                    n = star_n + 1;
+                    backtrack = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Whether written JSON has its object keys sorted (--sort-keys)
+static SORT_KEYS: OnceLock<bool> = OnceLock::new();
+
+/// Sort object keys in ~/.claude.json and profiles for the rest of this run
+pub fn set_sort_keys() {
+    let _ = SORT_KEYS.set(true);
+}
+
+/// Pretty-print `value` for writing to ~/.claude.json or a profile, ending
+/// with a newline like any POSIX text file (`context` labels a failure).
+/// Keys keep their source order unless --sort-keys is in effect.
+fn to_json_file(value: &serde_json::Value, context: &str) -> Result<String> {
+    let mut json = if SORT_KEYS.get().copied().unwrap_or(false) {
+        let mut sorted = value.clone();
+        sorted.sort_all_objects();
+        serde_json::to_string_pretty(&sorted)
+    } else {
+        serde_json::to_string_pretty(value)
+    }
+    .map_err(|e| Error::parse(context, e))?;
+    json.push('\n');
+    Ok(json)
+}
+
+/// Get the path to a profile file
+pub fn get_profile_path(name: &str) -> Result<PathBuf> {
+    let slug = slugify(name);
+    // slugify already maps separators and dots to '-'; this guards the
+    // invariant, since an escaping path would be written outside profiles_dir()
+    if slug.is_empty() || slug.contains(['/', '\\']) || slug.contains("..") {
+        return Err(Error::Other(format!(
+            "Invalid profile name '{}': it needs at least one letter or digit",
+            name
+        )));
+    }
+    Ok(profiles_dir().join(format!("{}{}", slug, profile_extension()?)))
+}
+
+/// Read and parse a saved profile
+pub fn read_profile(name: &str) -> Result<serde_json::Value> {
+    let path = get_profile_path(name)?;
+    let content = fs::read_to_string(&path).map_err(|e| Error::io("Failed to read profile", e))?;
+    serde_json::from_str(strip_bom(&content))
+        .map_err(|e| Error::parse("Failed to parse profile", e))
+}
+
+/// The keys of a profile or config that identify and describe its account.
+/// Deserializing into this skips every other key (e.g. large cache blobs)
+/// without building a `serde_json::Value` for it.
+#[derive(Deserialize)]
+struct AccountSummary {
+    #[serde(rename = "oauthAccount")]
+    oauth_account: Option<serde_json::Value>,
+    #[serde(rename = "primaryApiKey")]
+    primary_api_key: Option<serde_json::Value>,
+    #[serde(rename = "claudectxFull")]
+    full: Option<serde_json::Value>,
+}
+
+/// Parse only the account keys of a JSON config, returned as a small object
+/// that the account helpers (`describe_account`, `same_account`, ...) accept
+fn parse_account_summary(content: &str) -> serde_json::Result<serde_json::Value> {
+    let summary: AccountSummary = serde_json::from_str(strip_bom(content))?;
+    let mut obj = serde_json::Map::new();
+    for (key, value) in [
+        ("oauthAccount", summary.oauth_account),
+        (API_KEY_FIELD, summary.primary_api_key),
+        (FULL_PROFILE_MARKER, summary.full),
+    ] {
+        if let Some(value) = value {
+            obj.insert(key.to_string(), value);
+        }
+    }
+    Ok(serde_json::Value::Object(obj))
+}
+
+/// Read only the account keys of a saved profile (see `parse_account_summary`).
+/// Cheaper than `read_profile` for listing and matching.
+pub fn read_profile_summary(name: &str) -> Result<serde_json::Value> {
+    let path = get_profile_path(name)?;
+    let content = fs::read_to_string(&path).map_err(|e| Error::io("Failed to read profile", e))?;
+    parse_account_summary(&content).map_err(|e| Error::parse("Failed to parse profile", e))
+}
+
+/// Options controlling how `save_profile` snapshots ~/.claude.json
+#[derive(Debug, Clone, Default)]
+pub struct SaveOptions {
+    /// Keep the whole config instead of the account-specific fields
+    pub all_fields: bool,
+    /// Overlay the extracted fields onto an existing profile, keeping its other keys
+    pub merge: bool,
+    /// Replacement for oauthAccount.displayName
+    pub display_name: Option<String>,
+    /// Replacement for oauthAccount.emailAddress
+    pub email: Option<String>,
+    /// Replacement for oauthAccount.organizationName
+    pub organization_name: Option<String>,
+    /// Gateway URL to launch claude with (ANTHROPIC_BASE_URL)
+    pub base_url: Option<String>,
+    /// Config directory snapshot to sync into $CLAUDE_CONFIG_DIR on switch
+    pub config_dir: Option<PathBuf>,
+}
+
+impl SaveOptions {
+    /// oauthAccount keys to overwrite, with their new values
+    fn account_overrides(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("displayName", &self.display_name),
+            ("emailAddress", &self.email),
+            ("organizationName", &self.organization_name),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.as_deref().map(|value| (key, value)))
         .collect()
+    }
+}
+
+/// Save current ~/.claude.json as a slim profile (account-specific fields only),
+/// or as a full snapshot with `all_fields`. With `merge`, keys of an existing
+/// profile that weren't extracted (e.g. hand-added ones) are kept.
+/// ~/.claude.json stays a regular file, untouched.
+pub fn save_profile(name: &str, options: &SaveOptions) -> Result<()> {
+    let source = claude_config_path();
+    if !source.exists() {
+        return Err(missing_config_error(&source));
+    }
+
+    ensure_not_alias(name)?;
+    ensure_profiles_dir()?;
+    let dest = get_profile_path(name)?;
+
+    let content = fs::read_to_string(&source).map_err(|_| missing_config_error(&source))?;
+
+    let config: serde_json::Value = serde_json::from_str(strip_bom(&content))
+        .map_err(|e| Error::parse("Failed to parse Claude config JSON", e))?;
+
+    // A logged-out config (no oauthAccount, or an empty accountUuid) would
+    // only produce a dead profile
+    if !has_account_identity(&config) {
+        return Err(Error::Parse(format!(
+            "Current config at {:?} is not logged in - run 'claudectx login' first",
+            source
+        )));
+    }
+
+    let mut slim = if options.all_fields {
+        let mut full = config;
+        if let Some(obj) = full.as_object_mut() {
+            obj.insert(
+                FULL_PROFILE_MARKER.to_string(),
+                serde_json::Value::Bool(true),
+            );
+        }
+        full
+    } else {
+        extract_account_fields(&config, &account_fields()?)
+    };
+
+    let overrides = options.account_overrides();
+    if !overrides.is_empty() {
+        let Some(account) = slim
+            .get_mut("oauthAccount")
+            .and_then(serde_json::Value::as_object_mut)
+        else {
+            return Err(Error::Other(
+                "--display-name, --email and --org need an oauthAccount to override".to_string(),
+            ));
+        };
+        for (key, value) in overrides {
+            account.insert(key.to_string(), serde_json::Value::from(value));
+        }
+    }
+    if let (Some(url), Some(obj)) = (&options.base_url, slim.as_object_mut()) {
+        obj.insert(
+            BASE_URL_FIELD.to_string(),
+            serde_json::Value::from(url.as_str()),
+        );
+    }
+    if let (Some(dir), Some(obj)) = (&options.config_dir, slim.as_object_mut()) {
+        if !dir.is_dir() {
+            return Err(Error::Other(format!(
+                "Config directory {:?} does not exist",
+                dir
+            )));
+        }
+        let dir = dir
+            .canonicalize()
+            .map_err(|e| Error::io("Failed to resolve config directory", e))?;
+        obj.insert(
+            CONFIG_DIR_FIELD.to_string(),
+            serde_json::Value::from(dir.to_string_lossy().as_ref()),
+        );
+    }
+
+    // A profile without an account UUID (or API key) can't be switched to or
+    // detected as current, e.g. an API key left out of extra_account_fields
+    if !has_account_identity(&slim) {
+        return Err(Error::Parse(format!(
+            "Claude config at {:?} has no oauthAccount.accountUuid - log in to Claude Code before saving a profile",
+            source
+        )));
+    }
+
+    let slim = if options.merge && dest.exists() {
+        let mut existing = read_profile(name)?;
+        if let (Some(existing_obj), Some(slim_obj)) = (existing.as_object_mut(), slim.as_object()) {
+            for (key, value) in slim_obj {
+                existing_obj.insert(key.clone(), value.clone());
+            }
+        }
+        existing
+    } else {
+        slim
+    };
+
+    let slim_json = to_json_file(&slim, "Failed to serialize slim profile")?;
+
+    fs::write(&dest, slim_json).map_err(|e| profile_write_error("Failed to save profile", e))?;
+    set_current_marker(&slugify(name))
+}
+
+/// Result of a bulk import
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    /// Slugs of profiles written
+    pub imported: Vec<String>,
+    /// Skipped entries with the reason
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Collect every regular file under `dir`, recursively, sorted by path.
+/// Symlinks are skipped: they come from an untrusted archive and could point
+/// anywhere on the host.
+fn collect_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let entries = fs::read_dir(dir).map_err(|e| Error::io("Failed to read archive contents", e))?;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let file_type = entry
+            .file_type()
+            .map_err(|e| Error::io("Failed to read archive contents", e))?;
+        if file_type.is_dir() {
+            files.extend(collect_files(&entry.path())?);
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Profile name for an archive entry: the file name without the profile
+/// extension (or a plain ".json"), or None if it isn't a JSON file
+fn archive_entry_name(file_name: &str, extension: &str) -> Option<String> {
+    file_name
+        .strip_suffix(extension)
+        .or_else(|| file_name.strip_suffix(".claude.json"))
+        .or_else(|| file_name.strip_suffix(".json"))
+        .filter(|stem| !stem.is_empty() && !file_name.ends_with(".bak"))
+        .map(String::from)
+}
+
+/// Validate an exported profile for import: it must be a JSON object with
+/// `oauthAccount.accountUuid`. Returns what to store (slimmed to `fields`
+/// unless it is a full profile), or why it can't be imported.
+fn importable_profile(
+    content: &str,
+    fields: &[String],
+) -> std::result::Result<serde_json::Value, &'static str> {
+    let profile = serde_json::from_str::<serde_json::Value>(strip_bom(content))
+        .map_err(|_| "not a JSON object")?;
+    importable_value(profile, fields)
+}
+
+/// `importable_profile` for an already parsed profile
+fn importable_value(
+    profile: serde_json::Value,
+    fields: &[String],
+) -> std::result::Result<serde_json::Value, &'static str> {
+    if !profile.is_object() {
+        return Err("not a JSON object");
+    }
+    if !has_account_identity(&profile) {
+        return Err("missing oauthAccount.accountUuid");
+    }
+
+    if is_full_profile(&profile) {
+        return Ok(profile);
+    }
+    let mut slim = extract_account_fields(&profile, fields);
+    if let Some(obj) = slim.as_object_mut() {
+        for key in [BASE_URL_FIELD, CONFIG_DIR_FIELD] {
+            if let Some(value) = profile.get(key) {
+                obj.insert(key.to_string(), value.clone());
+            }
+        }
+    }
+    Ok(slim)
+}
+
+fn write_imported_profile(slug: &str, profile: &serde_json::Value) -> Result<()> {
+    let slim_json = to_json_file(profile, "Failed to serialize slim profile")?;
+    fs::write(get_profile_path(slug)?, slim_json)
+        .map_err(|e| profile_write_error("Failed to save profile", e))
+}
+
+/// Profile name for an imported file: its name without the profile extension
+/// (or ".json"), as `export` writes it
+pub fn import_file_name(file: &Path) -> Result<String> {
+    let file_name = file.file_name().unwrap_or_default().to_string_lossy();
+    archive_entry_name(&file_name, &profile_extension()?).ok_or_else(|| {
+        Error::Other(format!(
+            "Can't derive a profile name from {:?}; pass --name",
+            file
+        ))
+    })
+}
+
+/// Import a single exported profile file as `name`, replacing any existing
+/// profile of that name. Returns the profile slug.
+pub fn import_profile(file: &Path, name: &str) -> Result<String> {
+    let content = fs::read_to_string(file)
+        .map_err(|e| Error::io(&format!("Failed to read {:?}", file), e))?;
+    let profile = importable_profile(&content, &account_fields()?)
+        .map_err(|reason| Error::Other(format!("Can't import {:?}: {}", file, reason)))?;
+
+    ensure_not_alias(name)?;
+    ensure_profiles_dir()?;
+    let slug = slugify(name);
+    write_imported_profile(&slug, &profile)?;
+    Ok(slug)
+}
+
+/// The portable (non-account) settings of the live ~/.claude.json, bundled
+/// with a profile by `export --full`
+pub fn portable_settings() -> Result<serde_json::Value> {
+    let mut portable = read_config_or_empty(&claude_config_path());
+    patch_account_fields(&mut portable, &serde_json::json!({}), &account_fields()?);
+    Ok(portable)
+}
+
+/// Whether `value` is a placeholder left by `redact_secrets`
+fn is_redacted(value: &serde_json::Value) -> bool {
+    value
+        .as_str()
+        .is_some_and(|s| s == "***" || s.starts_with("API key ("))
+}
+
+/// Put the `live` value back wherever `redact_secrets` left a placeholder in
+/// `exported`, or drop the key when the live config has none, so a redacted
+/// export never overwrites a real secret with "***"
+fn unredact_from(exported: &mut serde_json::Value, live: Option<&serde_json::Value>) {
+    let Some(obj) = exported.as_object_mut() else {
+        return;
+    };
+    let keys: Vec<String> = obj.keys().cloned().collect();
+    for key in keys {
+        let live_child = live.and_then(|live| live.get(&key));
+        let Some(child) = obj.get_mut(&key) else {
+            continue;
+        };
+        if !is_redacted(child) {
+            unredact_from(child, live_child);
+        } else if let Some(live_child) = live_child {
+            *child = live_child.clone();
+        } else {
+            obj.shift_remove(&key);
+        }
+    }
+}
+
+/// Import an `export --full` bundle (`{"account": ..., "portable": ...}`) as
+/// profile `name`. With `restore_portable`, the portable settings of
+/// ~/.claude.json are also replaced by the bundled ones (after a backup), its
+/// account fields being kept. Returns the profile slug.
+pub fn import_full_export(file: &Path, name: &str, restore_portable: bool) -> Result<String> {
+    let content = fs::read_to_string(file)
+        .map_err(|e| Error::io(&format!("Failed to read {:?}", file), e))?;
+    let bundle: serde_json::Value = serde_json::from_str(strip_bom(&content))
+        .map_err(|e| Error::parse(&format!("Failed to parse {:?}", file), e))?;
+    let (Some(account), Some(portable)) = (bundle.get("account"), bundle.get("portable")) else {
+        return Err(Error::Other(format!(
+            "Can't import {:?}: not an 'export --full' file (expected \"account\" and \"portable\")",
+            file
+        )));
+    };
+
+    let fields = account_fields()?;
+    let profile = importable_value(account.clone(), &fields)
+        .map_err(|reason| Error::Other(format!("Can't import {:?}: {}", file, reason)))?;
+    ensure_not_alias(name)?;
+    ensure_profiles_dir()?;
+    let slug = slugify(name);
+    write_imported_profile(&slug, &profile)?;
+
+    if restore_portable {
+        let config_path = claude_config_path();
+        let live = read_config_or_empty(&config_path);
+        if config_path.exists() {
+            fs::copy(&config_path, claude_config_backup_path()?)
+                .map_err(|e| Error::io("Failed to back up Claude config", e))?;
+        }
+        let mut config = portable.clone();
+        unredact_from(&mut config, Some(&live));
+        patch_account_fields(&mut config, &live, &fields);
+        let output = to_json_file(&config, "Failed to serialize config")?;
+        fs::write(&config_path, output).map_err(|e| Error::io("Failed to write config", e))?;
+    }
+    Ok(slug)
+}
+
+/// Import every profile from a .tar.gz archive into ~/.claudectx/.
+/// Each entry must be a JSON object with `oauthAccount.accountUuid`; it is
+/// slimmed to account-specific fields before being written. Existing profiles
+/// are skipped unless `overwrite` is set, and so are names taken by an alias.
+pub fn import_all(archive: &Path, overwrite: bool) -> Result<ImportSummary> {
+    let staging = StagingDir::new()?;
+    extract_archive(archive, staging.path())?;
+
+    ensure_profiles_dir()?;
+    let extension = profile_extension()?;
+    let fields = account_fields()?;
+    let aliases = load_aliases()?;
+    let mut summary = ImportSummary::default();
+
+    for path in collect_files(staging.path())? {
+        let file_name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let Some(name) = archive_entry_name(&file_name, &extension) else {
+            continue;
+        };
+        let slug = slugify(&name);
+
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        let profile = match importable_profile(&content, &fields) {
+            Ok(profile) => profile,
+            Err(reason) => {
+                summary.skipped.push((slug, reason.to_string()));
+                continue;
+            }
+        };
+        if profile_exists(&slug)? && !overwrite {
+            summary
+                .skipped
+                .push((slug, "profile already exists".to_string()));
+            continue;
+        }
+        if let Some(target) = aliases.get(&slug) {
+            let reason = format!("name is an alias of '{}'", target);
+            summary.skipped.push((slug, reason));
+            continue;
+        }
+
+        write_imported_profile(&slug, &profile)?;
+        summary.imported.push(slug);
+    }
+
+    Ok(summary)
+}
+
+/// File listing the bundled profiles in an export-all archive
+pub const ARCHIVE_MANIFEST: &str = "MANIFEST.tsv";
+
+/// Bundle every profile (no .bak files) into a .tar.gz archive, along with a
+/// tab-separated manifest of profile names and account emails.
+/// Returns the exported profile names.
+pub fn export_all(out: &Path) -> Result<Vec<String>> {
+    let staging = StagingDir::new()?;
+    let mut profiles = list_profiles()?;
+    profiles.sort();
+
+    let mut manifest = String::from("name\temail\n");
+    for name in &profiles {
+        let path = get_profile_path(name)?;
+        let file_name = path.file_name().unwrap_or_default();
+        fs::copy(&path, staging.path().join(file_name))
+            .map_err(|e| Error::io("Failed to stage profile", e))?;
+
+        let email = read_profile_summary(name)
+            .ok()
+            .and_then(|profile| get_account_field(&profile, "emailAddress"))
+            .unwrap_or_default();
+        manifest.push_str(&format!("{}\t{}\n", name, email));
+    }
+
+    fs::write(staging.path().join(ARCHIVE_MANIFEST), manifest)
+        .map_err(|e| Error::io("Failed to write manifest", e))?;
+    create_archive(staging.path(), out)?;
+
+    Ok(profiles)
+}
+
+/// Delete a profile (and the current/default markers naming it)
+pub fn delete_profile(name: &str) -> Result<()> {
+    let path = get_profile_path(name)?;
+    fs::remove_file(&path).map_err(|e| Error::io("Failed to delete profile", e))?;
+    clear_markers_for(&slugify(name))
+}
+
+/// Remove the `.current` and `.default` markers if they name `slug`, and the
+/// aliases pointing at it, so a deleted profile isn't reported afterwards
+fn clear_markers_for(slug: &str) -> Result<()> {
+    remove_aliases_to(slug)?;
+    for (path, marked) in [
+        (current_marker_path(), read_current_marker()),
+        (default_profile_path(), get_default_profile()),
+    ] {
+        if marked.as_deref() == Some(slug) {
+            fs::remove_file(&path).map_err(|e| Error::io("Failed to clear profile marker", e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Rename a profile, keeping the default and current markers, its aliases
+/// and usage history pointing at it
+pub fn rename_profile(old: &str, new: &str) -> Result<()> {
+    let (old_slug, new_slug) = (slugify(old), slugify(new));
+    let from = get_profile_path(old)?;
+    if !from.exists() {
+        return Err(Error::profile_not_found(&old_slug));
+    }
+    let to = get_profile_path(new)?;
+    if to.exists() {
+        return Err(Error::Other(format!(
+            "Profile '{}' already exists",
+            new_slug
+        )));
+    }
+    ensure_not_alias(new)?;
+
+    fs::rename(&from, &to).map_err(|e| profile_write_error("Failed to rename profile", e))?;
+
+    if get_default_profile().as_deref() == Some(old_slug.as_str()) {
+        set_default_profile(&new_slug)?;
+    }
+    if read_current_marker().as_deref() == Some(old_slug.as_str()) {
+        set_current_marker(&new_slug)?;
+    }
+    retarget_aliases(&old_slug, &new_slug)?;
+    rename_usage(&old_slug, &new_slug)
+}
+
+/// Copy profile `source` to a new profile `dest`. With `from_current`, `dest`
+/// is instead saved from the live ~/.claude.json: its account fields come from
+/// the current config and only claudectx's own settings of `source` (the
+/// gateway URL) are carried over.
+pub fn copy_profile(source: &str, dest: &str, from_current: bool) -> Result<()> {
+    let from = get_profile_path(source)?;
+    if !from.exists() {
+        return Err(Error::profile_not_found(&slugify(source)));
+    }
+    let to = get_profile_path(dest)?;
+    if to.exists() {
+        return Err(Error::Other(format!(
+            "Profile '{}' already exists",
+            slugify(dest)
+        )));
+    }
+    ensure_not_alias(dest)?;
+
+    if !from_current {
+        fs::copy(&from, &to).map_err(|e| profile_write_error("Failed to copy profile", e))?;
+        return Ok(());
+    }
+
+    let template = read_profile(source)?;
+    save_profile(
+        dest,
+        &SaveOptions {
+            base_url: profile_base_url(&template).map(String::from),
+            config_dir: profile_config_dir(&template),
+            ..SaveOptions::default()
+        },
+    )
+}
+
+/// Re-extract the account fields of profile `name` from the live
+/// ~/.claude.json, keeping its other keys (a full profile is re-snapshotted).
+/// Refuses when the live config belongs to another account, so one account's
+/// fields never end up in another's profile.
+pub fn refresh_profile(name: &str) -> Result<()> {
+    let slug = slugify(name);
+    if !profile_exists(name)? {
+        return Err(Error::profile_not_found(&slug));
+    }
+
+    let profile = read_profile(name)?;
+    let config = read_claude_config()?;
+    if !same_account(&profile, &config) {
+        return Err(Error::Other(format!(
+            "The current account ({}) is not the one saved in profile '{}'; switch to it before refreshing",
+            describe_account(&config)?,
+            slug
+        )));
+    }
+
+    let full = is_full_profile(&profile);
+    save_profile(
+        name,
+        &SaveOptions {
+            all_fields: full,
+            merge: !full,
+            base_url: profile_base_url(&profile).map(String::from),
+            config_dir: profile_config_dir(&profile),
+            ..SaveOptions::default()
+        },
+    )
+}
+
+/// Get the trash directory for deleted profiles (~/.claudectx/trash/)
+pub fn trash_dir() -> PathBuf {
+    claudectx_dir().join("trash")
+}
+
+/// Move a profile to `<trash_dir>/<name>-<unix secs><ext>` instead of
+/// removing it, so `undelete` can bring it back. Returns the trashed path.
+pub fn trash_profile(name: &str) -> Result<PathBuf> {
+    let path = get_profile_path(name)?;
+    let dir = trash_dir();
+    fs::create_dir_all(&dir)
+        .map_err(|e| state_write_error("Failed to create trash directory", e))?;
+
+    let trashed = stamped_path(&dir, &slugify(name), &profile_extension()?);
+    fs::rename(&path, &trashed)
+        .map_err(|e| state_write_error("Failed to move profile to trash", e))?;
+    clear_markers_for(&slugify(name))?;
+    Ok(trashed)
+}
+
+/// Most recently trashed copy of a profile, if any
+fn latest_trashed_profile(name: &str) -> Result<Option<PathBuf>> {
+    let dir = trash_dir();
+    if !dir.exists() {
+        return Ok(None);
+    }
+
+    let slug = slugify(name);
+    let ext = profile_extension()?;
+    let mut latest: Option<((u64, u64), PathBuf)> = None;
+    for entry in fs::read_dir(&dir).map_err(|e| Error::io("Failed to read trash directory", e))? {
+        let entry = entry.map_err(|e| Error::io("Failed to read trash directory", e))?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let stamp = file_name
+            .strip_suffix(ext.as_str())
+            .and_then(|stem| stem.rsplit_once('-'))
+            .filter(|(trashed_slug, _)| *trashed_slug == slug)
+            .and_then(|(_, stamp)| parse_stamp(stamp));
+        if let Some(stamp) = stamp {
+            if latest.as_ref().is_none_or(|(best, _)| stamp > *best) {
+                latest = Some((stamp, entry.path()));
+            }
+        }
+    }
+    Ok(latest.map(|(_, path)| path))
+}
+
+/// Restore the most recently trashed copy of a profile
+pub fn undelete_profile(name: &str) -> Result<()> {
+    let slug = slugify(name);
+    let trashed = latest_trashed_profile(name)?
+        .ok_or_else(|| Error::ProfileNotFound(format!("No trashed copy of profile '{}'", slug)))?;
+
+    let path = get_profile_path(name)?;
+    if path.exists() {
+        return Err(Error::Other(format!("Profile '{}' already exists", slug)));
+    }
+    ensure_not_alias(name)?;
+
+    ensure_profiles_dir()?;
+    fs::rename(&trashed, &path).map_err(|e| profile_write_error("Failed to restore profile", e))
+}
+
+/// Check if a profile exists
+pub fn profile_exists(name: &str) -> Result<bool> {
+    Ok(get_profile_path(name)?.exists())
+}
+
+/// Switch to a profile by patching ~/.claude.json in-place.
+/// A slim profile only replaces the account-specific fields (see
+/// `account_fields`, including `extra_account_fields`) and keeps every other
+/// setting; a full profile (or `SwitchMode::AccountOnly`) replaces the whole
+/// config after backing it up. The profile's own keys (`baseUrl`, `configDir`)
+/// are never written to the config; with the `config_dirs` setting on,
+/// `configDir` is first synced into $CLAUDE_CONFIG_DIR.
+/// The profile file is read-only and never modified.
+pub fn switch_to_profile(name: &str, options: &SwitchOptions) -> Result<SwitchSummary> {
+    let profile_path = get_profile_path(name)?;
+    if !profile_path.exists() {
+        return Err(Error::profile_not_found(&slugify(name)));
+    }
+
+    // Read the profile
+    let profile_content = fs::read_to_string(&profile_path)
+        .map_err(|e| Error::io("Failed to read target profile", e))?;
+    let profile: serde_json::Value = serde_json::from_str(strip_bom(&profile_content))
+        .map_err(|e| Error::parse("Failed to parse target profile", e))?;
+
+    // Remember who the live config belonged to before it is patched
+    let live = read_config_or_empty(&claude_config_path());
+    if let (Some(uuid), Some(email)) = (
+        get_account_uuid(&live),
+        get_account_field(&live, "emailAddress"),
+    ) {
+        record_seen_account(&uuid, &email)?;
+    }
+    let identity_warning = if options.verify {
+        identity_mismatch(&slugify(name), &profile, &load_seen_accounts()?)
+    } else {
+        None
+    };
+
+    if load_settings()?.config_dirs == Some(true) {
+        if let Some(snapshot) = profile_config_dir(&profile) {
+            // Only touch $CLAUDE_CONFIG_DIR once the switch itself can't fail
+            ensure_config_writable(&claude_config_path(), options)?;
+            let patched = patched_config(&live, &profile, options.mode, &account_fields()?);
+            validate_patched_config(&patched, &profile, &slugify(name))?;
+            sync_config_dir(&slugify(name), &snapshot)?;
+        }
+    }
+    let mut summary = apply_profile(&slugify(name), &profile, options)?;
+    if options.verify {
+        let written = fs::read_to_string(claude_config_path())
+            .map_err(|e| Error::io("Failed to re-read Claude config", e))?;
+        let written: serde_json::Value = serde_json::from_str(strip_bom(&written))
+            .map_err(|e| Error::parse("Failed to parse Claude config after switching", e))?;
+        check_switch_applied(&summary.slug, &profile, &written)?;
+    }
+    summary.identity_warning = identity_warning;
+    set_current_marker(&summary.slug)?;
+    record_usage(&summary.slug)?;
+    record_history(&summary.slug)?;
+    Ok(summary)
+}
+
+/// Prefix of the backup taken of $CLAUDE_CONFIG_DIR before a sync
+const CONFIG_DIR_BACKUP_PREFIX: &str = "claude-config-dir";
+
+/// Copy a profile's config directory snapshot into $CLAUDE_CONFIG_DIR, after
+/// backing the directory up (only the latest such backup is kept). Files are
+/// copied over, not mirrored: files only present in $CLAUDE_CONFIG_DIR are kept. The snapshot's own Claude config
+/// file is skipped, since the account fields are patched in afterwards.
+fn sync_config_dir(slug: &str, snapshot: &Path) -> Result<()> {
+    let Some(target) = std::env::var_os("CLAUDE_CONFIG_DIR").filter(|dir| !dir.is_empty()) else {
+        return Err(Error::Other(format!(
+            "Profile '{}' has a config directory but $CLAUDE_CONFIG_DIR is not set",
+            slug
+        )));
+    };
+    let target = PathBuf::from(target);
+    if !snapshot.is_dir() {
+        return Err(Error::Other(format!(
+            "Config directory {:?} of profile '{}' does not exist",
+            snapshot, slug
+        )));
+    }
+    // Syncing a directory onto itself would only back it up
+    if target.canonicalize().ok() == snapshot.canonicalize().ok() {
+        return Ok(());
+    }
+
+    if target.exists() {
+        let backup = new_backup_path(CONFIG_DIR_BACKUP_PREFIX)?;
+        copy_dir(&target, &backup, None)
+            .map_err(|e| Error::io("Failed to back up $CLAUDE_CONFIG_DIR", e))?;
+        remove_older_config_dir_backups(&backup)?;
+    }
+    let config_name = claude_config_path().file_name().map(|name| name.to_owned());
+    copy_dir(snapshot, &target, config_name.as_deref())
+        .map_err(|e| Error::io("Failed to sync config directory", e))
+}
+
+/// Remove every $CLAUDE_CONFIG_DIR backup but `latest`, since each one is a
+/// full copy of the directory
+fn remove_older_config_dir_backups(latest: &Path) -> Result<()> {
+    let entries = fs::read_dir(backups_dir())
+        .map_err(|e| Error::io("Failed to read backups directory", e))?;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let is_config_dir_backup = name.starts_with(&format!("{}-", CONFIG_DIR_BACKUP_PREFIX))
+            && name.ends_with(".bak")
+            && entry.file_type().is_ok_and(|t| t.is_dir());
+        if is_config_dir_backup && path != latest {
+            fs::remove_dir_all(&path)
+                .map_err(|e| Error::io("Failed to remove old config directory backup", e))?;
+        }
+    }
+    Ok(())
 }
 
-/// Get the path to a profile file
-pub fn get_profile_path(name: &str) -> PathBuf {
-    let slug = slugify(name);
-    profiles_dir().join(format!("{}.claude.json", slug))
+/// Recursively copy `from` into `to`, creating it, and skipping the top-level
+/// entry named `skip`
+fn copy_dir(from: &Path, to: &Path, skip: Option<&std::ffi::OsStr>) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        if Some(entry.file_name().as_os_str()) == skip {
+            continue;
+        }
+        let dest = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &dest, None)?;
+        } else {
+            fs::copy(entry.path(), dest)?;
+        }
+    }
+    Ok(())
 }
 
-/// Save current ~/.claude.json as a slim profile (account-specific fields only).
-/// ~/.claude.json stays a regular file, untouched.
-pub fn save_profile(name: &str) {
-    let source = claude_config_path();
-    if !source.exists() {
-        panic!(
-            "Failed to read Claude config at {:?} - is Claude Code installed?",
-            source
-        );
+/// Error unless the re-read config belongs to the profile's account, i.e. the
+/// switch was not overwritten by another process
+fn check_switch_applied(
+    slug: &str,
+    profile: &serde_json::Value,
+    written: &serde_json::Value,
+) -> Result<()> {
+    if same_account(profile, written) {
+        return Ok(());
     }
 
-    ensure_profiles_dir();
-    let dest = get_profile_path(name);
+    let found = get_account_uuid(written).unwrap_or_else(|| "no account".to_string());
+    Err(Error::Io(format!(
+        "Switch to '{}' did not take effect: {} holds {} after writing",
+        slug,
+        claude_config_path().display(),
+        found
+    )))
+}
 
-    let content = fs::read_to_string(&source).unwrap_or_else(|_| {
-        panic!(
-            "Failed to read Claude config at {:?} - is Claude Code installed?",
-            source
-        )
-    });
+/// Warning when a profile's email differs from the email last seen in the
+/// live config for the same account UUID (e.g. a re-provisioned account)
+fn identity_mismatch(
+    slug: &str,
+    profile: &serde_json::Value,
+    seen: &SeenAccounts,
+) -> Option<String> {
+    let uuid = get_account_uuid(profile)?;
+    let email = get_account_field(profile, "emailAddress")?;
+    let last_seen = seen.get(&uuid)?;
+    if *last_seen == email {
+        return None;
+    }
 
-    let config: serde_json::Value =
-        serde_json::from_str(&content).expect("Failed to parse Claude config JSON");
+    Some(format!(
+        "Profile '{}' has email {} but account {} was last seen as {}; \
+         the profile may be stale (re-save it with 'claudectx save {}')",
+        slug, email, uuid, last_seen, slug
+    ))
+}
 
-    let slim = extract_account_fields(&config);
-    let slim_json = serde_json::to_string_pretty(&slim).expect("Failed to serialize slim profile");
+/// Read a JSON config, or an empty object if it is missing or invalid
+fn read_config_or_empty(path: &Path) -> serde_json::Value {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(strip_bom(&content)).ok())
+        .unwrap_or_else(|| serde_json::json!({}))
+}
 
-    fs::write(&dest, slim_json).expect("Failed to save profile");
+/// Switch to a one-off config (e.g. piped on stdin) without saving it as a
+/// profile. Only its account-specific fields are applied, and nothing is
+/// written to ~/.claudectx.
+pub fn switch_to_config(
+    label: &str,
+    config: &serde_json::Value,
+    options: &SwitchOptions,
+) -> Result<SwitchSummary> {
+    apply_profile(
+        label,
+        &extract_account_fields(config, &account_fields()?),
+        options,
+    )
 }
 
-/// Delete a profile
-pub fn delete_profile(name: &str) {
-    let path = get_profile_path(name);
-    fs::remove_file(&path).expect("Failed to delete profile");
+/// The config a switch to `profile` turns `config` into. Full snapshots and
+/// `SwitchMode::AccountOnly` replace it; slim profiles patch only the
+/// account-specific fields.
+fn patched_config(
+    config: &serde_json::Value,
+    profile: &serde_json::Value,
+    mode: SwitchMode,
+    fields: &[String],
+) -> serde_json::Value {
+    let mut config = config.clone();
+    match mode {
+        SwitchMode::AccountOnly => config = extract_account_fields(profile, fields),
+        _ if is_full_profile(profile) => {
+            config = profile.clone();
+            if let Some(obj) = config.as_object_mut() {
+                obj.shift_remove(FULL_PROFILE_MARKER);
+                obj.shift_remove(BASE_URL_FIELD);
+                obj.shift_remove(CONFIG_DIR_FIELD);
+            }
+        }
+        SwitchMode::Replace => patch_account_fields(&mut config, profile, fields),
+        SwitchMode::Merge => merge_account_fields(&mut config, profile, fields),
+    }
+    config
 }
 
-/// Check if a profile exists
-pub fn profile_exists(name: &str) -> bool {
-    get_profile_path(name).exists()
+/// The ~/.claude.json a switch to profile `name` would write, computed
+/// without writing anything (--print-resulting-config)
+pub fn resulting_config(name: &str, options: &SwitchOptions) -> Result<serde_json::Value> {
+    let slug = slugify(name);
+    if !profile_exists(name)? {
+        return Err(Error::profile_not_found(&slug));
+    }
+
+    let profile = read_profile(name)?;
+    let config = patched_config(
+        &read_config_or_empty(&claude_config_path()),
+        &profile,
+        options.mode,
+        &account_fields()?,
+    );
+    validate_patched_config(&config, &profile, &slug)?;
+    Ok(config)
 }
 
-/// Switch to a profile by patching ~/.claude.json in-place.
-/// Only the 8 account-specific fields are touched; all other settings are preserved.
-/// The profile file is read-only and never modified.
-pub fn switch_to_profile(name: &str) {
-    let profile_path = get_profile_path(name);
-    if !profile_path.exists() {
-        panic!("Profile '{}' not found", slugify(name));
+/// Re-apply profile `name` if ~/.claude.json no longer carries what a switch
+/// to it writes (`watch`). A config that can't be read or parsed (e.g. while
+/// another tool is rewriting it) is left alone until it settles. Returns
+/// whether the profile was re-applied.
+#[cfg(feature = "watch")]
+pub fn reapply_if_drifted(name: &str, options: &SwitchOptions) -> Result<bool> {
+    let profile = read_profile(name)?;
+    let Ok(content) = fs::read_to_string(claude_config_path()) else {
+        return Ok(false);
+    };
+    let Ok(live) = serde_json::from_str::<serde_json::Value>(strip_bom(&content)) else {
+        return Ok(false);
+    };
+    if patched_config(&live, &profile, options.mode, &account_fields()?) == live {
+        return Ok(false);
     }
 
-    let config_path = claude_config_path();
+    apply_profile(&slugify(name), &profile, options)?;
+    Ok(true)
+}
+
+/// Refuse to write through a symlinked Claude config, which would modify its
+/// target (possibly a profile), unless --follow-symlinks was passed
+fn ensure_config_writable(config_path: &Path, options: &SwitchOptions) -> Result<()> {
+    if config_path.is_symlink() && !options.follow_symlinks {
+        return Err(Error::Other(format!(
+            "Claude config at {:?} is a symlink; refusing to write through it. \
+             Replace it with a regular file (run claudectx without --follow-symlinks to migrate) \
+             or pass --follow-symlinks",
+            config_path
+        )));
+    }
+    Ok(())
+}
 
-    // Read the slim profile
-    let profile_content = fs::read_to_string(&profile_path).expect("Failed to read target profile");
-    let profile: serde_json::Value =
-        serde_json::from_str(&profile_content).expect("Failed to parse target profile");
+/// Patch the claude config in place with a slim profile's account fields
+fn apply_profile(
+    slug: &str,
+    profile: &serde_json::Value,
+    options: &SwitchOptions,
+) -> Result<SwitchSummary> {
+    let config_path = claude_config_path();
+    let fields = account_fields()?;
+    ensure_config_writable(&config_path, options)?;
 
     // Read current config or start from empty object
-    let mut config: serde_json::Value = if config_path.exists() {
-        let content = fs::read_to_string(&config_path).unwrap_or_else(|_| "{}".to_string());
-        serde_json::from_str(&content).unwrap_or_else(|_| serde_json::json!({}))
-    } else {
-        serde_json::json!({})
-    };
+    let before = read_config_or_empty(&config_path);
+
+    // Full snapshots and --no-portable replace the whole config: keep a
+    // backup, since nothing of it survives
+    if (is_full_profile(profile) || options.mode == SwitchMode::AccountOnly) && config_path.exists()
+    {
+        fs::copy(&config_path, claude_config_backup_path()?)
+            .map_err(|e| Error::io("Failed to back up Claude config", e))?;
+    }
+    let config = patched_config(&before, profile, options.mode, &fields);
 
-    // Patch only account-specific fields
-    patch_account_fields(&mut config, &profile);
+    // Make sure the result is usable before touching the file on disk
+    validate_patched_config(&config, profile, slug)?;
 
     // Write back
-    let output = serde_json::to_string_pretty(&config).expect("Failed to serialize config");
-    fs::write(&config_path, output).expect("Failed to write config");
+    let output = to_json_file(&config, "Failed to serialize config")?;
+    fs::write(&config_path, output).map_err(|e| Error::io("Failed to write config", e))?;
+
+    Ok(SwitchSummary {
+        slug: slug.to_string(),
+        email: get_account_field(profile, "emailAddress")
+            .or_else(|| get_api_key(profile).map(api_key_label)),
+        organization: get_account_field(profile, "organizationName"),
+        changed: count_changed_account_fields(&before, &config, &fields),
+        env: profile_env(profile),
+        identity_warning: None,
+    })
+}
+
+/// Fail if a profile contains keys outside the account-specific fields (--strict)
+pub fn ensure_canonical_profile(name: &str) -> Result<()> {
+    if !profile_exists(name)? {
+        return Err(Error::profile_not_found(&slugify(name)));
+    }
+
+    let unexpected = unexpected_keys(&read_profile(name)?, &account_fields()?);
+    if unexpected.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Parse(format!(
+            "Profile '{}' has unexpected keys: {}",
+            slugify(name),
+            unexpected.join(", ")
+        )))
+    }
 }
 
 /// Get the current profile name by comparing accountUuid in ~/.claude.json
-/// with saved profiles.
+/// with saved profiles (or the API key for API-key-only accounts). Ties are
+/// broken by the `.current` marker, then by name.
 pub fn get_current_profile() -> Option<String> {
     let config_path = claude_config_path();
 
@@ -201,62 +1629,218 @@ pub fn get_current_profile() -> Option<String> {
     }
 
     let current_content = fs::read_to_string(&config_path).ok()?;
-    let current_config: serde_json::Value = serde_json::from_str(&current_content).ok()?;
-    let current_uuid = get_account_uuid(&current_config)?;
-
-    // Search through profiles for matching accountUuid
-    for profile_name in list_profiles() {
-        let profile_path = get_profile_path(&profile_name);
-        let profile_content = fs::read_to_string(&profile_path).ok();
-        let profile_config: Option<serde_json::Value> =
-            profile_content.and_then(|c| serde_json::from_str(&c).ok());
-
-        if let Some(profile_uuid) = profile_config.and_then(|c| get_account_uuid(&c)) {
-            if profile_uuid == current_uuid {
-                return Some(profile_name);
-            }
+    let current_config = parse_account_summary(&current_content).ok()?;
+    if !has_account_identity(&current_config) {
+        return None;
+    }
+
+    // Search through profiles for a matching accountUuid (or API key)
+    let mut matching: Vec<String> = list_profiles()
+        .ok()?
+        .into_iter()
+        .filter(|profile_name| {
+            read_profile_summary(profile_name)
+                .map(|profile| same_account(&profile, &current_config))
+                .unwrap_or(false)
+        })
+        .collect();
+
+    // Several profiles can share an account: prefer the one last switched to
+    // or saved, then the alphabetically first, so the result is stable
+    if let Some(marked) = read_current_marker() {
+        if matching.contains(&marked) {
+            return Some(marked);
         }
     }
+    matching.sort();
+    matching.into_iter().next()
+}
 
-    None
+/// Get the path to the current profile marker (~/.claudectx/.current), the
+/// profile last switched to or saved
+fn current_marker_path() -> PathBuf {
+    claudectx_dir().join(".current")
 }
 
-/// Get the backup path for claude.json
-pub fn claude_config_backup_path() -> PathBuf {
-    home_dir().join(".claude.json.bak")
+/// Profile name recorded in the current profile marker, if any
+fn read_current_marker() -> Option<String> {
+    let content = fs::read_to_string(current_marker_path()).ok()?;
+    let name = content.trim();
+    (!name.is_empty()).then(|| name.to_string())
 }
 
-/// Backup ~/.claude.json to ~/.claude.json.bak if it exists
-/// Returns true if a backup was created, false if no config existed
-pub fn backup_claude_config() -> bool {
-    let config_path = claude_config_path();
-    let backup_path = claude_config_backup_path();
+/// Record `slug` as the profile last switched to or saved
+fn set_current_marker(slug: &str) -> Result<()> {
+    fs::create_dir_all(claudectx_dir())
+        .map_err(|e| state_write_error("Failed to create claudectx directory", e))?;
+    fs::write(current_marker_path(), format!("{}\n", slug))
+        .map_err(|e| state_write_error("Failed to save current profile", e))
+}
 
-    if config_path.exists() {
-        let content = fs::read_to_string(&config_path).expect("Failed to read Claude config");
-        fs::write(&backup_path, content).expect("Failed to create backup");
-        fs::remove_file(&config_path).expect("Failed to remove original config");
-        true
-    } else {
-        false
+/// Get the path to the default profile marker (~/.claudectx/.default)
+pub fn default_profile_path() -> PathBuf {
+    claudectx_dir().join(".default")
+}
+
+/// Get the default profile name, if one was set
+pub fn get_default_profile() -> Option<String> {
+    let content = fs::read_to_string(default_profile_path()).ok()?;
+    let name = content.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Set the default profile (the profile must exist)
+pub fn set_default_profile(name: &str) -> Result<()> {
+    let slug = slugify(name);
+    if !profile_exists(name)? {
+        return Err(Error::profile_not_found(&slug));
+    }
+
+    ensure_profiles_dir()?;
+    fs::write(default_profile_path(), format!("{}\n", slug))
+        .map_err(|e| state_write_error("Failed to save default profile", e))
+}
+
+/// Get the backup path for claude.json (next to it, with a .bak suffix)
+pub fn claude_config_backup_path() -> Result<PathBuf> {
+    let name = claude_config_path()
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| ".claude.json".to_string());
+    new_backup_path(&name)
+}
+
+/// Where older versions backed up the Claude config (~/.claude.json.bak),
+/// still read by `restore_claude_config`
+pub fn legacy_claude_config_backup_path() -> PathBuf {
+    let mut path = claude_config_path().into_os_string();
+    path.push(".bak");
+    PathBuf::from(path)
+}
+
+/// Move ~/.claude.json into the backups directory if it exists.
+/// Returns the backup path, or None if no config existed
+pub fn backup_claude_config() -> Result<Option<PathBuf>> {
+    let config_path = claude_config_path();
+    if !config_path.exists() {
+        return Ok(None);
     }
+
+    let backup_path = claude_config_backup_path()?;
+    let content = fs::read_to_string(&config_path)
+        .map_err(|e| Error::io("Failed to read Claude config", e))?;
+    fs::write(&backup_path, content).map_err(|e| Error::io("Failed to create backup", e))?;
+    fs::remove_file(&config_path).map_err(|e| Error::io("Failed to remove original config", e))?;
+    Ok(Some(backup_path))
 }
 
 /// Restore ~/.claude.json from backup, or remove the current config if no backup exists
-/// - If backup exists: restore it and remove backup
+/// - If a backup was taken: restore it (falling back to the legacy
+///   ~/.claude.json.bak if it is gone) and remove it
 /// - If no backup: just remove the current config (if any)
-pub fn restore_claude_config(had_backup: bool) {
+pub fn restore_claude_config(backup: Option<&Path>) -> Result<()> {
+    let config_path = claude_config_path();
+    let legacy_path = legacy_claude_config_backup_path();
+    let source = backup
+        .map(|backup_path| {
+            if backup_path.exists() {
+                backup_path
+            } else {
+                legacy_path.as_path()
+            }
+        })
+        .filter(|source| source.exists());
+
+    let Some(source) = source else {
+        // Remove current config if it exists
+        if config_path.exists() {
+            fs::remove_file(&config_path)
+                .map_err(|e| Error::io("Failed to remove current config", e))?;
+        }
+        return Ok(());
+    };
+
+    // The backup may live on another filesystem (--backup-dir), where a rename
+    // fails: copy it next to the config first, so the live config is only
+    // replaced once the copy succeeded
+    let mut staged = config_path.clone().into_os_string();
+    staged.push(".restore");
+    let staged = PathBuf::from(staged);
+    fs::copy(source, &staged).map_err(|e| Error::io("Failed to restore backup", e))?;
+    fs::rename(&staged, &config_path).map_err(|e| {
+        let _ = fs::remove_file(&staged);
+        Error::io("Failed to restore backup", e)
+    })?;
+    fs::remove_file(source).map_err(|e| Error::io("Failed to remove restored backup", e))
+}
+
+/// Copy the portable (non-account) settings of the config at `source` into
+/// ~/.claude.json, keeping any key the live config already has. Account
+/// fields are never copied.
+pub fn clone_portable_settings(source: &Path) -> Result<()> {
+    let content =
+        fs::read_to_string(source).map_err(|e| Error::io("Failed to read config to clone", e))?;
+    let mut portable: serde_json::Value = serde_json::from_str(strip_bom(&content))
+        .map_err(|e| Error::parse("Failed to parse config to clone", e))?;
+    patch_account_fields(&mut portable, &serde_json::json!({}), &account_fields()?);
+    let Some(portable) = portable.as_object() else {
+        return Ok(());
+    };
+
     let config_path = claude_config_path();
-    let backup_path = claude_config_backup_path();
+    let mut config = read_config_or_empty(&config_path);
+    let Some(config_obj) = config.as_object_mut() else {
+        return Ok(());
+    };
+    for (key, value) in portable {
+        if !config_obj.contains_key(key) {
+            config_obj.insert(key.clone(), value.clone());
+        }
+    }
 
-    // Remove current config if it exists
-    if config_path.exists() {
-        fs::remove_file(&config_path).expect("Failed to remove current config");
+    let output = to_json_file(&config, "Failed to serialize config")?;
+    fs::write(&config_path, output).map_err(|e| Error::io("Failed to write config", e))
+}
+
+/// Write a standalone ~/.claude.json-shaped file for profile `name` to
+/// `dest`: the live config's portable settings (or a minimal skeleton when
+/// there is no live config) with the profile's account fields applied. A full
+/// profile is written as is. The live config is never modified.
+pub fn materialize_config(name: &str, dest: &Path) -> Result<()> {
+    let slug = slugify(name);
+    if !profile_exists(name)? {
+        return Err(Error::profile_not_found(&slug));
+    }
+
+    let config_path = claude_config_path();
+    if dest.exists() && fs::canonicalize(dest).ok() == fs::canonicalize(&config_path).ok() {
+        return Err(Error::Other(format!(
+            "{:?} is the live Claude config; switch to '{}' instead",
+            dest, slug
+        )));
     }
 
-    if had_backup && backup_path.exists() {
-        fs::rename(&backup_path, &config_path).expect("Failed to restore backup");
+    let profile = read_profile(name)?;
+    let mut config = if is_full_profile(&profile) {
+        profile.clone()
+    } else {
+        let mut config = if config_path.exists() {
+            read_config_or_empty(&config_path)
+        } else {
+            serde_json::json!({ "hasCompletedOnboarding": true })
+        };
+        patch_account_fields(&mut config, &profile, &account_fields()?);
+        config
+    };
+    if let Some(obj) = config.as_object_mut() {
+        obj.shift_remove(FULL_PROFILE_MARKER);
+        obj.shift_remove(BASE_URL_FIELD);
+        obj.shift_remove(CONFIG_DIR_FIELD);
     }
+    validate_patched_config(&config, &profile, &slug)?;
+
+    let output = to_json_file(&config, "Failed to serialize config")?;
+    fs::write(dest, output).map_err(|e| Error::io("Failed to write config", e))
 }
 
 /// Check if claude.json exists
@@ -265,70 +1849,257 @@ pub fn claude_config_exists() -> bool {
     config_path.exists()
 }
 
+/// What `migrate` would do
+#[derive(Debug, Default)]
+pub struct MigrationPlan {
+    /// ~/.claude.json is a symlink to be replaced with a regular file
+    pub config_is_symlink: bool,
+    /// Profiles holding keys beyond the account-specific fields, to be slimmed
+    /// (only when migrating from a symlinked config)
+    pub profiles_to_slim: Vec<String>,
+}
+
+impl MigrationPlan {
+    /// Whether migrating would change anything
+    pub fn is_pending(&self) -> bool {
+        self.config_is_symlink || !self.profiles_to_slim.is_empty()
+    }
+}
+
+/// Profile files that still need slimming, with their profile names
+fn profiles_needing_slim() -> Result<Vec<(String, PathBuf)>> {
+    let fields = account_fields()?;
+    let mut pending = Vec::new();
+    for name in list_profiles()? {
+        let path = get_profile_path(&name)?;
+        // A profile that isn't valid JSON can't be slimmed; `doctor` reports it
+        let Some(profile) = fs::read_to_string(&path).ok().and_then(|content| {
+            serde_json::from_str::<serde_json::Value>(strip_bom(&content)).ok()
+        }) else {
+            continue;
+        };
+
+        // Full snapshots are kept whole on purpose (unexpected_keys skips them)
+        if !unexpected_keys(&profile, &fields).is_empty() {
+            pending.push((name, path));
+        }
+    }
+    pending.sort();
+    Ok(pending)
+}
+
+/// Report what `migrate` would do without touching any file
+pub fn plan_migration() -> Result<MigrationPlan> {
+    let config_is_symlink = claude_config_path().is_symlink();
+    // Without a symlink the profiles aren't the old design's full configs, so
+    // extra keys were added on purpose (`save --merge`, hand edits)
+    let profiles_to_slim = if config_is_symlink {
+        profiles_needing_slim()?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Ok(MigrationPlan {
+        config_is_symlink,
+        profiles_to_slim,
+    })
+}
+
 /// One-shot migration from symlink-based to slim-profile architecture.
 /// Triggered only when ~/.claude.json is a symlink (old architecture).
 /// On subsequent runs, is_symlink() returns false → no-op.
-pub fn migrate_if_needed() {
-    let config_path = claude_config_path();
+pub fn migrate_if_needed() -> Result<()> {
+    if !claude_config_path().is_symlink() {
+        return Ok(());
+    }
+    migrate().map(|_| ())
+}
 
+/// Replace a symlinked ~/.claude.json with a regular file and slim down every
+/// profile holding more than the account-specific fields (each backed up
+/// first). Without a symlink there is nothing to migrate: profiles are left
+/// as they are.
+pub fn migrate() -> Result<usize> {
+    let config_path = claude_config_path();
     if !config_path.is_symlink() {
-        return;
+        println!("Nothing to migrate.");
+        return Ok(0);
     }
 
     // 1. Read content through the symlink
-    let content =
-        fs::read_to_string(&config_path).expect("Failed to read Claude config through symlink");
-
-    // 2. Remove the symlink
-    fs::remove_file(&config_path).expect("Failed to remove symlink");
+    let content = fs::read_to_string(&config_path)
+        .map_err(|e| Error::io("Failed to read Claude config through symlink", e))?;
+
+    // 2. Keep a copy of the pre-migration config
+    fs::write(claude_config_backup_path()?, &content)
+        .map_err(|e| Error::io("Failed to back up Claude config", e))?;
+
+    // 3. Remove the symlink
+    fs::remove_file(&config_path).map_err(|e| Error::io("Failed to remove symlink", e))?;
+
+    // 4. Write the content as a regular file
+    fs::write(&config_path, &content)
+        .map_err(|e| Error::io("Failed to write config as regular file", e))?;
+    let mut migrated = 1;
+
+    // 5. Slim down each profile in ~/.claudectx/
+    let fields = account_fields()?;
+    for (name, path) in profiles_needing_slim()? {
+        // a. Create backup
+        let file_name = path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+            .unwrap_or(name);
+        let backup_path = new_backup_path(&file_name)?;
+        fs::copy(&path, &backup_path)
+            .map_err(|e| Error::io("Failed to create profile backup", e))?;
+
+        // b. Rewrite with only account-specific fields
+        let profile_content = fs::read_to_string(&path)
+            .map_err(|e| Error::io("Failed to read profile for migration", e))?;
+        let profile_config: serde_json::Value =
+            serde_json::from_str(strip_bom(&profile_content))
+                .map_err(|e| Error::parse("Failed to parse profile for migration", e))?;
+
+        let slim = extract_account_fields(&profile_config, &fields);
+        let slim_json = to_json_file(&slim, "Failed to serialize slim profile")?;
+        fs::write(&path, slim_json)
+            .map_err(|e| profile_write_error("Failed to write slim profile", e))?;
+        migrated += 1;
+    }
 
-    // 3. Write the content as a regular file
-    fs::write(&config_path, &content).expect("Failed to write config as regular file");
+    println!(
+        "Migrated profiles to slim format (backups in {})",
+        backups_dir().display()
+    );
+    Ok(migrated)
+}
 
-    // 4. Slim down each profile in ~/.claudectx/
-    let dir = profiles_dir();
-    if dir.exists() {
-        let entries: Vec<_> = fs::read_dir(&dir)
-            .expect("Failed to read profiles directory")
-            .filter_map(|e| e.ok())
-            .collect();
+/// A safe repair `doctor --fix` can apply
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Repair {
+    /// ~/.claudectx is missing
+    CreateDir(PathBuf),
+    /// A symlink (~/.claude.json or a profile) points to a missing file
+    RemoveDanglingSymlink(PathBuf),
+    /// `migrate` has work left to do
+    Migrate,
+    /// A marker file (.current, .default) names a profile that no longer exists
+    ClearStaleMarker(PathBuf, String),
+}
 
-        for entry in entries {
-            let path = entry.path();
-            let name = path
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
+impl Repair {
+    /// One-line description of the problem and its fix
+    pub fn describe(&self) -> String {
+        match self {
+            Repair::CreateDir(dir) => format!("Create missing directory {}", dir.display()),
+            Repair::RemoveDanglingSymlink(path) => {
+                format!("Remove dangling symlink {}", path.display())
+            }
+            Repair::Migrate => "Run the pending migration (claudectx migrate)".to_string(),
+            Repair::ClearStaleMarker(path, name) => format!(
+                "Clear {} (it points to missing profile '{}')",
+                path.display(),
+                name
+            ),
+        }
+    }
 
-            if !name.ends_with(".claude.json") || name.ends_with(".bak") {
-                continue;
+    /// Apply the repair
+    pub fn apply(&self) -> Result<()> {
+        match self {
+            Repair::CreateDir(dir) => fs::create_dir_all(dir)
+                .map_err(|e| profile_write_error("Failed to create profiles directory", e)),
+            Repair::RemoveDanglingSymlink(path) => {
+                fs::remove_file(path).map_err(|e| Error::io("Failed to remove dangling symlink", e))
+            }
+            Repair::Migrate => migrate().map(|_| ()),
+            Repair::ClearStaleMarker(path, _) => {
+                fs::remove_file(path).map_err(|e| Error::io("Failed to clear marker", e))
             }
+        }
+    }
+}
 
-            // a. Create backup
-            let backup_path = path.with_extension("json.bak");
-            fs::copy(&path, &backup_path).expect("Failed to create profile backup");
+/// Profiles whose file exists but is not valid JSON, sorted. `doctor` reports
+/// them for a manual fix.
+pub fn invalid_profiles() -> Result<Vec<String>> {
+    let mut invalid = Vec::new();
+    for name in list_profiles()? {
+        let path = get_profile_path(&name)?;
+        // Dangling symlinks are a repair of their own
+        if !path.exists() {
+            continue;
+        }
+        let valid = fs::read_to_string(&path).is_ok_and(|content| {
+            serde_json::from_str::<serde_json::Value>(strip_bom(&content)).is_ok()
+        });
+        if !valid {
+            invalid.push(name);
+        }
+    }
+    invalid.sort();
+    Ok(invalid)
+}
 
-            // b. Rewrite with only account-specific fields
-            let profile_content =
-                fs::read_to_string(&path).expect("Failed to read profile for migration");
-            let profile_config: serde_json::Value = serde_json::from_str(&profile_content)
-                .expect("Failed to parse profile for migration");
+/// Problems `doctor` knows how to repair, in the order they should be fixed.
+/// The migration is only checked once no dangling symlink is left, since it
+/// reads every profile.
+pub fn plan_repairs() -> Result<Vec<Repair>> {
+    let mut repairs = Vec::new();
+    let dir = claudectx_dir();
+    if !dir.exists() {
+        repairs.push(Repair::CreateDir(dir));
+    }
 
-            let slim = extract_account_fields(&profile_config);
-            let slim_json =
-                serde_json::to_string_pretty(&slim).expect("Failed to serialize slim profile");
-            fs::write(&path, slim_json).expect("Failed to write slim profile");
+    let config_path = claude_config_path();
+    let mut dangling = Vec::new();
+    if config_path.is_symlink() && !config_path.exists() {
+        dangling.push(config_path);
+    }
+    if let Ok(entries) = fs::read_dir(profiles_dir()) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_symlink() && !path.exists() {
+                dangling.push(path);
+            }
         }
     }
+    dangling.sort();
+    let has_dangling = !dangling.is_empty();
+    repairs.extend(dangling.into_iter().map(Repair::RemoveDanglingSymlink));
+
+    if !has_dangling && plan_migration()?.is_pending() {
+        repairs.push(Repair::Migrate);
+    }
 
-    println!("Migrated profiles to slim format (backups in ~/.claudectx/*.bak)");
+    let markers = [
+        (current_marker_path(), read_current_marker()),
+        (default_profile_path(), get_default_profile()),
+    ];
+    for (path, name) in markers {
+        if let Some(name) = name {
+            if !profile_exists(&name).unwrap_or(false) {
+                repairs.push(Repair::ClearStaleMarker(path, name));
+            }
+        }
+    }
+    Ok(repairs)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn default_fields() -> Vec<String> {
+        ACCOUNT_SPECIFIC_FIELDS
+            .iter()
+            .map(|field| field.to_string())
+            .collect()
+    }
+
     #[test]
     fn test_slugify_simple() {
         assert_eq!(slugify("fg"), "fg");
@@ -354,8 +2125,8 @@ mod tests {
     }
 
     #[test]
-    fn test_backup_path() {
-        let backup_path = super::claude_config_backup_path();
+    fn test_legacy_backup_path() {
+        let backup_path = super::legacy_claude_config_backup_path();
         assert!(backup_path.to_string_lossy().ends_with(".claude.json.bak"));
     }
 
@@ -375,7 +2146,7 @@ mod tests {
             "customSetting": "custom"
         });
 
-        let slim = extract_account_fields(&config);
+        let slim = extract_account_fields(&config, &default_fields());
         let obj = slim.as_object().unwrap();
 
         // Only account-specific keys present
@@ -402,7 +2173,7 @@ mod tests {
             "hasCompletedOnboarding": true
         });
 
-        let slim = extract_account_fields(&config);
+        let slim = extract_account_fields(&config, &default_fields());
         let obj = slim.as_object().unwrap();
 
         // Only the one account field present
@@ -423,7 +2194,7 @@ mod tests {
             "userID": "new-user"
         });
 
-        patch_account_fields(&mut config, &profile);
+        patch_account_fields(&mut config, &profile, &default_fields());
 
         assert_eq!(config["oauthAccount"]["accountUuid"], "new-uuid");
         assert_eq!(config["userID"], "new-user");
@@ -431,6 +2202,25 @@ mod tests {
         assert_eq!(config["hasCompletedOnboarding"], true);
     }
 
+    #[test]
+    fn test_patch_account_fields_preserves_key_order() {
+        let mut config: serde_json::Value = serde_json::from_str(
+            r#"{"zeta": 1, "userID": "old", "alpha": 2, "groveConfigCache": {}, "mid": 3}"#,
+        )
+        .unwrap();
+        let profile = serde_json::json!({ "userID": "new" });
+
+        patch_account_fields(&mut config, &profile, &default_fields());
+
+        let keys: Vec<&str> = config
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(keys, vec!["zeta", "userID", "alpha", "mid"]);
+    }
+
     #[test]
     fn test_patch_account_fields_removes_absent_keys() {
         let mut config = serde_json::json!({
@@ -445,7 +2235,7 @@ mod tests {
             "oauthAccount": {"accountUuid": "new-uuid"}
         });
 
-        patch_account_fields(&mut config, &profile);
+        patch_account_fields(&mut config, &profile, &default_fields());
 
         assert_eq!(config["oauthAccount"]["accountUuid"], "new-uuid");
         assert!(config.get("userID").is_none());
@@ -454,6 +2244,228 @@ mod tests {
         assert_eq!(config["hasCompletedOnboarding"], true);
     }
 
+    #[test]
+    fn test_archive_entry_name() {
+        assert_eq!(
+            archive_entry_name("work.claude.json", ".claude.json"),
+            Some("work".to_string())
+        );
+        assert_eq!(
+            archive_entry_name("work.json", ".claude.json"),
+            Some("work".to_string())
+        );
+        assert_eq!(
+            archive_entry_name("work.profile.json", ".profile.json"),
+            Some("work".to_string())
+        );
+        assert_eq!(archive_entry_name("MANIFEST.tsv", ".claude.json"), None);
+        assert_eq!(archive_entry_name(".claude.json", ".claude.json"), None);
+    }
+
+    #[test]
+    fn test_unexpected_keys() {
+        let canonical = serde_json::json!({
+            "oauthAccount": {"accountUuid": "uuid"},
+            "userID": "user"
+        });
+        assert!(unexpected_keys(&canonical, &default_fields()).is_empty());
+
+        let drifted = serde_json::json!({
+            "oauthAccount": {"accountUuid": "uuid"},
+            "primaryApiKey": "sk",
+            "editorTheme": "dark"
+        });
+        assert_eq!(
+            unexpected_keys(&drifted, &default_fields()),
+            vec!["editorTheme", "primaryApiKey"]
+        );
+    }
+
+    #[test]
+    fn test_api_key_identity_matches_by_key_without_uuid() {
+        let profile = serde_json::json!({"primaryApiKey": "sk-ant-123"});
+        let same = serde_json::json!({"primaryApiKey": "sk-ant-123", "theme": "dark"});
+        let other = serde_json::json!({"primaryApiKey": "sk-ant-456"});
+
+        assert!(has_account_identity(&profile));
+        assert!(same_account(&profile, &same));
+        assert!(!same_account(&profile, &other));
+        assert!(!same_account(
+            &serde_json::json!({}),
+            &serde_json::json!({})
+        ));
+        assert!(missing_account_keys(&profile).is_empty());
+    }
+
+    #[test]
+    fn test_identity_mismatch() {
+        let profile = serde_json::json!({
+            "oauthAccount": {"accountUuid": "uuid", "emailAddress": "new@example.com"}
+        });
+        let same = SeenAccounts::from([("uuid".to_string(), "new@example.com".to_string())]);
+        let changed = SeenAccounts::from([("uuid".to_string(), "old@example.com".to_string())]);
+
+        assert!(identity_mismatch("work", &profile, &SeenAccounts::new()).is_none());
+        assert!(identity_mismatch("work", &profile, &same).is_none());
+        let warning = identity_mismatch("work", &profile, &changed).unwrap();
+        assert!(warning.contains("old@example.com"));
+        assert!(warning.contains("new@example.com"));
+    }
+
+    #[test]
+    fn test_check_switch_applied() {
+        let profile = serde_json::json!({"oauthAccount": {"accountUuid": "uuid-work"}});
+        let applied =
+            serde_json::json!({"oauthAccount": {"accountUuid": "uuid-work"}, "theme": "dark"});
+        let clobbered = serde_json::json!({"oauthAccount": {"accountUuid": "uuid-other"}});
+
+        assert!(check_switch_applied("work", &profile, &applied).is_ok());
+        let err = check_switch_applied("work", &profile, &clobbered).unwrap_err();
+        assert!(err.to_string().contains("uuid-other"));
+        assert!(check_switch_applied("work", &profile, &serde_json::json!({})).is_err());
+    }
+
+    #[test]
+    fn test_parse_account_summary_keeps_only_account_keys() {
+        let content = serde_json::json!({
+            "oauthAccount": {"accountUuid": "uuid", "emailAddress": "a@example.com"},
+            "groveConfigCache": {"blob": vec![0; 64]},
+            "primaryApiKey": "sk-ant-api-abcd",
+            "theme": "dark"
+        })
+        .to_string();
+
+        let summary = parse_account_summary(&content).unwrap();
+        assert_eq!(
+            summary,
+            serde_json::json!({
+                "oauthAccount": {"accountUuid": "uuid", "emailAddress": "a@example.com"},
+                "primaryApiKey": "sk-ant-api-abcd"
+            })
+        );
+        assert!(parse_account_summary("[]").is_err());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("client-*", "client-acme"));
+        assert!(glob_match("client-*", "client-"));
+        assert!(!glob_match("client-*", "work"));
+        assert!(glob_match("*-dev", "acme-dev"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(!glob_match("a?c", "ac"));
+        assert!(glob_match("*a*b*", "xxaxxbxx"));
+        assert!(!glob_match("*a*b*", "xxbxxaxx"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn test_redact_secrets() {
+        let mut value = serde_json::json!({
+            "primaryApiKey": "sk-ant-123",
+            "oauthAccount": {
+                "accountUuid": "uuid",
+                "refreshToken": "rt-123"
+            },
+            "caches": [{"clientSecret": "shh", "keep": 1}]
+        });
+
+        redact_secrets(&mut value);
+
+        assert_eq!(value["primaryApiKey"], "API key (…-123)");
+        assert_eq!(value["oauthAccount"]["refreshToken"], "***");
+        assert_eq!(value["oauthAccount"]["accountUuid"], "uuid");
+        assert_eq!(value["caches"][0]["clientSecret"], "***");
+        assert_eq!(value["caches"][0]["keep"], 1);
+    }
+
+    #[test]
+    fn test_missing_account_keys() {
+        let complete = serde_json::json!({
+            "oauthAccount": {"accountUuid": "uuid", "emailAddress": "a@b.c"}
+        });
+        assert!(missing_account_keys(&complete).is_empty());
+
+        let no_email = serde_json::json!({"oauthAccount": {"accountUuid": "uuid"}});
+        assert_eq!(missing_account_keys(&no_email), vec!["emailAddress"]);
+
+        let empty = serde_json::json!({"userID": "user"});
+        assert_eq!(
+            missing_account_keys(&empty),
+            vec!["accountUuid", "emailAddress"]
+        );
+    }
+
+    #[test]
+    fn test_validate_patched_config_accepts_matching_uuid() {
+        let profile = serde_json::json!({"oauthAccount": {"accountUuid": "uuid"}});
+        let config = serde_json::json!({
+            "oauthAccount": {"accountUuid": "uuid"},
+            "hasCompletedOnboarding": true
+        });
+
+        assert!(validate_patched_config(&config, &profile, "work").is_ok());
+    }
+
+    #[test]
+    fn test_validate_patched_config_rejects_profile_without_uuid() {
+        let profile = serde_json::json!({"userID": "user-id"});
+        let mut config = serde_json::json!({"oauthAccount": {"accountUuid": "old"}});
+        patch_account_fields(&mut config, &profile, &default_fields());
+
+        let err = validate_patched_config(&config, &profile, "broken").unwrap_err();
+        assert!(err.to_string().contains("Profile 'broken' has no"));
+    }
+
+    #[test]
+    fn test_count_changed_account_fields() {
+        let before = serde_json::json!({
+            "oauthAccount": {"accountUuid": "old"},
+            "userID": "same",
+            "groveConfigCache": {"stale": true},
+            "editorTheme": "dark"
+        });
+        let after = serde_json::json!({
+            "oauthAccount": {"accountUuid": "new"},
+            "userID": "same",
+            "s1mAccessCache": {},
+            "editorTheme": "light"
+        });
+
+        // oauthAccount changed, groveConfigCache removed, s1mAccessCache added;
+        // portable editorTheme is not counted
+        assert_eq!(
+            count_changed_account_fields(&before, &after, &default_fields()),
+            3
+        );
+        assert_eq!(
+            count_changed_account_fields(&after, &after, &default_fields()),
+            0
+        );
+    }
+
+    #[test]
+    fn test_merge_account_fields_keeps_absent_keys() {
+        let mut config = serde_json::json!({
+            "oauthAccount": {"accountUuid": "old-uuid"},
+            "userID": "old-user",
+            "groveConfigCache": {"old": true},
+            "hasCompletedOnboarding": true
+        });
+
+        let profile = serde_json::json!({
+            "oauthAccount": {"accountUuid": "new-uuid"}
+        });
+
+        merge_account_fields(&mut config, &profile, &default_fields());
+
+        assert_eq!(config["oauthAccount"]["accountUuid"], "new-uuid");
+        // Absent from the profile, so left as-is
+        assert_eq!(config["userID"], "old-user");
+        assert_eq!(config["groveConfigCache"]["old"], true);
+        assert_eq!(config["hasCompletedOnboarding"], true);
+    }
+
     #[test]
     fn test_patch_account_fields_leaves_portable_fields_untouched() {
         let mut config = serde_json::json!({
@@ -468,7 +2480,7 @@ mod tests {
             "oauthAccount": {"accountUuid": "new"}
         });
 
-        patch_account_fields(&mut config, &profile);
+        patch_account_fields(&mut config, &profile, &default_fields());
 
         // Portable fields all untouched
         assert_eq!(config["hasCompletedOnboarding"], true);
@@ -478,4 +2490,24 @@ mod tests {
         // Account field updated
         assert_eq!(config["oauthAccount"]["accountUuid"], "new");
     }
+
+    #[test]
+    fn test_patched_config_is_stable_once_applied() {
+        let config = serde_json::json!({
+            "oauthAccount": {"accountUuid": "old"},
+            "userID": "old-user",
+            "editorTheme": "dark"
+        });
+        let profile = serde_json::json!({
+            "oauthAccount": {"accountUuid": "new"}
+        });
+
+        let patched = patched_config(&config, &profile, SwitchMode::Replace, &default_fields());
+        assert_ne!(patched, config);
+        // Drift detection relies on a second patch being a no-op
+        assert_eq!(
+            patched_config(&patched, &profile, SwitchMode::Replace, &default_fields()),
+            patched
+        );
+    }
 }
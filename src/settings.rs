@@ -0,0 +1,296 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::strip_bom;
+use crate::error::{Error, Result};
+use crate::profiles::{claudectx_dir, state_write_error};
+
+/// Default suffix appended to profile names to build profile file names
+pub const DEFAULT_PROFILE_EXTENSION: &str = ".claude.json";
+
+/// Order in which profiles are listed and offered for selection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ProfileSort {
+    /// Alphabetical
+    #[default]
+    Name,
+    /// Most recently used first
+    Recent,
+}
+
+/// Interactive picker shown by bare `claudectx`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum PickerStyle {
+    /// Arrow-key list
+    #[default]
+    List,
+    /// Type-to-filter fuzzy finder with account details on every entry
+    Fuzzy,
+}
+
+/// claudectx settings stored in ~/.claudectx/.settings.json
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Profile filename suffix (defaults to ".claude.json")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile_extension: Option<String>,
+    /// Default profile order for `list` and the interactive picker
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<ProfileSort>,
+    /// Keys stored in profiles on top of the built-in account fields
+    /// (e.g. "primaryApiKey" for API-key-only accounts)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extra_account_fields: Vec<String>,
+    /// Shell command run before every switch (can veto it with --strict-hooks)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before_switch: Option<String>,
+    /// Shell command run after every switch, before claude is launched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after_switch: Option<String>,
+    /// Sort object keys when writing ~/.claude.json and profiles
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_keys: Option<bool>,
+    /// Interactive picker used when no profile is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub picker: Option<PickerStyle>,
+    /// Print the active account as a banner before launching claude
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub banner: Option<bool>,
+    /// Sync a profile's config directory snapshot into $CLAUDE_CONFIG_DIR on switch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_dirs: Option<bool>,
+}
+
+impl Settings {
+    /// Profile filename suffix, falling back to the default when unset
+    pub fn profile_extension(&self) -> &str {
+        match self.profile_extension.as_deref() {
+            Some(ext) if !ext.is_empty() => ext,
+            _ => DEFAULT_PROFILE_EXTENSION,
+        }
+    }
+}
+
+/// Keys accepted by `claudectx config`, in display order
+pub const SETTING_KEYS: &[&str] = &[
+    "profile_extension",
+    "sort",
+    "extra_account_fields",
+    "before_switch",
+    "after_switch",
+    "sort_keys",
+    "picker",
+    "banner",
+    "config_dirs",
+];
+
+impl Settings {
+    /// Effective value of a setting (its default when unset)
+    pub fn get(&self, key: &str) -> Result<String> {
+        match key {
+            "profile_extension" => Ok(self.profile_extension().to_string()),
+            "sort" => Ok(
+                clap::ValueEnum::to_possible_value(&self.sort.unwrap_or_default())
+                    .map(|value| value.get_name().to_string())
+                    .unwrap_or_default(),
+            ),
+            "extra_account_fields" => Ok(self.extra_account_fields.join(",")),
+            "before_switch" => Ok(self.before_switch.clone().unwrap_or_default()),
+            "after_switch" => Ok(self.after_switch.clone().unwrap_or_default()),
+            "sort_keys" => Ok(self.sort_keys.unwrap_or(false).to_string()),
+            "picker" => Ok(
+                clap::ValueEnum::to_possible_value(&self.picker.unwrap_or_default())
+                    .map(|value| value.get_name().to_string())
+                    .unwrap_or_default(),
+            ),
+            "banner" => Ok(self.banner.unwrap_or(false).to_string()),
+            "config_dirs" => Ok(self.config_dirs.unwrap_or(false).to_string()),
+            _ => Err(unknown_setting(key)),
+        }
+    }
+
+    /// Set a setting from its string form; an empty value resets it to the default
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let value = Some(value).filter(|v| !v.is_empty());
+        match key {
+            "profile_extension" => {
+                if let Some(ext) = value {
+                    validate_profile_extension(ext)?;
+                }
+                self.profile_extension = value.map(String::from)
+            }
+            "sort" => {
+                self.sort = value
+                    .map(|v| {
+                        <ProfileSort as clap::ValueEnum>::from_str(v, true).map_err(|_| {
+                            Error::Other(format!(
+                                "Invalid value '{}' for 'sort' (expected name or recent)",
+                                v
+                            ))
+                        })
+                    })
+                    .transpose()?
+            }
+            "extra_account_fields" => {
+                self.extra_account_fields = value
+                    .map(|v| {
+                        v.split(',')
+                            .map(str::trim)
+                            .filter(|field| !field.is_empty())
+                            .map(String::from)
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            }
+            "before_switch" => self.before_switch = value.map(String::from),
+            "after_switch" => self.after_switch = value.map(String::from),
+            "sort_keys" => self.sort_keys = value.map(|v| parse_bool(key, v)).transpose()?,
+            "banner" => self.banner = value.map(|v| parse_bool(key, v)).transpose()?,
+            "config_dirs" => self.config_dirs = value.map(|v| parse_bool(key, v)).transpose()?,
+            "picker" => {
+                self.picker = value
+                    .map(|v| {
+                        <PickerStyle as clap::ValueEnum>::from_str(v, true).map_err(|_| {
+                            Error::Other(format!(
+                                "Invalid value '{}' for 'picker' (expected list or fuzzy)",
+                                v
+                            ))
+                        })
+                    })
+                    .transpose()?
+            }
+            _ => return Err(unknown_setting(key)),
+        }
+        Ok(())
+    }
+}
+
+/// Check that a profile extension keeps profile files inside the profiles
+/// directory: a '.' followed by something, without path separators or `..`
+fn validate_profile_extension(ext: &str) -> Result<()> {
+    if ext.len() > 1 && ext.starts_with('.') && !ext.contains(['/', '\\']) && !ext.contains("..") {
+        return Ok(());
+    }
+    Err(Error::Other(format!(
+        "Invalid value '{}' for 'profile_extension' (expected a suffix starting with '.', without '/', '\\' or '..')",
+        ext
+    )))
+}
+
+/// Parse a true/false setting value
+fn parse_bool(key: &str, value: &str) -> Result<bool> {
+    value.parse().map_err(|_| {
+        Error::Other(format!(
+            "Invalid value '{}' for '{}' (expected true or false)",
+            value, key
+        ))
+    })
+}
+
+fn unknown_setting(key: &str) -> Error {
+    Error::Other(format!(
+        "Unknown config key '{}' (expected one of: {})",
+        key,
+        SETTING_KEYS.join(", ")
+    ))
+}
+
+/// Get the path to the settings file (~/.claudectx/.settings.json)
+pub fn settings_path() -> PathBuf {
+    claudectx_dir().join(".settings.json")
+}
+
+/// Blank out `//` line comments and `/* */` block comments outside of JSON
+/// strings, so the settings file can document e.g. why an extra account field
+/// is needed. Newlines are kept so parse errors point at the right line.
+fn strip_json_comments(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => while chars.next_if(|&next| next != '\n').is_some() {},
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        out.push('\n');
+                    }
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Load settings, or defaults if the settings file doesn't exist
+pub fn load_settings() -> Result<Settings> {
+    let path = settings_path();
+    if !path.exists() {
+        return Ok(Settings::default());
+    }
+
+    let content =
+        fs::read_to_string(&path).map_err(|e| Error::io("Failed to read claudectx settings", e))?;
+    let settings: Settings = serde_json::from_str(&strip_json_comments(strip_bom(&content)))
+        .map_err(|e| Error::parse("Failed to parse claudectx settings", e))?;
+    if let Some(ext) = settings
+        .profile_extension
+        .as_deref()
+        .filter(|ext| !ext.is_empty())
+    {
+        validate_profile_extension(ext).map_err(|e| {
+            Error::Parse(format!(
+                "Invalid claudectx settings in {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+    }
+    Ok(settings)
+}
+
+/// Write settings to ~/.claudectx/.settings.json. Refuses to rewrite a file
+/// holding comments, since they can't be carried over.
+pub fn save_settings(settings: &Settings) -> Result<()> {
+    let path = settings_path();
+    if let Ok(content) = fs::read_to_string(&path) {
+        let content = strip_bom(&content);
+        if strip_json_comments(content) != content {
+            return Err(Error::Other(format!(
+                "{} contains comments that rewriting it would drop; edit the file by hand instead",
+                path.display()
+            )));
+        }
+    }
+
+    fs::create_dir_all(claudectx_dir())
+        .map_err(|e| state_write_error("Failed to create claudectx directory", e))?;
+    let json = serde_json::to_string_pretty(settings)
+        .map_err(|e| Error::parse("Failed to serialize claudectx settings", e))?;
+    fs::write(path, json).map_err(|e| state_write_error("Failed to write claudectx settings", e))
+}
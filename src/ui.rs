@@ -1,50 +1,275 @@
-use dialoguer::Select;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::config::get_oauth_account;
-use crate::profiles::get_profile_path;
+use console::style;
+use dialoguer::{Confirm, FuzzySelect, Input, Select};
 
-/// Interactively select a profile from the list
+use crate::config::{account_email, account_names, describe_account, get_oauth_account};
+use crate::error::{Error, Result};
+use crate::profiles::{get_default_profile, read_profile_summary};
+use crate::settings::{load_settings, PickerStyle, ProfileSort};
+use crate::usage::{format_last_used, load_usage, now_secs, sort_profiles};
+
+/// Plain mode for the current invocation (--plain): never show a prompt
+static PLAIN_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Resolve every prompt to its default for the rest of this run
+pub fn set_plain_mode() {
+    PLAIN_MODE.store(true, Ordering::Relaxed);
+}
+
+fn is_plain() -> bool {
+    PLAIN_MODE.load(Ordering::Relaxed)
+}
+
+/// Whether prompts can be shown (stdin is a terminal and --plain is off)
+pub fn is_interactive() -> bool {
+    !is_plain() && std::io::stdin().is_terminal()
+}
+
+/// Ask a yes/no question for `action`. With `assume_yes` (--yes) the answer is
+/// yes without prompting and in plain mode it is `default`; without a terminal
+/// this fails up front instead of surfacing dialoguer's error.
+pub fn confirm(action: &str, prompt: &str, default: bool, assume_yes: bool) -> Result<bool> {
+    if assume_yes {
+        return Ok(true);
+    }
+    if is_plain() {
+        eprintln!("{} {}", prompt, if default { "yes" } else { "no" });
+        return Ok(default);
+    }
+    if !is_interactive() {
+        return Err(Error::Other(format!(
+            "'{}' requires confirmation; pass --yes or run in a terminal",
+            action
+        )));
+    }
+
+    Confirm::new()
+        .with_prompt(prompt)
+        .default(default)
+        .interact()
+        .map_err(Error::prompt)
+}
+
+/// Fail unless text can be prompted for `action`, pointing at `flag` as the
+/// non-interactive alternative
+pub fn require_input(action: &str, flag: &str) -> Result<()> {
+    if is_interactive() {
+        Ok(())
+    } else {
+        Err(Error::Other(format!(
+            "'{}' requires input; pass {} or run in a terminal",
+            action, flag
+        )))
+    }
+}
+
+/// Prompt for a line of text for `action` (see `require_input`)
+pub fn input_text(action: &str, prompt: &str, flag: &str) -> Result<String> {
+    require_input(action, flag)?;
+
+    Input::new()
+        .with_prompt(prompt)
+        .interact_text()
+        .map_err(Error::prompt)
+}
+
+/// Interactively select a profile from the list. With `details`, each entry
+/// also shows the organization role and when the profile was last used. With
+/// `fuzzy` (or the `picker` setting), a type-to-filter finder is shown instead,
+/// always with details so the highlighted entry previews its account. In
+/// plain mode the current profile (or the first) is picked without prompting.
 /// Returns the selected profile name, or None if cancelled
-pub fn select_profile(profiles: &[String], current_profile: Option<&str>) -> Option<String> {
+pub fn select_profile(
+    profiles: &[String],
+    current_profile: Option<&str>,
+    details: bool,
+    fuzzy: bool,
+) -> Result<Option<String>> {
     if profiles.is_empty() {
         println!("No profiles found. Use 'claudectx save <name>' to create one.");
-        return None;
+        return Ok(None);
     }
 
-    // Build display items with profile info
-    let items: Vec<String> = profiles
-        .iter()
-        .map(|name| {
-            let path = get_profile_path(name);
-            let config: serde_json::Value = serde_json::from_str(
-                &std::fs::read_to_string(&path).expect("Failed to read profile"),
-            )
-            .expect("Failed to parse profile");
-
-            let account = get_oauth_account(&config);
+    let settings = load_settings()?;
+    let sort = settings.sort.unwrap_or_default();
+    let fuzzy = fuzzy || settings.picker == Some(PickerStyle::Fuzzy);
+    let details = details || fuzzy;
+    let usage = load_usage()?;
+    let mut profiles = profiles.to_vec();
+    sort_profiles(&mut profiles, sort, &usage);
+
+    // Build display items with profile info, leaving out broken profiles
+    let now = now_secs();
+    let mut items = Vec::new();
+    profiles.retain(|name| {
+        let item = read_profile_summary(name).and_then(|config| {
             let marker = if current_profile == Some(name.as_str()) {
                 " *"
             } else {
                 ""
             };
-            format!(
-                "{} - {} @ {}{}",
-                name, account.display_name, account.organization_name, marker
-            )
-        })
-        .collect();
-
-    // Find current selection index (default to first if not found)
-    let default_index = current_profile
-        .and_then(|current| profiles.iter().position(|name| name == current))
+            let info = if details {
+                let last_used = format_last_used(usage.get(name).copied(), now);
+                match get_oauth_account(&config) {
+                    Ok(account) => format!(" ({}, {})", account.organization_role, last_used),
+                    Err(_) => format!(" ({})", last_used),
+                }
+            } else {
+                String::new()
+            };
+            Ok(format!(
+                "{} - {}{}{}",
+                name,
+                describe_account(&config)?,
+                info,
+                marker
+            ))
+        });
+        match item {
+            Ok(item) => {
+                items.push(item);
+                true
+            }
+            Err(err) => {
+                eprintln!("Warning: skipped profile '{}' ({})", name, err);
+                false
+            }
+        }
+    });
+    if profiles.is_empty() {
+        println!("No usable profiles found. Use 'claudectx save <name>' to create one.");
+        return Ok(None);
+    }
+
+    if is_plain() {
+        let selected = current_profile
+            .filter(|current| profiles.iter().any(|name| name == current))
+            .unwrap_or(&profiles[0]);
+        eprintln!("Select Claude profile: {}", selected);
+        return Ok(Some(selected.to_string()));
+    }
+
+    // Highlight the default profile, then (when sorting by recency) the most
+    // recently used one, then the current one, then the first
+    let position = |wanted: &str| profiles.iter().position(|name| name == wanted);
+    let most_recent = profiles
+        .first()
+        .filter(|name| sort == ProfileSort::Recent && usage.contains_key(name.as_str()));
+    let default_index = get_default_profile()
+        .and_then(|default| position(&default))
+        .or_else(|| most_recent.and_then(|name| position(name)))
+        .or_else(|| current_profile.and_then(position))
+        .unwrap_or(0);
+
+    let selection = if fuzzy {
+        FuzzySelect::new()
+            .with_prompt("Select Claude profile (type to filter)")
+            .default(default_index)
+            .items(&items)
+            .interact_opt()
+    } else {
+        Select::new()
+            .with_prompt("Select Claude profile")
+            .default(default_index)
+            .items(&items)
+            .interact_opt()
+    }
+    .map_err(|e| Error::Prompt(format!("Failed to display selection UI: {}", e)))?;
+
+    Ok(selection.map(|idx| profiles[idx].clone()))
+}
+
+/// Print the account claude is about to run as (display name, email and
+/// organization of `config`) as a framed banner on stderr
+pub fn print_account_banner(config: &serde_json::Value) -> Result<()> {
+    let (display_name, organization_name) = account_names(config)?;
+    let mut lines = vec![match account_email(config) {
+        Some(email) => format!("Claude account: {} <{}>", display_name, email),
+        None => format!("Claude account: {}", display_name),
+    }];
+    if let Some(organization_name) = organization_name {
+        lines.push(format!("Organization:   {}", organization_name));
+    }
+
+    let width = lines
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
         .unwrap_or(0);
+    let rule = "=".repeat(width + 4);
+    eprintln!("{}", style(&rule).yellow().for_stderr());
+    for line in &lines {
+        eprintln!("  {}", style(line).bold().for_stderr());
+    }
+    eprintln!("{}", style(&rule).yellow().for_stderr());
+    Ok(())
+}
+
+/// One profile row of the `list --wide` table
+pub struct ProfileRow {
+    pub name: String,
+    pub display_name: String,
+    pub organization_name: String,
+    pub current: bool,
+}
 
-    let selection = Select::new()
-        .with_prompt("Select Claude profile")
-        .default(default_index)
-        .items(&items)
-        .interact_opt()
-        .expect("Failed to display selection UI");
+/// Print profiles as left-aligned columns (name | display | org | current)
+/// sized to the longest entry. Colors are only used when stdout is a terminal.
+pub fn print_profile_table(rows: &[ProfileRow]) {
+    let headers = ["NAME", "DISPLAY", "ORGANIZATION", "CURRENT"];
+    let width = |header: &str, cell: fn(&ProfileRow) -> &str| {
+        rows.iter()
+            .map(|row| cell(row).chars().count())
+            .chain(std::iter::once(header.len()))
+            .max()
+            .unwrap_or(0)
+    };
+    let name_width = width(headers[0], |row| &row.name);
+    let display_width = width(headers[1], |row| &row.display_name);
+    let org_width = width(headers[2], |row| &row.organization_name);
 
-    selection.map(|idx| profiles[idx].clone())
+    let pad = |text: &str, width: usize| format!("{:<width$}", text, width = width);
+
+    println!(
+        "{}",
+        style(format!(
+            "{}  {}  {}  {}",
+            pad(headers[0], name_width),
+            pad(headers[1], display_width),
+            pad(headers[2], org_width),
+            headers[3]
+        ))
+        .bold()
+        .for_stdout()
+    );
+
+    for row in rows {
+        let name = style(pad(&row.name, name_width)).cyan().for_stdout();
+        let current = if row.current {
+            style("*").green().bold().for_stdout().to_string()
+        } else {
+            String::new()
+        };
+        let line = format!(
+            "{}  {}  {}  {}",
+            name,
+            pad(&row.display_name, display_width),
+            pad(&row.organization_name, org_width),
+            current
+        );
+        println!("{}", line.trim_end());
+    }
+}
+
+/// Human-readable file size, e.g. "512 B", "1.5 KB", "2.0 MB"
+pub fn format_size(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = 1024 * KB;
+    match bytes {
+        0..KB => format!("{} B", bytes),
+        KB..MB => format!("{:.1} KB", bytes as f64 / KB as f64),
+        _ => format!("{:.1} MB", bytes as f64 / MB as f64),
+    }
 }
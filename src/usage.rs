@@ -0,0 +1,362 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::strip_bom;
+use crate::error::{Error, Result};
+use crate::profiles::{claudectx_dir, ensure_profiles_dir, state_write_error};
+use crate::settings::ProfileSort;
+
+/// Last-used time (seconds since the Unix epoch) per profile slug
+pub type Usage = BTreeMap<String, u64>;
+
+/// Get the path to the usage file (~/.claudectx/.usage.json)
+pub fn usage_path() -> PathBuf {
+    claudectx_dir().join(".usage.json")
+}
+
+/// Current time in seconds since the Unix epoch
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Load usage timestamps, or an empty map if none were recorded yet
+pub fn load_usage() -> Result<Usage> {
+    let path = usage_path();
+    if !path.exists() {
+        return Ok(Usage::new());
+    }
+
+    let content = fs::read_to_string(&path).map_err(|e| Error::io("Failed to read usage", e))?;
+    serde_json::from_str(strip_bom(&content)).map_err(|e| Error::parse("Failed to parse usage", e))
+}
+
+/// Record that a profile was just used
+pub fn record_usage(slug: &str) -> Result<()> {
+    let mut usage = load_usage()?;
+    usage.insert(slug.to_string(), now_secs());
+
+    ensure_profiles_dir()?;
+    let json = serde_json::to_string_pretty(&usage)
+        .map_err(|e| Error::parse("Failed to serialize usage", e))?;
+    fs::write(usage_path(), json).map_err(|e| state_write_error("Failed to write usage", e))
+}
+
+/// Carry a profile's usage timestamp over to its new name
+pub fn rename_usage(old: &str, new: &str) -> Result<()> {
+    let mut usage = load_usage()?;
+    let Some(used) = usage.remove(old) else {
+        return Ok(());
+    };
+    usage.insert(new.to_string(), used);
+
+    let json = serde_json::to_string_pretty(&usage)
+        .map_err(|e| Error::parse("Failed to serialize usage", e))?;
+    fs::write(usage_path(), json).map_err(|e| state_write_error("Failed to write usage", e))
+}
+
+/// Most switches kept in the history log; older ones are dropped
+const HISTORY_LIMIT: usize = 500;
+
+/// Get the path to the switch history log (~/.claudectx/.history), one
+/// `<unix secs>\t<slug>` line per switch, oldest first
+pub fn history_path() -> PathBuf {
+    claudectx_dir().join(".history")
+}
+
+/// Parse history log lines, skipping malformed ones
+fn parse_history(content: &str) -> Vec<(u64, String)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (secs, slug) = line.split_once('\t')?;
+            Some((secs.parse().ok()?, slug.to_string()))
+        })
+        .collect()
+}
+
+/// Load recorded switches, oldest first
+pub fn load_history() -> Result<Vec<(u64, String)>> {
+    let path = history_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path).map_err(|e| Error::io("Failed to read history", e))?;
+    Ok(parse_history(&content))
+}
+
+/// The profile switched to before the latest one: the most recent history
+/// entry naming a different profile than the last switch
+pub fn previous_profile(history: &[(u64, String)]) -> Option<&str> {
+    let (_, latest) = history.last()?;
+    history
+        .iter()
+        .rev()
+        .map(|(_, slug)| slug.as_str())
+        .find(|slug| *slug != latest)
+}
+
+/// Append a switch to the history log, dropping the oldest entries beyond
+/// HISTORY_LIMIT
+pub fn record_history(slug: &str) -> Result<()> {
+    ensure_profiles_dir()?;
+    let path = history_path();
+    let line = format!("{}\t{}\n", now_secs(), slug);
+
+    let mut history = load_history()?;
+    if history.len() < HISTORY_LIMIT {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| state_write_error("Failed to open history", e))?;
+        return file
+            .write_all(line.as_bytes())
+            .map_err(|e| state_write_error("Failed to write history", e));
+    }
+
+    history.drain(..=history.len() - HISTORY_LIMIT);
+    let mut content: String = history
+        .iter()
+        .map(|(secs, slug)| format!("{}\t{}\n", secs, slug))
+        .collect();
+    content.push_str(&line);
+    fs::write(&path, content).map_err(|e| state_write_error("Failed to write history", e))
+}
+
+/// Last email address seen in ~/.claude.json per account UUID
+pub type SeenAccounts = BTreeMap<String, String>;
+
+/// Get the path to the seen-accounts cache (~/.claudectx/.accounts.json)
+pub fn seen_accounts_path() -> PathBuf {
+    claudectx_dir().join(".accounts.json")
+}
+
+/// Load the seen-accounts cache, or an empty map if none was recorded yet
+pub fn load_seen_accounts() -> Result<SeenAccounts> {
+    let path = seen_accounts_path();
+    if !path.exists() {
+        return Ok(SeenAccounts::new());
+    }
+
+    let content =
+        fs::read_to_string(&path).map_err(|e| Error::io("Failed to read seen accounts", e))?;
+    serde_json::from_str(strip_bom(&content))
+        .map_err(|e| Error::parse("Failed to parse seen accounts", e))
+}
+
+/// Remember the email an account UUID had in the live config
+pub fn record_seen_account(uuid: &str, email: &str) -> Result<()> {
+    let mut seen = load_seen_accounts()?;
+    if seen.get(uuid).map(String::as_str) == Some(email) {
+        return Ok(());
+    }
+    seen.insert(uuid.to_string(), email.to_string());
+
+    ensure_profiles_dir()?;
+    let json = serde_json::to_string_pretty(&seen)
+        .map_err(|e| Error::parse("Failed to serialize seen accounts", e))?;
+    fs::write(seen_accounts_path(), json)
+        .map_err(|e| state_write_error("Failed to write seen accounts", e))
+}
+
+/// Parse a relative window such as `7d`, `12h` or `2w` into seconds
+pub fn parse_window(value: &str) -> std::result::Result<u64, String> {
+    let invalid = || format!("invalid window '{}': expected e.g. 12h, 7d or 2w", value);
+    let unit_start = value.char_indices().last().map_or(0, |(index, _)| index);
+    let (amount, unit) = value.split_at(unit_start);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+    let unit_secs = match unit {
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    Ok(amount.saturating_mul(unit_secs))
+}
+
+/// Whether a profile was used within the last `window` seconds. Profiles
+/// without recorded usage never match.
+pub fn used_within(usage: &Usage, slug: &str, window: u64, now: u64) -> bool {
+    usage
+        .get(slug)
+        .is_some_and(|&used| used >= now.saturating_sub(window))
+}
+
+/// Human-readable age of a usage timestamp, e.g. "5m ago", "3d ago"
+pub fn format_last_used(used: Option<u64>, now: u64) -> String {
+    let Some(used) = used else {
+        return "never used".to_string();
+    };
+
+    let elapsed = now.saturating_sub(used);
+    match elapsed {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", elapsed / 60),
+        3600..=86_399 => format!("{}h ago", elapsed / 3600),
+        86_400..=604_799 => format!("{}d ago", elapsed / 86_400),
+        _ => format!("{}w ago", elapsed / 604_800),
+    }
+}
+
+/// How usage timestamps are printed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TimeFormat {
+    /// Age such as "2d ago"
+    #[default]
+    Relative,
+    /// RFC 3339 timestamp in UTC
+    Iso,
+}
+
+/// Format a usage timestamp as an RFC 3339 UTC date, e.g. "2024-01-31T09:05:00Z"
+pub fn format_rfc3339(secs: u64) -> String {
+    let days = secs / 86_400;
+    let rem = secs % 86_400;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// Format a usage timestamp in the requested style ("never used" when unset)
+pub fn format_usage_time(used: Option<u64>, now: u64, format: TimeFormat) -> String {
+    match (format, used) {
+        (TimeFormat::Iso, Some(used)) => format_rfc3339(used),
+        _ => format_last_used(used, now),
+    }
+}
+
+/// Sort profile names in place. `Recent` puts the most recently used first;
+/// profiles without usage data come last, by name.
+pub fn sort_profiles(profiles: &mut [String], sort: ProfileSort, usage: &Usage) {
+    match sort {
+        ProfileSort::Name => profiles.sort(),
+        ProfileSort::Recent => profiles.sort_by(|a, b| {
+            let (used_a, used_b) = (usage.get(a), usage.get(b));
+            used_b.cmp(&used_a).then_with(|| a.cmp(b))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_history_skips_malformed_lines() {
+        let history = parse_history("100\twork\ngarbage\nabc\tx\n200\tpersonal\n");
+
+        assert_eq!(
+            history,
+            vec![(100, "work".to_string()), (200, "personal".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_previous_profile_skips_repeated_switches() {
+        let history = parse_history("1\talpha\n2\twork\n3\tpersonal\n4\tpersonal\n");
+        assert_eq!(previous_profile(&history), Some("work"));
+
+        let history = parse_history("1\twork\n2\twork\n");
+        assert_eq!(previous_profile(&history), None);
+        assert_eq!(previous_profile(&[]), None);
+    }
+
+    #[test]
+    fn test_format_rfc3339() {
+        assert_eq!(format_rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_rfc3339(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_rfc3339(1_706_691_900), "2024-01-31T09:05:00Z");
+        assert_eq!(format_usage_time(None, 1000, TimeFormat::Iso), "never used");
+        assert_eq!(
+            format_usage_time(Some(0), 300, TimeFormat::Relative),
+            "5m ago"
+        );
+    }
+
+    #[test]
+    fn test_sort_profiles_recent_first_then_unused_by_name() {
+        let usage = Usage::from([("old".to_string(), 100), ("new".to_string(), 200)]);
+        let mut profiles = vec![
+            "zeta".to_string(),
+            "old".to_string(),
+            "alpha".to_string(),
+            "new".to_string(),
+        ];
+
+        sort_profiles(&mut profiles, ProfileSort::Recent, &usage);
+
+        assert_eq!(profiles, vec!["new", "old", "alpha", "zeta"]);
+    }
+
+    #[test]
+    fn test_parse_window() {
+        assert_eq!(parse_window("12h"), Ok(12 * 3600));
+        assert_eq!(parse_window("7d"), Ok(7 * 86400));
+        assert_eq!(parse_window("2w"), Ok(14 * 86400));
+        assert!(parse_window("7").is_err());
+        assert!(parse_window("d").is_err());
+        assert!(parse_window("7m").is_err());
+    }
+
+    #[test]
+    fn test_parse_window_rejects_non_ascii() {
+        assert!(parse_window("7é").is_err());
+        assert!(parse_window("é").is_err());
+        assert!(parse_window("٧d").is_err());
+        assert!(parse_window("").is_err());
+    }
+
+    #[test]
+    fn test_used_within_excludes_unused_and_old() {
+        let usage = Usage::from([("recent".to_string(), 950), ("old".to_string(), 100)]);
+
+        assert!(used_within(&usage, "recent", 100, 1000));
+        assert!(!used_within(&usage, "old", 100, 1000));
+        assert!(!used_within(&usage, "never", 100, 1000));
+    }
+
+    #[test]
+    fn test_format_last_used() {
+        assert_eq!(format_last_used(None, 1000), "never used");
+        assert_eq!(format_last_used(Some(990), 1000), "just now");
+        assert_eq!(format_last_used(Some(0), 300), "5m ago");
+        assert_eq!(format_last_used(Some(0), 2 * 3600), "2h ago");
+        assert_eq!(format_last_used(Some(0), 3 * 86_400), "3d ago");
+        assert_eq!(format_last_used(Some(0), 15 * 86_400), "2w ago");
+    }
+
+    #[test]
+    fn test_sort_profiles_by_name() {
+        let usage = Usage::from([("b".to_string(), 100)]);
+        let mut profiles = vec!["c".to_string(), "b".to_string(), "a".to_string()];
+
+        sort_profiles(&mut profiles, ProfileSort::Name, &usage);
+
+        assert_eq!(profiles, vec!["a", "b", "c"]);
+    }
+}
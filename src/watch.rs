@@ -0,0 +1,71 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher};
+
+use crate::config::claude_config_path;
+use crate::error::{Error, Result};
+use crate::profiles::{profile_exists, reapply_if_drifted, slugify, SwitchOptions};
+
+/// Keep profile `name` applied: re-patch ~/.claude.json whenever another tool
+/// rewrites it with different account fields, until Ctrl-C. The directory is
+/// watched rather than the file, since tools often replace the file by renaming
+/// a new one over it.
+pub fn watch_profile(name: &str, options: &SwitchOptions) -> Result<()> {
+    let slug = slugify(name);
+    if !profile_exists(name)? {
+        return Err(Error::profile_not_found(&slug));
+    }
+
+    let config_path = claude_config_path();
+    let (Some(dir), Some(file_name)) = (config_path.parent(), config_path.file_name()) else {
+        return Err(Error::Other(format!(
+            "Can't watch Claude config at {:?}",
+            config_path
+        )));
+    };
+
+    // Stop between events rather than mid-write
+    let stop = Arc::new(AtomicBool::new(false));
+    let handler_stop = Arc::clone(&stop);
+    ctrlc::set_handler(move || handler_stop.store(true, Ordering::SeqCst))
+        .map_err(|e| Error::Other(format!("Failed to install Ctrl-C handler: {}", e)))?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)
+        .map_err(|e| Error::Other(format!("Failed to start watching: {}", e)))?;
+    watcher
+        .watch(dir, RecursiveMode::NonRecursive)
+        .map_err(|e| Error::Other(format!("Failed to watch {:?}: {}", dir, e)))?;
+
+    if reapply_if_drifted(name, options)? {
+        println!("Applied '{}'", slug);
+    }
+    println!(
+        "Watching {} to keep '{}' applied (Ctrl-C to stop)",
+        config_path.display(),
+        slug
+    );
+
+    while !stop.load(Ordering::SeqCst) {
+        match rx.recv_timeout(Duration::from_millis(200)) {
+            Ok(Ok(event)) => {
+                let touches_config = event
+                    .paths
+                    .iter()
+                    .any(|path| path.file_name() == Some(file_name));
+                if touches_config && reapply_if_drifted(name, options)? {
+                    println!("Config changed; re-applied '{}'", slug);
+                }
+            }
+            Ok(Err(err)) => eprintln!("Warning: watch error: {}", err),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    println!("Stopped watching.");
+    Ok(())
+}
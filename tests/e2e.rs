@@ -112,6 +112,7 @@ impl TestEnv {
         let mut cmd = Command::cargo_bin("claudectx").expect("Failed to find binary");
         // Use CLAUDECTX_HOME for reliable cross-platform home directory override
         cmd.env("CLAUDECTX_HOME", self.home_path());
+        cmd.env_remove("CLAUDE_CONFIG_DIR");
         assert_cmd::Command::from_std(cmd)
     }
 }
@@ -470,7 +471,7 @@ fn test_launch_nonexistent_profile_panics() {
 
     // Try to launch nonexistent profile (will prompt to create)
     // Since we can't interact with prompts in tests, this should fail
-    // The test binary runs without a TTY so dialoguer will fail
+    // The test binary runs without a TTY, so confirmation requires --yes
     env.cmd().arg("nonexistent").assert().failure();
 }
 
@@ -532,7 +533,7 @@ fn test_launch_switches_account_between_profiles() {
 // =============================================================================
 
 #[test]
-fn test_malformed_profile_panics() {
+fn test_malformed_profile_is_reported() {
     let env = TestEnv::new();
     // Write invalid JSON to profile
     fs::create_dir_all(env.claudectx_dir()).expect("Failed to create dir");
@@ -542,6 +543,13 @@ fn test_malformed_profile_panics() {
     env.cmd()
         .arg("list")
         .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "(1 profile skipped: 1 parse error)",
+        ));
+    env.cmd()
+        .args(["show", "bad"])
+        .assert()
         .failure()
         .stderr(predicate::str::contains("Failed to parse profile"));
 }
@@ -675,7 +683,8 @@ fn test_delete_help() {
         .assert()
         .success()
         .stdout(predicate::str::contains("Delete a profile"))
-        .stdout(predicate::str::contains("<NAME>"));
+        .stdout(predicate::str::contains("[NAME]"))
+        .stdout(predicate::str::contains("--all"));
 }
 
 #[test]
@@ -777,10 +786,34 @@ fn test_help_includes_login_command() {
 // =============================================================================
 
 impl TestEnv {
-    /// Get path to .claude.json.bak in test environment
+    /// Get path to the legacy .claude.json.bak in test environment
     fn claude_config_backup_path(&self) -> std::path::PathBuf {
         self.home_dir.path().join(".claude.json.bak")
     }
+
+    /// Default backups directory (~/.claudectx/backups)
+    fn backups_dir(&self) -> std::path::PathBuf {
+        self.claudectx_dir().join("backups")
+    }
+
+    /// File names in a backups directory, sorted (empty if it doesn't exist)
+    fn backup_files_in(&self, dir: &std::path::Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.file_name().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    /// File names in ~/.claudectx/backups, sorted
+    fn backup_files(&self) -> Vec<String> {
+        self.backup_files_in(&self.backups_dir())
+    }
 }
 
 #[test]
@@ -1179,12 +1212,18 @@ fn test_migration_resolves_symlink_and_converts_profiles() {
         "Portable field should be stripped from slim profile"
     );
 
-    // 4. Backup should exist
-    let backup_path = env.profile_path("old-profile").with_extension("json.bak");
-    assert!(
-        backup_path.exists(),
+    // 4. Backup should exist in ~/.claudectx/backups
+    let backups: Vec<String> = env
+        .backup_files()
+        .into_iter()
+        .filter(|name| name.starts_with("old-profile.claude.json-"))
+        .collect();
+    assert_eq!(
+        backups.len(),
+        1,
         "Backup file should be created during migration"
     );
+    let backup_path = env.backups_dir().join(&backups[0]);
 
     // 5. Backup should contain the original full content
     let backup_content = fs::read_to_string(&backup_path).expect("read backup");
@@ -1301,3 +1340,6547 @@ fn test_migration_is_one_shot() {
         stdout2
     );
 }
+
+// =============================================================================
+// CLAUDE ARGUMENT PASSTHROUGH TESTS
+// =============================================================================
+
+#[cfg(unix)]
+impl TestEnv {
+    /// Install a fake `claude` executable that records its arguments (one per
+    /// line) to `claude-args.txt` in the test home directory.
+    fn install_fake_claude(&self) -> std::path::PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let bin_dir = self.home_path().join("bin");
+        fs::create_dir_all(&bin_dir).expect("Failed to create bin dir");
+        let script = bin_dir.join("claude");
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\nfor a in \"$@\"; do echo \"$a\"; done > {:?}\n",
+                self.home_path().join("claude-args.txt")
+            ),
+        )
+        .expect("Failed to write fake claude");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))
+            .expect("Failed to chmod fake claude");
+        bin_dir
+    }
+
+    /// Run claudectx with the fake `claude` first in PATH
+    fn cmd_with_fake_claude(&self) -> assert_cmd::Command {
+        let bin_dir = self.install_fake_claude();
+        let path = std::env::var("PATH").unwrap_or_default();
+        let mut cmd = self.cmd();
+        cmd.env("PATH", format!("{}:{}", bin_dir.display(), path));
+        cmd
+    }
+
+    /// Arguments received by the fake `claude`
+    fn recorded_claude_args(&self) -> Vec<String> {
+        fs::read_to_string(self.home_path().join("claude-args.txt"))
+            .expect("Fake claude was not launched")
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_claude_arg_is_passed_through() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd_with_fake_claude()
+        .args(["work", "--claude-arg", "--resume", "--claude-arg", "foo"])
+        .assert()
+        .success();
+
+    assert_eq!(env.recorded_claude_args(), vec!["--resume", "foo"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_claude_arg_precedes_double_dash_args() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd_with_fake_claude()
+        .args(["work", "--claude-arg", "first", "--", "second", "third"])
+        .assert()
+        .success();
+
+    assert_eq!(env.recorded_claude_args(), vec!["first", "second", "third"]);
+}
+
+// =============================================================================
+// STATUS COMMAND TESTS
+// =============================================================================
+
+#[test]
+fn test_status_shows_account_and_matching_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "User work <user-work@example.com> @ Org work, profile 'work'",
+        ));
+}
+
+#[test]
+fn test_status_reports_unsaved_account() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("fresh"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("unsaved account"));
+}
+
+#[test]
+fn test_status_fails_without_claude_config() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .arg("status")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Failed to read Claude config"));
+}
+
+// =============================================================================
+// PROFILE EXTENSION SETTING TESTS
+// =============================================================================
+
+impl TestEnv {
+    /// Write ~/.claudectx/.settings.json
+    fn write_settings(&self, settings: &serde_json::Value) {
+        fs::create_dir_all(self.claudectx_dir()).expect("Failed to create claudectx dir");
+        fs::write(
+            self.claudectx_dir().join(".settings.json"),
+            serde_json::to_string_pretty(settings).expect("serialize"),
+        )
+        .expect("Failed to write settings");
+    }
+}
+
+#[test]
+fn test_custom_profile_extension_is_used_by_save_and_list() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.write_settings(&json!({ "profile_extension": ".profile.json" }));
+
+    env.cmd().args(["save", "work"]).assert().success();
+
+    assert!(env.claudectx_dir().join("work.profile.json").exists());
+    assert!(!env.profile_path("work").exists());
+
+    let output = env.cmd().arg("list").assert().success();
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout);
+    assert!(stdout.contains("work - User work @ Org work *"));
+    assert!(!stdout.contains(".settings"));
+}
+
+#[test]
+fn test_custom_profile_extension_ignores_other_suffixes() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("legacy", &sample_account("legacy"));
+    env.write_settings(&json!({ "profile_extension": ".profile.json" }));
+
+    env.cmd()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No profiles found."));
+}
+
+#[test]
+fn test_config_rejects_escaping_profile_extension() {
+    let env = TestEnv::new();
+
+    for ext in ["/../../x", ".json/../../y", ".a\\b", "json", "."] {
+        env.cmd()
+            .args(["config", "profile_extension", ext])
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains(
+                "Invalid value '".to_string() + ext + "' for 'profile_extension'",
+            ));
+    }
+    assert!(!env.claudectx_dir().join(".settings.json").exists());
+}
+
+#[test]
+fn test_escaping_profile_extension_in_settings_file_is_rejected() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.write_settings(&json!({ "profile_extension": "/../../x" }));
+
+    env.cmd()
+        .args(["save", "work"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("Invalid claudectx settings"));
+    assert!(!env.home_path().join("work").exists());
+    assert!(!env.home_path().join("x").exists());
+}
+
+#[test]
+fn test_changing_profile_extension_warns_about_hidden_profiles() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("home", &sample_account("home"));
+
+    env.cmd()
+        .args(["config", "profile_extension", ".profile.json"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "2 profile(s) saved as *.claude.json are hidden until renamed to *.profile.json",
+        ));
+
+    // Resetting brings them back, with nothing hidden under the custom suffix
+    env.cmd()
+        .args(["config", "profile_extension", ""])
+        .assert()
+        .success()
+        .stderr(predicate::str::is_empty());
+}
+
+// =============================================================================
+// ERROR REPORTING TESTS
+// =============================================================================
+
+/// Parse the JSON error line printed on stderr with --error-json
+fn parse_error_json(output: &std::process::Output) -> serde_json::Value {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    serde_json::from_str(stderr.trim()).expect("stderr should be a single JSON object")
+}
+
+#[test]
+fn test_error_json_missing_config() {
+    let env = TestEnv::new();
+
+    let output = env.cmd().args(["status", "--error-json"]).assert().code(2);
+    let error = parse_error_json(output.get_output());
+    assert_eq!(error["code"], 2);
+    assert!(error["error"]
+        .as_str()
+        .unwrap()
+        .contains("Failed to read Claude config"));
+}
+
+#[test]
+fn test_error_json_parse_error() {
+    let env = TestEnv::new();
+    fs::create_dir_all(env.claudectx_dir()).expect("Failed to create dir");
+    fs::write(env.profile_path("bad"), "not valid json {{{").expect("write");
+
+    let output = env
+        .cmd()
+        .args(["--error-json", "show", "bad"])
+        .assert()
+        .code(3);
+    let error = parse_error_json(output.get_output());
+    assert_eq!(error["code"], 3);
+    assert!(error["error"]
+        .as_str()
+        .unwrap()
+        .contains("Failed to parse profile"));
+}
+
+#[test]
+fn test_error_json_missing_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+
+    let output = env
+        .cmd()
+        .args(["delete", "ghost", "--error-json"])
+        .assert()
+        .code(4);
+    let error = parse_error_json(output.get_output());
+    assert_eq!(error["code"], 4);
+    assert_eq!(error["error"], "Profile 'ghost' not found");
+}
+
+#[test]
+fn test_errors_are_plain_text_by_default() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+
+    env.cmd()
+        .args(["delete", "ghost"])
+        .assert()
+        .code(4)
+        .stderr(predicate::str::diff("Error: Profile 'ghost' not found\n"));
+}
+
+// =============================================================================
+// SWITCH VALIDATION TESTS
+// =============================================================================
+
+#[test]
+fn test_switch_refuses_profile_without_account_uuid() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    let original = fs::read_to_string(env.claude_config_path()).expect("read config");
+
+    fs::create_dir_all(env.claudectx_dir()).expect("mkdir");
+    fs::write(
+        env.profile_path("partial"),
+        serde_json::to_string_pretty(&json!({ "userID": "user-partial" })).expect("serialize"),
+    )
+    .expect("write profile");
+
+    env.cmd()
+        .arg("partial")
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "Profile 'partial' has no oauthAccount.accountUuid",
+        ));
+
+    // Original config left intact
+    let after = fs::read_to_string(env.claude_config_path()).expect("read config");
+    assert_eq!(original, after);
+}
+
+// =============================================================================
+// LIST --STALE TESTS
+// =============================================================================
+
+#[test]
+fn test_list_stale_shows_only_incomplete_profiles() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("good", &sample_account("good"));
+    env.create_profile("no-email", &json!({ "accountUuid": "uuid-no-email" }));
+    fs::write(
+        env.profile_path("empty"),
+        serde_json::to_string_pretty(&json!({ "userID": "user" })).expect("serialize"),
+    )
+    .expect("write profile");
+
+    let output = env.cmd().args(["list", "--stale"]).assert().success();
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout);
+
+    assert!(!stdout.contains("good"));
+    assert!(stdout.contains("no-email - missing emailAddress"));
+    assert!(stdout.contains("empty - missing accountUuid, emailAddress"));
+}
+
+#[test]
+fn test_list_stale_reports_unparseable_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("no-email", &json!({ "accountUuid": "uuid-no-email" }));
+    fs::write(env.profile_path("broken"), "not json").expect("write");
+
+    let output = env.cmd().args(["list", "--stale"]).assert().success();
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout);
+
+    assert!(stdout.contains("broken - unreadable (Failed to parse profile"));
+    assert!(stdout.contains("no-email - missing emailAddress"));
+}
+
+#[test]
+fn test_list_stale_when_all_profiles_are_complete() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("good", &sample_account("good"));
+
+    env.cmd()
+        .args(["list", "--stale"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No stale profiles found."));
+}
+
+// =============================================================================
+// CLAUDE CONFIG PATH RESOLUTION TESTS
+// =============================================================================
+
+#[test]
+fn test_claude_config_path_override() {
+    let env = TestEnv::new();
+    let custom_path = env.home_path().join("custom-claude.json");
+    fs::write(
+        &custom_path,
+        serde_json::to_string_pretty(&json!({
+            "oauthAccount": sample_account("custom"),
+            "hasCompletedOnboarding": true
+        }))
+        .expect("serialize"),
+    )
+    .expect("write custom config");
+    // Default location holds a different account and must be ignored
+    env.create_claude_config(&sample_account("default"));
+
+    env.cmd()
+        .env("CLAUDE_CONFIG_PATH", &custom_path)
+        .args(["save", "custom"])
+        .assert()
+        .success();
+
+    assert_eq!(
+        env.read_profile("custom")["oauthAccount"]["accountUuid"],
+        "uuid-custom"
+    );
+}
+
+#[test]
+fn test_claude_config_path_override_is_patched_on_switch() {
+    let env = TestEnv::new();
+    let custom_path = env.home_path().join("custom-claude.json");
+    fs::write(&custom_path, "{}").expect("write custom config");
+    env.create_claude_config(&sample_account("default"));
+    env.create_profile("work", &sample_account("work"));
+
+    let _ = env
+        .cmd()
+        .env("CLAUDE_CONFIG_PATH", &custom_path)
+        .arg("work")
+        .assert();
+
+    let custom: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&custom_path).expect("read")).expect("parse");
+    assert_eq!(custom["oauthAccount"]["accountUuid"], "uuid-work");
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-default"
+    );
+}
+
+// =============================================================================
+// SWITCH --MERGE TESTS
+// =============================================================================
+
+#[test]
+fn test_switch_merge_keeps_fields_absent_from_profile() {
+    let env = TestEnv::new();
+    let config = json!({
+        "oauthAccount": sample_account("current"),
+        "userID": "current-user",
+        "groveConfigCache": {"current": true},
+        "hasCompletedOnboarding": true
+    });
+    fs::write(
+        env.claude_config_path(),
+        serde_json::to_string_pretty(&config).expect("serialize"),
+    )
+    .expect("write config");
+    env.create_profile("work", &sample_account("work"));
+
+    let _ = env.cmd().args(["work", "--merge"]).assert();
+
+    let config = env.read_claude_config();
+    assert_eq!(config["oauthAccount"]["accountUuid"], "uuid-work");
+    assert_eq!(config["userID"], "user-id-work");
+    // Not in the profile: kept with --merge
+    assert_eq!(config["groveConfigCache"]["current"], true);
+}
+
+#[test]
+fn test_switch_without_merge_removes_fields_absent_from_profile() {
+    let env = TestEnv::new();
+    let config = json!({
+        "oauthAccount": sample_account("current"),
+        "groveConfigCache": {"current": true}
+    });
+    fs::write(
+        env.claude_config_path(),
+        serde_json::to_string_pretty(&config).expect("serialize"),
+    )
+    .expect("write config");
+    env.create_profile("work", &sample_account("work"));
+
+    let _ = env.cmd().arg("work").assert();
+
+    assert!(env.read_claude_config().get("groveConfigCache").is_none());
+}
+
+// =============================================================================
+// SWITCH SUMMARY TESTS
+// =============================================================================
+
+#[test]
+fn test_switch_prints_summary() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+
+    // oauthAccount changed, userID added
+    env.cmd()
+        .arg("work")
+        .assert()
+        .stdout(predicate::str::contains(
+            "Switched to 'work' (user-work@example.com @ Org work), 2 account fields updated",
+        ));
+
+    // Already active: nothing to change
+    env.cmd()
+        .arg("work")
+        .assert()
+        .stdout(predicate::str::contains("0 account fields updated"));
+}
+
+#[test]
+fn test_switch_quiet_hides_summary() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["work", "--quiet"])
+        .assert()
+        .stdout(predicate::str::contains("Switched to").not());
+}
+
+// =============================================================================
+// LIST --COUNT TESTS
+// =============================================================================
+
+#[test]
+fn test_list_count() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("personal", &sample_account("personal"));
+    // Malformed profiles are counted without being parsed
+    fs::write(env.profile_path("broken"), "not json").expect("write");
+
+    env.cmd()
+        .args(["list", "--count"])
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("3\n"));
+}
+
+#[test]
+fn test_list_count_without_profiles() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["list", "--count"])
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("0\n"));
+}
+
+// =============================================================================
+// DEFAULT PROFILE TESTS
+// =============================================================================
+
+#[test]
+fn test_default_command_writes_default_file() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["default", "Work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Default profile set to 'work'"));
+
+    let content = fs::read_to_string(env.claudectx_dir().join(".default")).expect("read");
+    assert_eq!(content.trim(), "work");
+}
+
+#[test]
+fn test_default_command_rejects_unknown_profile() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["default", "ghost"])
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("Profile 'ghost' not found"));
+    assert!(!env.claudectx_dir().join(".default").exists());
+}
+
+#[test]
+fn test_no_args_without_tty_launches_default_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("personal", &sample_account("personal"));
+    env.cmd().args(["default", "personal"]).assert().success();
+
+    // No TTY in tests: the default is used instead of prompting
+    let _ = env.cmd().assert();
+
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-personal"
+    );
+}
+
+#[test]
+fn test_no_args_without_tty_or_default_fails() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "No default profile set; pass a profile name",
+        ))
+        .stderr(predicate::str::contains("selection UI").not());
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-current"
+    );
+}
+
+#[test]
+fn test_default_file_is_not_listed_as_profile() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    env.cmd().args(["default", "work"]).assert().success();
+
+    env.cmd()
+        .args(["list", "--count"])
+        .assert()
+        .success()
+        .stdout(predicate::str::diff("1\n"));
+}
+
+#[test]
+fn test_set_default_command() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["set-default", "work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Default profile set to 'work'"));
+
+    let content = fs::read_to_string(env.claudectx_dir().join(".default")).expect("read");
+    assert_eq!(content.trim(), "work");
+
+    env.cmd().args(["set-default", "ghost"]).assert().code(4);
+    let content = fs::read_to_string(env.claudectx_dir().join(".default")).expect("read");
+    assert_eq!(content.trim(), "work");
+}
+
+// =============================================================================
+// SHOW / EXPORT TESTS
+// =============================================================================
+
+impl TestEnv {
+    /// Create a profile whose account data contains secret-looking keys
+    fn create_profile_with_secrets(&self, name: &str) {
+        fs::create_dir_all(self.claudectx_dir()).expect("Failed to create claudectx dir");
+        let mut account = sample_account(name);
+        account["accessToken"] = json!("tok-secret-value");
+        let config = json!({
+            "oauthAccount": account,
+            "userID": format!("user-id-{}", name),
+            "primaryApiKey": "sk-ant-secret"
+        });
+        fs::write(
+            self.profile_path(name),
+            serde_json::to_string_pretty(&config).expect("serialize"),
+        )
+        .expect("Failed to write profile");
+    }
+}
+
+#[test]
+fn test_show_redacts_secrets_by_default() {
+    let env = TestEnv::new();
+    env.create_profile_with_secrets("work");
+
+    let output = env.cmd().args(["show", "work"]).assert().success();
+    let shown: serde_json::Value =
+        serde_json::from_slice(&output.get_output().stdout).expect("show prints JSON");
+
+    assert_eq!(shown["primaryApiKey"], "API key (…cret)");
+    assert_eq!(shown["oauthAccount"]["accessToken"], "***");
+    assert_eq!(
+        shown["oauthAccount"]["emailAddress"],
+        "user-work@example.com"
+    );
+}
+
+#[test]
+fn test_show_include_secrets() {
+    let env = TestEnv::new();
+    env.create_profile_with_secrets("work");
+
+    env.cmd()
+        .args(["show", "work", "--include-secrets"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("sk-ant-secret"))
+        .stdout(predicate::str::contains("tok-secret-value"));
+}
+
+#[test]
+fn test_show_nonexistent_profile() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["show", "ghost"])
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("Profile 'ghost' not found"));
+}
+
+#[test]
+fn test_export_to_file_redacts_secrets() {
+    let env = TestEnv::new();
+    env.create_profile_with_secrets("work");
+    let dest = env.home_path().join("work-export.json");
+
+    env.cmd()
+        .args(["export", "work", "--output"])
+        .arg(&dest)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Exported profile 'work'"));
+
+    let content = fs::read_to_string(&dest).expect("read export");
+    assert!(!content.contains("sk-ant-secret"));
+    assert!(!content.contains("tok-secret-value"));
+}
+
+#[test]
+fn test_export_include_secrets_to_stdout() {
+    let env = TestEnv::new();
+    env.create_profile_with_secrets("work");
+
+    let output = env
+        .cmd()
+        .args(["export", "work", "--include-secrets"])
+        .assert()
+        .success();
+    let exported: serde_json::Value =
+        serde_json::from_slice(&output.get_output().stdout).expect("export prints JSON");
+    assert_eq!(exported, env.read_profile("work"));
+}
+
+// =============================================================================
+// --PROFILES-FROM TESTS
+// =============================================================================
+
+/// Write a slim profile into an arbitrary directory
+fn write_profile_in(dir: &Path, name: &str, account: &serde_json::Value) {
+    fs::create_dir_all(dir).expect("Failed to create dir");
+    fs::write(
+        dir.join(format!("{}.claude.json", name)),
+        serde_json::to_string_pretty(&json!({ "oauthAccount": account })).expect("serialize"),
+    )
+    .expect("Failed to write profile");
+}
+
+#[test]
+fn test_list_profiles_from_other_directory() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    let backup_dir = env.home_path().join("backup-set");
+    write_profile_in(&backup_dir, "archived", &sample_account("archived"));
+
+    let output = env
+        .cmd()
+        .args(["list", "--profiles-from"])
+        .arg(&backup_dir)
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout);
+
+    assert!(stdout.contains("archived - User archived @ Org archived"));
+    assert!(!stdout.contains("work"));
+    // Nothing was copied into ~/.claudectx
+    assert_eq!(env.list_profile_files(), vec!["work"]);
+}
+
+#[test]
+fn test_show_profiles_from_other_directory() {
+    let env = TestEnv::new();
+    let backup_dir = env.home_path().join("backup-set");
+    write_profile_in(&backup_dir, "archived", &sample_account("archived"));
+
+    env.cmd()
+        .args(["show", "archived", "--profiles-from"])
+        .arg(&backup_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("uuid-archived"));
+}
+
+#[test]
+fn test_profiles_from_missing_directory() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["list", "--profiles-from"])
+        .arg(env.home_path().join("nope"))
+        .assert()
+        .code(5)
+        .stderr(predicate::str::contains("not found"));
+}
+
+// =============================================================================
+// WHICH COMMAND / CLAUDE BINARY RESOLUTION TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_which_finds_claude_on_path() {
+    let env = TestEnv::new();
+
+    let output = env.cmd_with_fake_claude().arg("which").assert().success();
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout);
+    assert_eq!(
+        stdout.trim(),
+        env.home_path()
+            .join("bin")
+            .join("claude")
+            .display()
+            .to_string()
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_which_honors_claude_bin_flag_and_env() {
+    let env = TestEnv::new();
+    let bin_dir = env.install_fake_claude();
+    let custom = bin_dir.join("my-claude");
+    fs::copy(bin_dir.join("claude"), &custom).expect("copy fake claude");
+    let expected = custom.canonicalize().expect("canonicalize");
+
+    env.cmd()
+        .args(["which", "--claude-bin"])
+        .arg(&custom)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(expected.display().to_string()));
+
+    env.cmd()
+        .env("CLAUDE_BIN", &custom)
+        .arg("which")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(expected.display().to_string()));
+}
+
+#[test]
+fn test_which_fails_when_claude_is_missing() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .env("PATH", env.home_path())
+        .env_remove("CLAUDE_BIN")
+        .arg("which")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("claude executable not found"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_launch_uses_claude_bin() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    let bin_dir = env.install_fake_claude();
+    let custom = bin_dir.join("my-claude");
+    fs::rename(bin_dir.join("claude"), &custom).expect("rename fake claude");
+
+    env.cmd()
+        .args(["work", "--claude-bin"])
+        .arg(&custom)
+        .args(["--", "hello"])
+        .assert()
+        .success();
+
+    assert_eq!(env.recorded_claude_args(), vec!["hello"]);
+}
+
+// =============================================================================
+// USAGE TRACKING / --SORT TESTS
+// =============================================================================
+
+impl TestEnv {
+    /// Path to ~/.claudectx/.usage.json
+    fn usage_path(&self) -> std::path::PathBuf {
+        self.claudectx_dir().join(".usage.json")
+    }
+
+    /// Write ~/.claudectx/.usage.json
+    fn write_usage(&self, usage: &serde_json::Value) {
+        fs::create_dir_all(self.claudectx_dir()).expect("Failed to create claudectx dir");
+        fs::write(
+            self.usage_path(),
+            serde_json::to_string_pretty(usage).expect("serialize"),
+        )
+        .expect("Failed to write usage");
+    }
+}
+
+/// Profile names in the order `list` printed them
+fn listed_names(stdout: &str) -> Vec<String> {
+    stdout
+        .lines()
+        .filter_map(|line| line.split(" - ").next())
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn test_switch_records_usage() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+
+    let _ = env.cmd().arg("work").assert();
+
+    let usage: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(env.usage_path()).expect("read usage"))
+            .expect("parse usage");
+    assert!(usage["work"].as_u64().unwrap() > 0);
+}
+
+#[test]
+fn test_list_sort_recent() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    for name in ["alpha", "beta", "gamma", "delta"] {
+        env.create_profile(name, &sample_account(name));
+    }
+    env.write_usage(&json!({ "gamma": 300, "alpha": 100 }));
+
+    let output = env
+        .cmd()
+        .args(["list", "--sort", "recent"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout);
+
+    assert_eq!(
+        listed_names(&stdout),
+        vec!["gamma", "alpha", "beta", "delta"]
+    );
+}
+
+#[test]
+fn test_list_sort_defaults_to_setting_then_name() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    for name in ["beta", "alpha", "gamma"] {
+        env.create_profile(name, &sample_account(name));
+    }
+    env.write_usage(&json!({ "gamma": 300 }));
+
+    let output = env.cmd().arg("list").assert().success();
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout);
+    assert_eq!(listed_names(&stdout), vec!["alpha", "beta", "gamma"]);
+
+    env.write_settings(&json!({ "sort": "recent" }));
+    let output = env.cmd().arg("list").assert().success();
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout);
+    assert_eq!(listed_names(&stdout), vec!["gamma", "alpha", "beta"]);
+}
+
+// =============================================================================
+// IMPORT-ALL TESTS
+// =============================================================================
+
+/// Build a .tar.gz from the contents of `dir` using the system tar
+fn make_archive(dir: &Path, archive: &Path) {
+    let status = Command::new("tar")
+        .arg("-czf")
+        .arg(archive)
+        .arg("-C")
+        .arg(dir)
+        .arg(".")
+        .status()
+        .expect("Failed to run tar");
+    assert!(status.success());
+}
+
+#[test]
+fn test_import_all_from_archive() {
+    let env = TestEnv::new();
+    let bundle = env.home_path().join("bundle");
+    fs::create_dir_all(&bundle).expect("mkdir");
+    fs::write(
+        bundle.join("work.claude.json"),
+        serde_json::to_string_pretty(&json!({
+            "oauthAccount": sample_account("work"),
+            "userID": "user-work",
+            "editorTheme": "dark"
+        }))
+        .expect("serialize"),
+    )
+    .expect("write");
+    fs::write(
+        bundle.join("no-uuid.claude.json"),
+        serde_json::to_string_pretty(&json!({ "userID": "nobody" })).expect("serialize"),
+    )
+    .expect("write");
+    fs::write(bundle.join("list.claude.json"), "[1, 2]").expect("write");
+    fs::write(bundle.join("MANIFEST.tsv"), "work\tuser-work@example.com\n").expect("write");
+    let archive = env.home_path().join("profiles.tar.gz");
+    make_archive(&bundle, &archive);
+
+    env.cmd()
+        .arg("import-all")
+        .arg(&archive)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Imported 'work'"))
+        .stdout(predicate::str::contains(
+            "Skipped 'no-uuid': missing oauthAccount.accountUuid",
+        ))
+        .stdout(predicate::str::contains(
+            "Skipped 'list': not a JSON object",
+        ))
+        .stdout(predicate::str::contains("1 imported, 2 skipped"));
+
+    assert_eq!(env.list_profile_files(), vec!["work"]);
+    // Slimmed on the way in
+    let profile = env.read_profile("work");
+    assert_eq!(profile["oauthAccount"]["accountUuid"], "uuid-work");
+    assert!(profile.get("editorTheme").is_none());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_import_all_ignores_symlinked_entries() {
+    let env = TestEnv::new();
+    let outside = env.home_path().join("outside");
+    fs::create_dir_all(&outside).expect("mkdir");
+    fs::write(
+        outside.join("host.claude.json"),
+        serde_json::to_string_pretty(&json!({ "oauthAccount": sample_account("host") }))
+            .expect("serialize"),
+    )
+    .expect("write");
+    let bundle = env.home_path().join("bundle");
+    fs::create_dir_all(&bundle).expect("mkdir");
+    fs::write(
+        bundle.join("work.claude.json"),
+        serde_json::to_string_pretty(&json!({ "oauthAccount": sample_account("work") }))
+            .expect("serialize"),
+    )
+    .expect("write");
+    std::os::unix::fs::symlink(".", bundle.join("loop")).expect("symlink");
+    std::os::unix::fs::symlink(&outside, bundle.join("hostdir")).expect("symlink");
+    std::os::unix::fs::symlink(
+        outside.join("host.claude.json"),
+        bundle.join("linked.claude.json"),
+    )
+    .expect("symlink");
+    let archive = env.home_path().join("profiles.tar.gz");
+    make_archive(&bundle, &archive);
+
+    env.cmd()
+        .arg("import-all")
+        .arg(&archive)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 imported, 0 skipped"));
+
+    assert_eq!(env.list_profile_files(), vec!["work"]);
+}
+
+#[test]
+fn test_import_all_skips_existing_profiles_unless_overwrite() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("old"));
+    let bundle = env.home_path().join("bundle");
+    write_profile_in(&bundle, "work", &sample_account("new"));
+    let archive = env.home_path().join("profiles.tar.gz");
+    make_archive(&bundle, &archive);
+
+    env.cmd()
+        .arg("import-all")
+        .arg(&archive)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Skipped 'work': profile already exists",
+        ));
+    assert_eq!(
+        env.read_profile("work")["oauthAccount"]["accountUuid"],
+        "uuid-old"
+    );
+
+    env.cmd()
+        .arg("import-all")
+        .arg(&archive)
+        .arg("--overwrite")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Imported 'work'"));
+    assert_eq!(
+        env.read_profile("work")["oauthAccount"]["accountUuid"],
+        "uuid-new"
+    );
+}
+
+#[test]
+fn test_import_all_missing_archive() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["import-all", "nope.tar.gz"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("not found"));
+}
+
+// =============================================================================
+// EXPORT-ALL TESTS
+// =============================================================================
+
+#[test]
+fn test_export_all_creates_archive_with_manifest() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("personal", &sample_account("personal"));
+    fs::write(env.profile_path("work").with_extension("json.bak"), "{}").expect("write bak");
+    let archive = env.home_path().join("all.tar.gz");
+
+    env.cmd()
+        .arg("export-all")
+        .arg(&archive)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Exported 2 profiles"));
+
+    let extracted = env.home_path().join("extracted");
+    fs::create_dir_all(&extracted).expect("mkdir");
+    let status = Command::new("tar")
+        .arg("-xzf")
+        .arg(&archive)
+        .arg("-C")
+        .arg(&extracted)
+        .status()
+        .expect("run tar");
+    assert!(status.success());
+
+    assert!(extracted.join("work.claude.json").exists());
+    assert!(extracted.join("personal.claude.json").exists());
+    assert!(!extracted.join("work.claude.json.bak").exists());
+    let manifest = fs::read_to_string(extracted.join("MANIFEST.tsv")).expect("manifest");
+    assert!(manifest.contains("personal\tuser-personal@example.com"));
+    assert!(manifest.contains("work\tuser-work@example.com"));
+}
+
+#[test]
+fn test_export_all_then_import_all_round_trip() {
+    let source = TestEnv::new();
+    source.create_profile("work", &sample_account("work"));
+    source.create_profile("personal", &sample_account("personal"));
+    let archive = source.home_path().join("all.tar.gz");
+    source
+        .cmd()
+        .arg("export-all")
+        .arg(&archive)
+        .assert()
+        .success();
+
+    let target = TestEnv::new();
+    target
+        .cmd()
+        .arg("import-all")
+        .arg(&archive)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2 imported, 0 skipped"));
+
+    assert_eq!(target.read_profile("work"), source.read_profile("work"));
+    assert_eq!(
+        target.read_profile("personal"),
+        source.read_profile("personal")
+    );
+}
+
+// =============================================================================
+// --STRICT / VALIDATE TESTS
+// =============================================================================
+
+impl TestEnv {
+    /// Create a profile that also carries a portable (non-account) key
+    fn create_drifted_profile(&self, name: &str) {
+        fs::create_dir_all(self.claudectx_dir()).expect("Failed to create claudectx dir");
+        let config = json!({
+            "oauthAccount": sample_account(name),
+            "userID": format!("user-id-{}", name),
+            "editorTheme": "dark",
+            "customSetting": true
+        });
+        fs::write(
+            self.profile_path(name),
+            serde_json::to_string_pretty(&config).expect("serialize"),
+        )
+        .expect("Failed to write profile");
+    }
+}
+
+#[test]
+fn test_strict_switch_rejects_unexpected_keys() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_drifted_profile("drifted");
+
+    env.cmd()
+        .args(["drifted", "--strict"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "Profile 'drifted' has unexpected keys: customSetting, editorTheme",
+        ));
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-current"
+    );
+}
+
+#[test]
+fn test_non_strict_switch_accepts_unexpected_keys() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_drifted_profile("drifted");
+
+    let _ = env.cmd().arg("drifted").assert();
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-drifted"
+    );
+}
+
+#[test]
+fn test_validate_reports_problems() {
+    let env = TestEnv::new();
+    env.create_profile("good", &sample_account("good"));
+    env.create_drifted_profile("drifted");
+
+    // Unexpected keys only fail in strict mode
+    env.cmd()
+        .arg("validate")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("drifted: ok"))
+        .stdout(predicate::str::contains("good: ok"));
+
+    env.cmd()
+        .args(["validate", "--strict"])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains(
+            "drifted: unexpected keys customSetting, editorTheme",
+        ))
+        .stdout(predicate::str::contains("good: ok"))
+        .stderr(predicate::str::contains(
+            "1 of 2 profiles failed validation",
+        ));
+}
+
+#[test]
+fn test_validate_single_profile_with_missing_fields() {
+    let env = TestEnv::new();
+    env.create_profile("good", &sample_account("good"));
+    env.create_profile("partial", &json!({ "accountUuid": "uuid-partial" }));
+
+    env.cmd()
+        .args(["validate", "partial"])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains("partial: missing emailAddress"));
+}
+
+// =============================================================================
+// SAVE GUARD TESTS
+// =============================================================================
+
+#[test]
+fn test_save_refuses_config_without_oauth_account() {
+    let env = TestEnv::new();
+    fs::write(
+        env.claude_config_path(),
+        serde_json::to_string_pretty(&json!({
+            "userID": "logged-out-user",
+            "hasCompletedOnboarding": true
+        }))
+        .expect("serialize"),
+    )
+    .expect("write config");
+
+    env.cmd()
+        .args(["save", "empty"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "is not logged in - run 'claudectx login' first",
+        ));
+
+    assert!(!env.profile_path("empty").exists());
+}
+
+#[test]
+fn test_save_refuses_config_with_empty_account_uuid() {
+    let env = TestEnv::new();
+    let mut account = sample_account("blank");
+    account["accountUuid"] = json!("");
+    fs::write(
+        env.claude_config_path(),
+        serde_json::to_string_pretty(&json!({ "oauthAccount": account })).expect("serialize"),
+    )
+    .expect("write config");
+
+    env.cmd()
+        .args(["save", "blank"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("is not logged in"));
+
+    assert!(!env.profile_path("blank").exists());
+}
+
+// =============================================================================
+// SYMLINKED CONFIG SAFETY TESTS
+// =============================================================================
+
+#[cfg(unix)]
+impl TestEnv {
+    /// Make ~/.claude.json a symlink to `dotfiles/claude.json` holding `account`
+    fn create_symlinked_claude_config(&self, account: &serde_json::Value) -> std::path::PathBuf {
+        let target_dir = self.home_path().join("dotfiles");
+        fs::create_dir_all(&target_dir).expect("mkdir");
+        let target = target_dir.join("claude.json");
+        fs::write(
+            &target,
+            serde_json::to_string_pretty(&json!({
+                "oauthAccount": account,
+                "hasCompletedOnboarding": true
+            }))
+            .expect("serialize"),
+        )
+        .expect("write target");
+        std::os::unix::fs::symlink(&target, self.claude_config_path()).expect("symlink");
+        target
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_switch_does_not_write_through_symlinked_config() {
+    let env = TestEnv::new();
+    let target = env.create_symlinked_claude_config(&sample_account("linked"));
+    env.create_profile("work", &sample_account("work"));
+    let original_target = fs::read_to_string(&target).expect("read target");
+
+    let _ = env.cmd().arg("work").assert();
+
+    // The link target is never modified
+    assert_eq!(fs::read_to_string(&target).expect("read"), original_target);
+    assert!(!env.claude_config_path().is_symlink());
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_follow_symlinks_writes_through_symlinked_config() {
+    let env = TestEnv::new();
+    let target = env.create_symlinked_claude_config(&sample_account("linked"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["work", "--follow-symlinks"])
+        .assert()
+        .stdout(predicate::str::contains("Migrated").not());
+
+    assert!(env.claude_config_path().is_symlink());
+    let target_config: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&target).expect("read")).expect("parse");
+    assert_eq!(target_config["oauthAccount"]["accountUuid"], "uuid-work");
+}
+
+// =============================================================================
+// LIST --WIDE TESTS
+// =============================================================================
+
+#[test]
+fn test_list_wide_aligns_columns() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("work", &sample_account("work"));
+    let mut long_org = sample_account("personal");
+    long_org["organizationName"] = json!("A Very Long Organization Name");
+    env.create_profile("personal", &long_org);
+
+    let output = env.cmd().args(["list", "--wide"]).assert().success();
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+
+    assert_eq!(lines.len(), 3);
+    // No color codes when stdout isn't a terminal
+    assert!(!stdout.contains('\u{1b}'));
+    assert_eq!(
+        lines[0],
+        "NAME      DISPLAY        ORGANIZATION                   CURRENT"
+    );
+    assert_eq!(
+        lines[1],
+        "personal  User personal  A Very Long Organization Name"
+    );
+    assert_eq!(
+        lines[2],
+        "work      User work      Org work                       *"
+    );
+}
+
+// =============================================================================
+// LOGIN --NO-BACKUP TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_login_no_backup_leaves_config_in_place() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+
+    // The fake claude "logs in" without changing anything; the workflow then
+    // stops at the launch prompt since there is no TTY
+    let output = env
+        .cmd_with_fake_claude()
+        .args(["login", "--no-backup", "--name", "work"])
+        .assert()
+        .failure();
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout);
+
+    assert!(!stdout.contains("Backed up"));
+    assert_eq!(env.recorded_claude_args(), vec!["/login"]);
+    assert!(!env.claude_config_backup_path().exists());
+    assert!(env.backup_files().is_empty());
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-current"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_login_backs_up_config_by_default() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+
+    env.cmd_with_fake_claude()
+        .args(["login", "--name", "work"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Backed up existing config"));
+}
+
+// =============================================================================
+// EDIT TESTS
+// =============================================================================
+
+#[cfg(unix)]
+impl TestEnv {
+    /// Write an executable editor script that runs `body` with the file as $1
+    fn install_fake_editor(&self, body: &str) -> std::path::PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let script = self.home_path().join("fake-editor");
+        fs::write(&script, format!("#!/bin/sh\n{}\n", body)).expect("Failed to write editor");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))
+            .expect("Failed to chmod editor");
+        script
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_edit_saves_valid_changes() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    let editor = env.install_fake_editor("sed -i 's/uuid-work/uuid-edited/' \"$1\"");
+
+    env.cmd()
+        .args(["edit", "work"])
+        .env_remove("VISUAL")
+        .env("EDITOR", &editor)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Saved profile 'work'"));
+
+    assert_eq!(
+        env.read_profile("work")["oauthAccount"]["accountUuid"],
+        "uuid-edited"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_edit_rejects_invalid_json() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    let editor = env.install_fake_editor("echo '{ broken' > \"$1\"");
+
+    env.cmd()
+        .args(["edit", "work"])
+        .env_remove("VISUAL")
+        .env("EDITOR", &editor)
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("not valid JSON"));
+
+    assert_eq!(
+        env.read_profile("work")["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_edit_without_editor_explains_how_to_set_one() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["edit", "work"])
+        .env_remove("VISUAL")
+        .env_remove("EDITOR")
+        .env("PATH", env.home_path().join("empty"))
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("set $EDITOR"));
+}
+
+#[test]
+fn test_edit_missing_profile() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["edit", "ghost"])
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("not found"));
+}
+
+// =============================================================================
+// LIST --SINCE TESTS
+// =============================================================================
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[test]
+fn test_list_since_filters_by_recent_usage() {
+    let env = TestEnv::new();
+    env.create_profile("fresh", &sample_account("fresh"));
+    env.create_profile("stale", &sample_account("stale"));
+    env.create_profile("never", &sample_account("never"));
+    env.write_usage(&json!({
+        "fresh": now_secs() - 3600,
+        "stale": now_secs() - 30 * 86400,
+    }));
+
+    let output = env.cmd().args(["list", "--since", "7d"]).assert().success();
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout);
+
+    assert_eq!(listed_names(&stdout), vec!["fresh"]);
+}
+
+#[test]
+fn test_list_since_hours_and_weeks() {
+    let env = TestEnv::new();
+    env.create_profile("fresh", &sample_account("fresh"));
+    env.create_profile("stale", &sample_account("stale"));
+    env.write_usage(&json!({
+        "fresh": now_secs() - 3600,
+        "stale": now_secs() - 10 * 86400,
+    }));
+
+    let output = env.cmd().args(["list", "--since", "2h"]).assert().success();
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout);
+    assert_eq!(listed_names(&stdout), vec!["fresh"]);
+
+    let output = env.cmd().args(["list", "--since", "2w"]).assert().success();
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout);
+    assert_eq!(listed_names(&stdout), vec!["fresh", "stale"]);
+}
+
+#[test]
+fn test_list_since_rejects_invalid_window() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["list", "--since", "7m"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid window"));
+}
+
+// =============================================================================
+// --FROM-STDIN TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_from_stdin_switches_without_saving() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    let piped = json!({
+        "oauthAccount": sample_account("ci"),
+        "userID": "user-ci",
+        "mcpServers": { "ignored": {} }
+    });
+
+    env.cmd_with_fake_claude()
+        .args(["--from-stdin", "--", "--print", "hi"])
+        .write_stdin(piped.to_string())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Switched to 'stdin'"));
+
+    let config = env.read_claude_config();
+    assert_eq!(config["oauthAccount"]["accountUuid"], "uuid-ci");
+    assert_eq!(config["userID"], "user-ci");
+    assert!(config.get("mcpServers").is_none());
+    assert_eq!(env.recorded_claude_args(), vec!["--print", "hi"]);
+    assert!(!env.claudectx_dir().exists());
+}
+
+#[test]
+fn test_from_stdin_rejects_invalid_json() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+
+    env.cmd()
+        .arg("--from-stdin")
+        .write_stdin("not json")
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("stdin"));
+
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-current"
+    );
+}
+
+#[test]
+fn test_from_stdin_conflicts_with_profile() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["work", "--from-stdin"])
+        .write_stdin("{}")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn test_from_stdin_conflicts_with_verify() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+
+    env.cmd()
+        .args(["--from-stdin", "--verify"])
+        .write_stdin(
+            serde_json::to_string(&json!({ "oauthAccount": sample_account("piped") }))
+                .expect("serialize"),
+        )
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("cannot be used with"));
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-current"
+    );
+}
+
+// =============================================================================
+// NON-INTERACTIVE PROMPT TESTS
+// =============================================================================
+
+#[test]
+fn test_save_overwrite_without_tty_asks_for_yes() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("new"));
+    env.create_profile("work", &sample_account("old"));
+
+    env.cmd()
+        .args(["save", "work"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "'save' requires confirmation; pass --yes or run in a terminal",
+        ));
+
+    assert_eq!(
+        env.read_profile("work")["oauthAccount"]["accountUuid"],
+        "uuid-old"
+    );
+}
+
+#[test]
+fn test_save_overwrite_with_yes() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("new"));
+    env.create_profile("work", &sample_account("old"));
+
+    env.cmd().args(["save", "work", "--yes"]).assert().success();
+
+    assert_eq!(
+        env.read_profile("work")["oauthAccount"]["accountUuid"],
+        "uuid-new"
+    );
+}
+
+#[test]
+fn test_launch_missing_profile_without_tty_suggests_create() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+
+    env.cmd()
+        .arg("nonexistent")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "Profile 'nonexistent' not found; pass --create",
+        ));
+
+    assert!(!env.profile_path("nonexistent").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_launch_missing_profile_with_yes_creates_it() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+
+    env.cmd_with_fake_claude()
+        .args(["-y", "newprofile"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Profile 'newprofile' saved."));
+
+    assert!(env.profile_path("newprofile").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_login_without_tty_asks_for_name() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+
+    env.cmd_with_fake_claude()
+        .arg("login")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "'login' requires input; pass --name or run in a terminal",
+        ));
+
+    // Nothing was launched or backed up
+    assert!(!env.home_path().join("claude-args.txt").exists());
+    assert!(!env.claude_config_backup_path().exists());
+    assert!(env.backup_files().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_login_with_name_and_yes_saves_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+
+    env.cmd_with_fake_claude()
+        .args(["login", "--name", "fresh", "--yes", "--no-backup"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Saved profile 'fresh'"));
+
+    assert_eq!(
+        env.read_profile("fresh")["oauthAccount"]["accountUuid"],
+        "uuid-current"
+    );
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-current"
+    );
+}
+
+// =============================================================================
+// CONFIG TESTS
+// =============================================================================
+
+#[test]
+fn test_config_prints_effective_settings() {
+    let env = TestEnv::new();
+    env.write_settings(&json!({ "sort": "recent" }));
+
+    env.cmd()
+        .arg("config")
+        .assert()
+        .success()
+        .stdout(
+            "profile_extension = .claude.json\nsort = recent\nextra_account_fields = \nbefore_switch = \nafter_switch = \nsort_keys = false\npicker = list\nbanner = false\nconfig_dirs = false\n",
+        );
+}
+
+#[test]
+fn test_config_get_and_set() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["config", "sort", "recent"])
+        .assert()
+        .success()
+        .stdout("sort = recent\n");
+    env.cmd()
+        .args(["config", "sort"])
+        .assert()
+        .success()
+        .stdout("recent\n");
+
+    let settings: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(env.claudectx_dir().join(".settings.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(settings, json!({ "sort": "recent" }));
+
+    // An empty value resets to the default
+    env.cmd().args(["config", "sort", ""]).assert().success();
+    env.cmd()
+        .args(["config", "sort"])
+        .assert()
+        .success()
+        .stdout("name\n");
+}
+
+#[test]
+fn test_config_rejects_unknown_key_and_bad_value() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["config", "nope"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Unknown config key 'nope'"));
+    env.cmd()
+        .args(["config", "sort", "oldest"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Invalid value 'oldest'"));
+}
+
+// =============================================================================
+// DELETE --ALL TESTS
+// =============================================================================
+
+#[test]
+fn test_delete_all_with_yes_removes_every_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("personal", &sample_account("personal"));
+    fs::write(env.claudectx_dir().join("work.claude.json.bak"), "{}").unwrap();
+
+    env.cmd()
+        .args(["delete", "--all", "--yes"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("personal"))
+        .stdout(predicate::str::contains("Deleted 2 profile(s)"));
+
+    assert!(!env.profile_path("work").exists());
+    assert!(!env.profile_path("personal").exists());
+    assert!(env.claudectx_dir().join("work.claude.json.bak").exists());
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-current"
+    );
+}
+
+#[test]
+fn test_delete_all_requires_confirmation_without_tty() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["delete", "--all"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "'delete --all' requires confirmation",
+        ));
+
+    assert!(env.profile_path("work").exists());
+}
+
+#[test]
+fn test_delete_all_conflicts_with_name() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["delete", "work", "--all"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    env.cmd().arg("delete").assert().failure();
+}
+
+// =============================================================================
+// KEY ORDER TESTS
+// =============================================================================
+
+#[test]
+fn test_switch_preserves_config_key_order() {
+    let env = TestEnv::new();
+    fs::write(
+        env.claude_config_path(),
+        r#"{
+  "numStartups": 3,
+  "oauthAccount": { "accountUuid": "uuid-current" },
+  "theme": "dark",
+  "userID": "user-current",
+  "autoUpdates": false
+}"#,
+    )
+    .unwrap();
+    env.create_profile("work", &sample_account("work"));
+
+    let _ = env.cmd().arg("work").assert();
+
+    let content = fs::read_to_string(env.claude_config_path()).unwrap();
+    let position = |key: &str| content.find(&format!("\"{}\"", key)).unwrap();
+    assert!(position("numStartups") < position("oauthAccount"));
+    assert!(position("oauthAccount") < position("theme"));
+    assert!(position("theme") < position("autoUpdates"));
+}
+
+// =============================================================================
+// PICKER DETAILS TESTS
+// =============================================================================
+
+#[test]
+fn test_details_flag_is_documented() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--details"));
+}
+
+// =============================================================================
+// API KEY PROFILE TESTS
+// =============================================================================
+
+impl TestEnv {
+    /// Write ~/.claude.json for an API-key-only account (no oauthAccount)
+    fn create_api_key_config(&self, key: &str) {
+        fs::write(
+            self.claude_config_path(),
+            serde_json::to_string_pretty(&json!({ "primaryApiKey": key, "theme": "dark" }))
+                .expect("serialize"),
+        )
+        .expect("Failed to write config");
+    }
+}
+
+#[test]
+fn test_api_key_profile_requires_opt_in() {
+    let env = TestEnv::new();
+    env.create_api_key_config("sk-ant-api-abcd");
+
+    env.cmd()
+        .args(["save", "ci"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("accountUuid"));
+}
+
+#[test]
+fn test_api_key_profile_save_list_and_switch() {
+    let env = TestEnv::new();
+    env.write_settings(&json!({ "extra_account_fields": ["primaryApiKey"] }));
+    env.create_api_key_config("sk-ant-api-abcd");
+
+    env.cmd().args(["save", "ci"]).assert().success();
+    assert_eq!(env.read_profile("ci")["primaryApiKey"], "sk-ant-api-abcd");
+
+    env.cmd()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ci - API key (…abcd) *"));
+    env.cmd()
+        .arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("API key (…abcd), profile 'ci'"));
+
+    // Switching to an OAuth profile removes the key; switching back restores it
+    env.create_profile("work", &sample_account("work"));
+    let _ = env.cmd().arg("work").assert();
+    assert!(env.read_claude_config().get("primaryApiKey").is_none());
+
+    let _ = env.cmd().arg("ci").assert();
+    let config = env.read_claude_config();
+    assert_eq!(config["primaryApiKey"], "sk-ant-api-abcd");
+    assert!(config.get("oauthAccount").is_none());
+    assert_eq!(config["theme"], "dark");
+}
+
+#[test]
+fn test_api_key_profile_show_masks_key() {
+    let env = TestEnv::new();
+    env.write_settings(&json!({ "extra_account_fields": ["primaryApiKey"] }));
+    env.create_api_key_config("sk-ant-api-abcd");
+    env.cmd().args(["save", "ci"]).assert().success();
+
+    env.cmd()
+        .args(["show", "ci"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("API key (…abcd)"))
+        .stdout(predicate::str::contains("sk-ant-api-abcd").not());
+}
+
+// =============================================================================
+// BACKUP DIR TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_login_backs_up_into_backups_dir_and_restores() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+
+    // The fake claude doesn't create a config, so the workflow restores the backup
+    env.cmd_with_fake_claude()
+        .args(["login", "--name", "work"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("Backed up existing config to"))
+        .stdout(predicate::str::contains("backups"));
+
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-current"
+    );
+    assert!(env.backup_files().is_empty());
+    assert!(!env.claude_config_backup_path().exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_backup_dir_override_for_migration() {
+    let env = TestEnv::new();
+    env.create_symlinked_claude_config(&sample_account("linked"));
+    fs::create_dir_all(env.claudectx_dir()).expect("mkdir");
+    fs::write(
+        env.profile_path("work"),
+        serde_json::to_string_pretty(&json!({
+            "oauthAccount": sample_account("work"),
+            "customSetting": "old-value"
+        }))
+        .expect("serialize"),
+    )
+    .expect("write");
+    let custom = env.home_path().join("my-backups");
+
+    env.cmd()
+        .args(["--backup-dir", custom.to_str().unwrap(), "list"])
+        .assert()
+        .success();
+
+    assert!(!env.backups_dir().exists());
+    let backups = env.backup_files_in(&custom);
+    assert_eq!(backups.len(), 2);
+    assert!(backups
+        .iter()
+        .any(|name| name.starts_with("work.claude.json-") && name.ends_with(".bak")));
+    assert!(backups.iter().any(|name| name.starts_with(".claude.json-")));
+}
+
+// =============================================================================
+// SHOW/STATUS --JSON TESTS
+// =============================================================================
+
+#[test]
+fn test_show_json_uses_snake_case_account() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("personal", &sample_account("personal"));
+
+    let output = env
+        .cmd()
+        .args(["show", "work", "--json"])
+        .assert()
+        .success();
+    let shown: serde_json::Value =
+        serde_json::from_slice(&output.get_output().stdout).expect("show --json prints JSON");
+
+    assert_eq!(shown["profile"], "work");
+    assert_eq!(shown["current"], true);
+    assert_eq!(shown["account"]["account_uuid"], "uuid-work");
+    assert_eq!(shown["account"]["email_address"], "user-work@example.com");
+    assert!(shown["account"].get("accountUuid").is_none());
+
+    let output = env
+        .cmd()
+        .args(["show", "personal", "--json"])
+        .assert()
+        .success();
+    let shown: serde_json::Value = serde_json::from_slice(&output.get_output().stdout).unwrap();
+    assert_eq!(shown["current"], false);
+}
+
+#[test]
+fn test_status_json() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("work", &sample_account("work"));
+
+    let output = env.cmd().args(["status", "--json"]).assert().success();
+    let status: serde_json::Value = serde_json::from_slice(&output.get_output().stdout).unwrap();
+
+    assert_eq!(status["profile"], "work");
+    assert_eq!(status["current"], true);
+    assert_eq!(status["account"]["organization_name"], "Org work");
+
+    env.cmd().args(["delete", "work"]).assert().success();
+    let output = env.cmd().args(["status", "--json"]).assert().success();
+    let status: serde_json::Value = serde_json::from_slice(&output.get_output().stdout).unwrap();
+    assert!(status["profile"].is_null());
+    assert_eq!(status["current"], false);
+}
+
+// =============================================================================
+// --VERIFY IDENTITY TESTS
+// =============================================================================
+
+#[test]
+fn test_verify_warns_when_profile_email_diverges() {
+    let env = TestEnv::new();
+    // The live config shows the account under its new email...
+    let mut live = sample_account("work");
+    live["emailAddress"] = json!("renamed@example.com");
+    env.create_claude_config(&live);
+    // ...while the saved profile still carries the old one
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["--verify", "work"])
+        .assert()
+        .stderr(predicate::str::contains(
+            "Warning: Profile 'work' has email",
+        ))
+        .stderr(predicate::str::contains("renamed@example.com"));
+}
+
+#[test]
+fn test_verify_uses_cached_email_after_switching_away() {
+    let env = TestEnv::new();
+    let mut live = sample_account("work");
+    live["emailAddress"] = json!("renamed@example.com");
+    env.create_claude_config(&live);
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("personal", &sample_account("personal"));
+
+    // Switching away records the live email for uuid-work
+    let _ = env.cmd().arg("personal").assert();
+
+    env.cmd()
+        .args(["--verify", "work"])
+        .assert()
+        .stderr(predicate::str::contains("renamed@example.com"));
+}
+
+#[test]
+fn test_verify_is_silent_when_identity_matches() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["--verify", "work"])
+        .assert()
+        .stderr(predicate::str::contains("Warning").not());
+}
+
+// =============================================================================
+// SAVE --ALL-FIELDS TESTS
+// =============================================================================
+
+#[test]
+fn test_save_all_fields_snapshots_whole_config() {
+    let env = TestEnv::new();
+    fs::write(
+        env.claude_config_path(),
+        serde_json::to_string_pretty(&json!({
+            "oauthAccount": sample_account("full"),
+            "theme": "dark",
+            "mcpServers": { "local": {} }
+        }))
+        .unwrap(),
+    )
+    .unwrap();
+
+    env.cmd()
+        .args(["save", "snap", "--all-fields"])
+        .assert()
+        .success();
+
+    let profile = env.read_profile("snap");
+    assert_eq!(profile["claudectxFull"], true);
+    assert_eq!(profile["theme"], "dark");
+    assert!(profile["mcpServers"]["local"].is_object());
+
+    env.cmd()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "snap - User full @ Org full [full] *",
+        ));
+}
+
+#[test]
+fn test_switch_to_full_profile_replaces_whole_config() {
+    let env = TestEnv::new();
+    fs::write(
+        env.claude_config_path(),
+        serde_json::to_string_pretty(&json!({
+            "oauthAccount": sample_account("full"),
+            "theme": "dark"
+        }))
+        .unwrap(),
+    )
+    .unwrap();
+    env.cmd()
+        .args(["save", "snap", "--all-fields"])
+        .assert()
+        .success();
+
+    // Change the live config, then switch back to the snapshot
+    fs::write(
+        env.claude_config_path(),
+        serde_json::to_string_pretty(&json!({
+            "oauthAccount": sample_account("other"),
+            "theme": "light",
+            "extra": 1
+        }))
+        .unwrap(),
+    )
+    .unwrap();
+    let _ = env.cmd().arg("snap").assert();
+
+    let config = env.read_claude_config();
+    assert_eq!(config["oauthAccount"]["accountUuid"], "uuid-full");
+    assert_eq!(config["theme"], "dark");
+    assert!(config.get("extra").is_none());
+    assert!(config.get("claudectxFull").is_none());
+}
+
+#[test]
+fn test_full_profile_passes_strict_validation() {
+    let env = TestEnv::new();
+    fs::write(
+        env.claude_config_path(),
+        serde_json::to_string_pretty(&json!({
+            "oauthAccount": sample_account("full"),
+            "theme": "dark"
+        }))
+        .unwrap(),
+    )
+    .unwrap();
+    env.cmd()
+        .args(["save", "snap", "--all-fields"])
+        .assert()
+        .success();
+
+    env.cmd()
+        .args(["validate", "snap", "--strict"])
+        .assert()
+        .success();
+}
+
+// =============================================================================
+// DELETE GLOB TESTS
+// =============================================================================
+
+#[test]
+fn test_delete_glob_removes_matching_profiles() {
+    let env = TestEnv::new();
+    env.create_profile("client-acme", &sample_account("acme"));
+    env.create_profile("client-globex", &sample_account("globex"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["delete", "client-*", "--yes"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("client-acme"))
+        .stdout(predicate::str::contains("client-globex"))
+        .stdout(predicate::str::contains("Deleted 2 profile(s)"));
+
+    assert!(!env.profile_path("client-acme").exists());
+    assert!(!env.profile_path("client-globex").exists());
+    assert!(env.profile_path("work").exists());
+}
+
+#[test]
+fn test_delete_glob_requires_confirmation_without_tty() {
+    let env = TestEnv::new();
+    env.create_profile("client-acme", &sample_account("acme"));
+
+    env.cmd()
+        .args(["delete", "client-?cme"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("'delete' requires confirmation"));
+
+    assert!(env.profile_path("client-acme").exists());
+}
+
+#[test]
+fn test_delete_glob_without_matches() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["delete", "client-*"])
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("No profiles match 'client-*'"));
+}
+
+// =============================================================================
+// MIGRATE COMMAND TESTS
+// =============================================================================
+
+impl TestEnv {
+    /// Write an old-style profile holding the whole config
+    fn create_unslimmed_profile(&self, name: &str) {
+        fs::create_dir_all(self.claudectx_dir()).expect("mkdir");
+        fs::write(
+            self.profile_path(name),
+            serde_json::to_string_pretty(&json!({
+                "oauthAccount": sample_account(name),
+                "customSetting": "old-value"
+            }))
+            .expect("serialize"),
+        )
+        .expect("write");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_migrate_check_reports_pending_without_changes() {
+    let env = TestEnv::new();
+    env.create_symlinked_claude_config(&sample_account("current"));
+    env.create_unslimmed_profile("old");
+    env.create_profile("slim", &sample_account("slim"));
+
+    env.cmd()
+        .args(["migrate", "--check"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("Claude config is a symlink: yes"))
+        .stdout(predicate::str::contains("Profiles to slim: 1"))
+        .stdout(predicate::str::contains("  old"));
+
+    assert_eq!(env.read_profile("old")["customSetting"], "old-value");
+    assert!(env.backup_files().is_empty());
+}
+
+#[test]
+fn test_migrate_check_nothing_to_do() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("slim", &sample_account("slim"));
+
+    env.cmd()
+        .args(["migrate", "--check"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Nothing to migrate."));
+}
+
+#[test]
+fn test_migrate_without_symlink_keeps_merged_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_unslimmed_profile("merged");
+
+    env.cmd()
+        .args(["migrate", "--check"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Nothing to migrate."));
+    env.cmd()
+        .arg("migrate")
+        .assert()
+        .success()
+        .stdout("Nothing to migrate.\n");
+    env.cmd()
+        .args(["--yes", "doctor", "--fix"])
+        .assert()
+        .stdout(predicate::str::contains("Run the pending migration").not());
+
+    assert_eq!(env.read_profile("merged")["customSetting"], "old-value");
+    assert!(env.backup_files().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_migrate_slims_profiles_from_symlinked_config() {
+    let env = TestEnv::new();
+    env.create_symlinked_claude_config(&sample_account("current"));
+    env.create_unslimmed_profile("old");
+
+    env.cmd()
+        .arg("migrate")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Migrated profiles"));
+
+    assert!(!env.claude_config_path().is_symlink());
+    assert!(env.read_profile("old").get("customSetting").is_none());
+    env.cmd().args(["migrate", "--check"]).assert().success();
+}
+
+#[test]
+fn test_migrate_nothing_to_do() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("slim", &sample_account("slim"));
+
+    env.cmd()
+        .arg("migrate")
+        .assert()
+        .success()
+        .stdout("Nothing to migrate.\n");
+    assert!(env.backup_files().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_migrate_check_reports_symlinked_config() {
+    let env = TestEnv::new();
+    env.create_symlinked_claude_config(&sample_account("linked"));
+
+    env.cmd()
+        .args(["migrate", "--check"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("Claude config is a symlink: yes"));
+
+    // The check itself must not trigger the startup migration
+    assert!(env.claude_config_path().is_symlink());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_migration_backs_up_live_config() {
+    let env = TestEnv::new();
+    env.create_symlinked_claude_config(&sample_account("linked"));
+
+    env.cmd().arg("list").assert().success();
+
+    assert!(!env.claude_config_path().is_symlink());
+    let backups = env.backup_files();
+    let backup = backups
+        .iter()
+        .find(|name| name.starts_with(".claude.json-"))
+        .expect("live config backup should be created");
+    let content = fs::read_to_string(env.backups_dir().join(backup)).expect("read backup");
+    let config: serde_json::Value = serde_json::from_str(&content).expect("parse backup");
+    assert_eq!(config["oauthAccount"]["accountUuid"], "uuid-linked");
+}
+
+// =============================================================================
+// --TIME-FORMAT TESTS
+// =============================================================================
+
+#[test]
+fn test_list_verbose_shows_relative_last_used() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("idle", &sample_account("idle"));
+    env.write_usage(&json!({ "work": now_secs() - 2 * 3600 }));
+
+    env.cmd()
+        .args(["list", "--verbose"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "work - User work @ Org work (2h ago)",
+        ))
+        .stdout(predicate::str::contains(
+            "idle - User idle @ Org idle (never used)",
+        ));
+}
+
+#[test]
+fn test_list_verbose_iso_time_format() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    env.write_usage(&json!({ "work": 1_706_691_900u64 }));
+
+    env.cmd()
+        .args(["list", "-v", "--time-format", "iso"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "work - User work @ Org work (2024-01-31T09:05:00Z)",
+        ));
+}
+
+#[test]
+fn test_status_time_format_iso() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("work", &sample_account("work"));
+    env.write_usage(&json!({ "work": 1_706_691_900u64 }));
+
+    env.cmd()
+        .args(["status", "--time-format", "iso"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "profile 'work' (2024-01-31T09:05:00Z)",
+        ));
+}
+
+#[test]
+fn test_list_plain_has_no_last_used() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    env.write_usage(&json!({ "work": now_secs() }));
+
+    env.cmd()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("just now").not());
+}
+
+// =============================================================================
+// RENAME TESTS
+// =============================================================================
+
+#[test]
+fn test_rename_active_profile_keeps_current_marker() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["rename", "work", "job"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Renamed profile 'work' to 'job'"));
+
+    assert!(!env.profile_path("work").exists());
+    env.cmd()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("job - User work @ Org work *"));
+}
+
+#[test]
+fn test_rename_updates_default_and_usage() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    env.write_usage(&json!({ "work": 1234 }));
+    env.cmd().args(["set-default", "work"]).assert().success();
+
+    env.cmd().args(["rename", "work", "job"]).assert().success();
+
+    let default = fs::read_to_string(env.claudectx_dir().join(".default")).expect("read");
+    assert_eq!(default.trim(), "job");
+    let usage: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(env.usage_path()).expect("read")).expect("parse");
+    assert_eq!(usage, json!({ "job": 1234 }));
+}
+
+#[test]
+fn test_rename_refuses_existing_target() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("job", &sample_account("job"));
+
+    env.cmd()
+        .args(["rename", "work", "job"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Profile 'job' already exists"));
+    assert!(env.profile_path("work").exists());
+}
+
+#[test]
+fn test_rename_missing_profile() {
+    let env = TestEnv::new();
+
+    env.cmd().args(["rename", "nope", "job"]).assert().code(4);
+}
+
+// =============================================================================
+// LIST --ORG TESTS
+// =============================================================================
+
+#[test]
+fn test_list_org_filters_case_insensitively() {
+    let env = TestEnv::new();
+    let mut acme = sample_account("work");
+    acme["organizationName"] = json!("Acme Corp");
+    env.create_profile("work", &acme);
+    env.create_profile("personal", &sample_account("personal"));
+    env.write_settings(&json!({ "extra_account_fields": ["primaryApiKey"] }));
+    env.create_api_key_config("sk-ant-api-abcd");
+    env.cmd().args(["save", "ci"]).assert().success();
+
+    let output = env.cmd().args(["list", "--org", "acme"]).assert().success();
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout);
+    assert_eq!(listed_names(&stdout), vec!["work"]);
+
+    env.cmd()
+        .args(["list", "--org", "ACME", "--count"])
+        .assert()
+        .success()
+        .stdout("1\n");
+}
+
+#[test]
+fn test_list_org_no_match() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["list", "--org", "Globex"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No profiles found."));
+}
+
+#[test]
+fn test_list_org_skips_broken_profile() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    fs::write(env.profile_path("broken"), "not json").expect("write");
+
+    let output = env
+        .cmd()
+        .args(["list", "--org", "org work"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Warning: skipped profile 'broken'",
+        ));
+    let stdout = String::from_utf8_lossy(&output.get_output().stdout);
+    assert_eq!(listed_names(&stdout), vec!["work"]);
+}
+
+// =============================================================================
+// PRUNE TESTS
+// =============================================================================
+
+impl TestEnv {
+    /// Write a backup file of `size` bytes into ~/.claudectx/backups
+    fn create_backup(&self, name: &str, size: usize) {
+        fs::create_dir_all(self.backups_dir()).expect("mkdir");
+        fs::write(self.backups_dir().join(name), "x".repeat(size)).expect("write backup");
+    }
+}
+
+#[test]
+fn test_prune_dry_run_lists_without_deleting() {
+    let env = TestEnv::new();
+    env.create_backup("work.claude.json-100.bak", 512);
+    env.create_backup(".claude.json-200.bak", 2048);
+
+    env.cmd()
+        .args(["prune", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("work.claude.json-100.bak (512 B)"))
+        .stdout(predicate::str::contains(".claude.json-200.bak (2.0 KB)"))
+        .stdout(predicate::str::contains("2 backup(s), 2.5 KB total"));
+
+    assert_eq!(env.backup_files().len(), 2);
+}
+
+#[test]
+fn test_prune_requires_confirmation_without_tty() {
+    let env = TestEnv::new();
+    env.create_backup("work.claude.json-100.bak", 10);
+
+    env.cmd()
+        .arg("prune")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("--yes"));
+
+    assert_eq!(env.backup_files().len(), 1);
+}
+
+#[test]
+fn test_prune_yes_deletes_only_backups() {
+    let env = TestEnv::new();
+    env.create_backup("work.claude.json-100.bak", 10);
+    env.create_backup("notes.txt", 10);
+
+    env.cmd()
+        .args(["--yes", "prune"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Deleted 1 backup(s)"));
+
+    assert_eq!(env.backup_files(), vec!["notes.txt".to_string()]);
+}
+
+#[test]
+fn test_prune_without_backups() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .arg("prune")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No backups found."));
+}
+
+// =============================================================================
+// LAUNCH EXIT CODE TESTS
+// =============================================================================
+
+#[test]
+fn test_launch_missing_claude_exits_with_launch_code() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["work", "--claude-bin"])
+        .arg(env.home_path().join("no-such-claude"))
+        .assert()
+        .code(7)
+        .stderr(predicate::str::contains("Failed to launch claude"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_launch_passes_through_claude_exit_code() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    let script = env.home_path().join("failing-claude");
+    fs::write(&script, "#!/bin/sh\nexit 42\n").expect("write script");
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+    env.cmd()
+        .args(["work", "--claude-bin"])
+        .arg(&script)
+        .assert()
+        .code(42);
+}
+
+// =============================================================================
+// SAVE --QUIET / --PRINT-PATH TESTS
+// =============================================================================
+
+#[test]
+fn test_save_print_path_prints_only_the_path() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("tmp", &sample_account("old"));
+
+    let output = env
+        .cmd()
+        .args(["--yes", "save", "tmp", "--quiet", "--print-path"])
+        .assert()
+        .success();
+
+    let expected = env
+        .profile_path("tmp")
+        .canonicalize()
+        .expect("canonicalize");
+    assert_eq!(
+        String::from_utf8_lossy(&output.get_output().stdout),
+        format!("{}\n", expected.display())
+    );
+    assert_eq!(
+        env.read_profile("tmp")["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+}
+
+#[test]
+fn test_save_quiet_prints_nothing() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+
+    env.cmd()
+        .args(["save", "work", "-q"])
+        .assert()
+        .success()
+        .stdout("");
+    assert!(env.profile_path("work").exists());
+}
+
+// =============================================================================
+// DELETE --TRASH / UNDELETE TESTS
+// =============================================================================
+
+impl TestEnv {
+    /// File names in ~/.claudectx/trash, sorted
+    fn trash_files(&self) -> Vec<String> {
+        self.backup_files_in(&self.claudectx_dir().join("trash"))
+    }
+}
+
+#[test]
+fn test_delete_trash_and_undelete_round_trip() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["delete", "work", "--trash"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Moved profile 'work' to"));
+
+    assert!(!env.profile_path("work").exists());
+    let trashed = env.trash_files();
+    assert_eq!(trashed.len(), 1);
+    assert!(trashed[0].starts_with("work-") && trashed[0].ends_with(".claude.json"));
+
+    env.cmd()
+        .args(["undelete", "work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Restored profile 'work'"));
+
+    assert_eq!(
+        env.read_profile("work")["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+    assert!(env.trash_files().is_empty());
+}
+
+#[test]
+fn test_undelete_restores_most_recent_copy() {
+    let env = TestEnv::new();
+    let trash = env.claudectx_dir().join("trash");
+    fs::create_dir_all(&trash).expect("mkdir");
+    for (secs, suffix) in [(100, "old"), (200, "new")] {
+        fs::write(
+            trash.join(format!("my-work-{}.claude.json", secs)),
+            serde_json::to_string(&json!({ "oauthAccount": sample_account(suffix) }))
+                .expect("serialize"),
+        )
+        .expect("write");
+    }
+    // A different profile whose name shares the prefix must be ignored
+    fs::write(trash.join("my-work-copy-300.claude.json"), "{}").expect("write");
+
+    env.cmd().args(["undelete", "my-work"]).assert().success();
+
+    assert_eq!(
+        env.read_profile("my-work")["oauthAccount"]["accountUuid"],
+        "uuid-new"
+    );
+    assert_eq!(env.trash_files().len(), 2);
+}
+
+#[test]
+fn test_trash_twice_in_a_row_keeps_both_copies() {
+    let env = TestEnv::new();
+    for suffix in ["first", "second"] {
+        env.create_profile("work", &sample_account(suffix));
+        env.cmd()
+            .args(["delete", "work", "--trash"])
+            .assert()
+            .success();
+    }
+
+    assert_eq!(env.trash_files().len(), 2);
+    env.cmd().args(["undelete", "work"]).assert().success();
+    assert_eq!(
+        env.read_profile("work")["oauthAccount"]["accountUuid"],
+        "uuid-second"
+    );
+}
+
+#[test]
+fn test_undelete_without_trashed_copy() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["undelete", "work"])
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains(
+            "No trashed copy of profile 'work'",
+        ));
+}
+
+#[test]
+fn test_undelete_refuses_to_overwrite() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    env.cmd()
+        .args(["delete", "work", "--trash"])
+        .assert()
+        .success();
+    env.create_profile("work", &sample_account("other"));
+
+    env.cmd()
+        .args(["undelete", "work"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("already exists"));
+}
+
+#[test]
+fn test_delete_all_trash() {
+    let env = TestEnv::new();
+    env.create_profile("a", &sample_account("a"));
+    env.create_profile("b", &sample_account("b"));
+
+    env.cmd()
+        .args(["--yes", "delete", "--all", "--trash"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Moved 2 profile(s)"));
+
+    assert_eq!(env.trash_files().len(), 2);
+}
+
+// =============================================================================
+// --VERIFY SWITCH TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_verify_confirms_switch_before_launch() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd_with_fake_claude()
+        .args(["work", "--verify"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("did not take effect").not());
+
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+}
+
+// =============================================================================
+// LOGIN --CLONE-FROM-CURRENT TESTS
+// =============================================================================
+
+#[cfg(unix)]
+impl TestEnv {
+    /// Install a fake `claude` whose `/login` copies the config it starts
+    /// with to ~/login-start.json, then writes a fresh config for `account`
+    fn install_fake_login_claude(&self, account: &serde_json::Value) -> std::path::PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let fresh = self.home_path().join("fresh-login.json");
+        fs::write(
+            &fresh,
+            serde_json::to_string_pretty(&json!({ "oauthAccount": account })).expect("serialize"),
+        )
+        .expect("write fresh config");
+
+        let bin_dir = self.home_path().join("bin");
+        fs::create_dir_all(&bin_dir).expect("mkdir");
+        let script = bin_dir.join("claude");
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\n[ \"$1\" = /login ] || exit 0\ncp {config:?} {start:?} 2>/dev/null\ncp {fresh:?} {config:?}\n",
+                config = self.claude_config_path(),
+                start = self.home_path().join("login-start.json"),
+                fresh = fresh,
+            ),
+        )
+        .expect("write fake claude");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+        script
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_login_clone_from_current_carries_portable_settings() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    let claude = env.install_fake_login_claude(&sample_account("new"));
+
+    env.cmd()
+        .args(["--yes", "--claude-bin"])
+        .arg(&claude)
+        .args(["login", "--name", "new", "--clone-from-current"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Carried over current settings"));
+
+    // The login session started from the portable settings only
+    let start: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(env.home_path().join("login-start.json")).expect("read"),
+    )
+    .expect("parse");
+    assert_eq!(start["hasCompletedOnboarding"], true);
+    assert!(start.get("oauthAccount").is_none());
+    assert_eq!(start["primaryApiKey"], "sk-ant-test-key");
+
+    assert_eq!(
+        env.read_profile("new")["oauthAccount"]["accountUuid"],
+        "uuid-new"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_login_without_clone_starts_empty() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    let claude = env.install_fake_login_claude(&sample_account("new"));
+
+    env.cmd()
+        .args(["--yes", "--claude-bin"])
+        .arg(&claude)
+        .args(["login", "--name", "new"])
+        .assert()
+        .success();
+
+    assert!(!env.home_path().join("login-start.json").exists());
+}
+
+// =============================================================================
+// LIST --EMAILS TESTS
+// =============================================================================
+
+#[test]
+fn test_list_emails_prints_bare_addresses() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("personal", &sample_account("personal"));
+    env.write_settings(&json!({ "extra_account_fields": ["primaryApiKey"] }));
+    fs::write(
+        env.profile_path("ci"),
+        serde_json::to_string(&json!({ "primaryApiKey": "sk-ant-api-abcd" })).expect("serialize"),
+    )
+    .expect("write");
+
+    env.cmd()
+        .args(["list", "--emails"])
+        .assert()
+        .success()
+        .stdout("user-personal@example.com\nuser-work@example.com\n");
+}
+
+#[test]
+fn test_list_emails_with_org_filter() {
+    let env = TestEnv::new();
+    let mut acme = sample_account("work");
+    acme["organizationName"] = json!("Acme");
+    env.create_profile("work", &acme);
+    env.create_profile("personal", &sample_account("personal"));
+
+    env.cmd()
+        .args(["list", "--emails", "--org", "acme"])
+        .assert()
+        .success()
+        .stdout("user-work@example.com\n");
+}
+
+#[test]
+fn test_list_emails_skips_broken_profile() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    fs::write(env.profile_path("broken"), "not json").expect("write");
+
+    env.cmd()
+        .args(["list", "--emails"])
+        .assert()
+        .success()
+        .stdout("user-work@example.com\n")
+        .stderr(predicate::str::contains(
+            "Warning: skipped profile 'broken'",
+        ));
+}
+
+#[test]
+fn test_list_emails_empty_prints_nothing() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["list", "--emails"])
+        .assert()
+        .success()
+        .stdout("");
+}
+
+// =============================================================================
+// HOME DIRECTORY TESTS
+// =============================================================================
+
+#[test]
+fn test_home_falls_back_to_home_env() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .env_remove("CLAUDECTX_HOME")
+        .env("HOME", env.home_path())
+        .env("USERPROFILE", env.home_path())
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("work - User work @ Org work"));
+}
+
+#[test]
+fn test_empty_claudectx_home_is_ignored() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .env("CLAUDECTX_HOME", "")
+        .env("HOME", env.home_path())
+        .env("USERPROFILE", env.home_path())
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("work - User work @ Org work"));
+}
+
+// =============================================================================
+// AFTER-SWITCH HOOK TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_after_hook_receives_profile_name() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    let log = env.home_path().join("hook.log");
+
+    env.cmd_with_fake_claude()
+        .args(["work", "--after"])
+        .arg(format!("echo \"$CLAUDECTX_PROFILE\" > {:?}", log))
+        .assert()
+        .success();
+
+    assert_eq!(fs::read_to_string(&log).expect("read hook log"), "work\n");
+    assert_eq!(env.recorded_claude_args(), Vec::<String>::new());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_after_switch_setting_is_used() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    let log = env.home_path().join("hook.log");
+    env.write_settings(&json!({
+        "after_switch": format!("echo \"$CLAUDECTX_PROFILE\" > {:?}", log)
+    }));
+
+    env.cmd_with_fake_claude().arg("work").assert().success();
+
+    assert_eq!(fs::read_to_string(&log).expect("read hook log"), "work\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_failing_after_hook_warns_but_launches() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd_with_fake_claude()
+        .args(["work", "--after", "exit 3"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Warning: after-switch hook 'exit 3' exited",
+        ));
+
+    assert!(env.home_path().join("claude-args.txt").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_failing_after_hook_blocks_with_strict_hooks() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd_with_fake_claude()
+        .args(["work", "--after", "exit 3", "--strict-hooks"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "after-switch hook 'exit 3' exited",
+        ));
+
+    assert!(!env.home_path().join("claude-args.txt").exists());
+}
+
+// =============================================================================
+// BEFORE-SWITCH HOOK TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_before_hook_sees_target_and_previous_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("personal"));
+    env.create_profile("personal", &sample_account("personal"));
+    env.create_profile("work", &sample_account("work"));
+    let log = env.home_path().join("hook.log");
+    let config = env.claude_config_path();
+
+    // The hook runs before the config is patched
+    env.cmd_with_fake_claude()
+        .args(["work", "--before"])
+        .arg(format!(
+            "echo \"$CLAUDECTX_PROFILE $CLAUDECTX_PREV_PROFILE\" > {log:?}; grep -c '\"uuid-personal\"' {config:?} >> {log:?}",
+        ))
+        .assert()
+        .success();
+
+    assert_eq!(
+        fs::read_to_string(&log).expect("read hook log"),
+        "work personal\n2\n"
+    );
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_before_hook_vetoes_switch_with_strict_hooks() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    env.write_settings(&json!({ "before_switch": "exit 1" }));
+
+    env.cmd_with_fake_claude()
+        .args(["work", "--strict-hooks"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "before-switch hook 'exit 1' exited",
+        ));
+
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-current"
+    );
+    assert!(!env.home_path().join("claude-args.txt").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_failing_before_hook_only_warns() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd_with_fake_claude()
+        .args(["work", "--before", "exit 1"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Warning: before-switch hook"));
+
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+}
+
+// =============================================================================
+// --PROFILE OPTION TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_profile_option_launches_profile_named_like_subcommand() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("list", &sample_account("list"));
+
+    env.cmd_with_fake_claude()
+        .args(["--profile", "list", "--", "hello"])
+        .assert()
+        .success();
+
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-list"
+    );
+    assert_eq!(env.recorded_claude_args(), vec!["hello"]);
+}
+
+#[test]
+fn test_positional_subcommand_name_runs_subcommand() {
+    let env = TestEnv::new();
+    env.create_profile("list", &sample_account("list"));
+
+    env.cmd()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("list - User list @ Org list"));
+}
+
+#[test]
+fn test_profile_option_rejects_subcommand() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["--profile", "work", "list"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "can't be combined with a subcommand",
+        ));
+}
+
+#[test]
+fn test_profile_option_conflicts_with_positional() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["--profile", "work", "personal"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+// =============================================================================
+// WHOAMI TESTS
+// =============================================================================
+
+#[test]
+fn test_whoami_prints_identity_without_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+
+    env.cmd()
+        .arg("whoami")
+        .assert()
+        .success()
+        .stdout("User current <user-current@example.com> @ Org current\n");
+}
+
+#[test]
+fn test_whoami_json() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("current"));
+
+    let output = env.cmd().args(["whoami", "--json"]).assert().success();
+    let account: serde_json::Value =
+        serde_json::from_slice(&output.get_output().stdout).expect("parse json");
+
+    assert_eq!(account["account_uuid"], "uuid-current");
+    assert_eq!(account["email_address"], "user-current@example.com");
+    assert_eq!(account["organization_name"], "Org current");
+    assert!(account.get("profile").is_none());
+}
+
+#[test]
+fn test_whoami_without_config() {
+    let env = TestEnv::new();
+
+    env.cmd().arg("whoami").assert().code(2);
+}
+
+// =============================================================================
+// DUPLICATE ACCOUNT CURRENT MARKER TESTS
+// =============================================================================
+
+#[test]
+fn test_duplicate_uuid_profiles_mark_alphabetically_first() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("shared"));
+    env.create_profile("zeta", &sample_account("shared"));
+    env.create_profile("alpha", &sample_account("shared"));
+    env.create_profile("mid", &sample_account("shared"));
+
+    for _ in 0..3 {
+        env.cmd()
+            .arg("list")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "alpha - User shared @ Org shared *",
+            ))
+            .stdout(predicate::str::contains(
+                "zeta - User shared @ Org shared\n",
+            ))
+            .stdout(predicate::str::contains("mid - User shared @ Org shared\n"));
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_duplicate_uuid_profiles_prefer_last_switched() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("alpha", &sample_account("shared"));
+    env.create_profile("zeta", &sample_account("shared"));
+
+    env.cmd_with_fake_claude().arg("zeta").assert().success();
+
+    let current = fs::read_to_string(env.claudectx_dir().join(".current")).expect("read");
+    assert_eq!(current.trim(), "zeta");
+    env.cmd()
+        .arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("profile 'zeta'"));
+}
+
+#[test]
+fn test_stale_current_marker_is_ignored() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("shared"));
+    env.create_profile("beta", &sample_account("shared"));
+    env.create_profile("gamma", &sample_account("shared"));
+    fs::write(env.claudectx_dir().join(".current"), "deleted\n").expect("write");
+
+    env.cmd()
+        .arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("profile 'beta'"));
+}
+
+// =============================================================================
+// --NO-MIGRATE TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_no_migrate_leaves_symlinked_setup_untouched() {
+    let env = TestEnv::new();
+    env.create_symlinked_claude_config(&sample_account("linked"));
+    env.create_unslimmed_profile("old");
+
+    env.cmd()
+        .args(["list", "--no-migrate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("old - User old @ Org old"))
+        .stdout(predicate::str::contains("Migrated").not());
+
+    assert!(env.claude_config_path().is_symlink());
+    assert_eq!(env.read_profile("old")["customSetting"], "old-value");
+    assert!(env.backup_files().is_empty());
+
+    // Without the flag the migration still runs
+    env.cmd().arg("list").assert().success();
+    assert!(!env.claude_config_path().is_symlink());
+}
+
+// =============================================================================
+// SAVE --MERGE TESTS
+// =============================================================================
+
+#[test]
+fn test_save_merge_keeps_extra_keys() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    let mut profile = json!({
+        "oauthAccount": sample_account("old"),
+        "userID": "user-old",
+        "claudectxNote": "hand-added"
+    });
+    fs::create_dir_all(env.claudectx_dir()).expect("mkdir");
+    fs::write(
+        env.profile_path("work"),
+        serde_json::to_string(&profile).expect("serialize"),
+    )
+    .expect("write");
+
+    // No overwrite prompt, so this works without a TTY or --yes
+    env.cmd()
+        .args(["save", "work", "--merge"])
+        .assert()
+        .success();
+
+    profile = env.read_profile("work");
+    assert_eq!(profile["oauthAccount"]["accountUuid"], "uuid-work");
+    assert_eq!(profile["claudectxNote"], "hand-added");
+    assert_eq!(profile["userID"], "user-old");
+}
+
+#[test]
+fn test_save_without_merge_replaces_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    fs::create_dir_all(env.claudectx_dir()).expect("mkdir");
+    fs::write(
+        env.profile_path("work"),
+        serde_json::to_string(&json!({
+            "oauthAccount": sample_account("old"),
+            "claudectxNote": "hand-added"
+        }))
+        .expect("serialize"),
+    )
+    .expect("write");
+
+    env.cmd().args(["--yes", "save", "work"]).assert().success();
+
+    assert!(env.read_profile("work").get("claudectxNote").is_none());
+}
+
+#[test]
+fn test_save_merge_creates_missing_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+
+    env.cmd()
+        .args(["save", "fresh", "--merge"])
+        .assert()
+        .success();
+
+    assert_eq!(
+        env.read_profile("fresh")["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+}
+
+// =============================================================================
+// OPEN TESTS
+// =============================================================================
+
+#[test]
+fn test_open_print_path_for_directory_and_profile() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["open", "--print-path"])
+        .assert()
+        .success()
+        .stdout(format!("{}\n", env.claudectx_dir().display()));
+    env.cmd()
+        .args(["open", "work", "--print-path"])
+        .assert()
+        .success()
+        .stdout(format!("{}\n", env.profile_path("work").display()));
+}
+
+#[test]
+fn test_open_missing_profile() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["open", "nope", "--print-path"])
+        .assert()
+        .code(4);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_open_without_handler_errors_clearly() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .env("PATH", env.home_path())
+        .arg("open")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("No file opener found"))
+        .stderr(predicate::str::contains("--print-path"));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_open_uses_xdg_open() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    let bin_dir = env.home_path().join("bin");
+    fs::create_dir_all(&bin_dir).expect("mkdir");
+    let opener = bin_dir.join("xdg-open");
+    fs::write(
+        &opener,
+        format!(
+            "#!/bin/sh\necho \"$1\" > {:?}\n",
+            env.home_path().join("opened.txt")
+        ),
+    )
+    .expect("write opener");
+    fs::set_permissions(&opener, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+    env.cmd()
+        .env("PATH", &bin_dir)
+        .args(["open", "work"])
+        .assert()
+        .success();
+
+    assert_eq!(
+        fs::read_to_string(env.home_path().join("opened.txt")).expect("read"),
+        format!("{}\n", env.profile_path("work").display())
+    );
+}
+
+// =============================================================================
+// STATS TESTS
+// =============================================================================
+
+#[test]
+fn test_stats_json_summary() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    let mut acme = sample_account("work");
+    acme["organizationName"] = json!("Acme");
+    env.create_profile("work", &acme);
+    let mut acme_dev = sample_account("dev");
+    acme_dev["organizationName"] = json!("Acme");
+    env.create_profile("dev", &acme_dev);
+    env.create_profile("personal", &sample_account("personal"));
+
+    let output = env.cmd().args(["stats", "--json"]).assert().success();
+    let stats: serde_json::Value =
+        serde_json::from_slice(&output.get_output().stdout).expect("parse json");
+
+    assert_eq!(
+        stats,
+        json!({
+            "total": 3,
+            "current": "work",
+            "orgs": { "Acme": 2, "Org personal": 1 }
+        })
+    );
+}
+
+#[test]
+fn test_stats_skips_broken_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("work", &sample_account("work"));
+    fs::write(env.profile_path("broken"), "{").expect("write broken profile");
+
+    env.cmd()
+        .arg("stats")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Profiles: 1"))
+        .stdout(predicate::str::contains("Org work: 1"))
+        .stderr(predicate::str::contains(
+            "Warning: skipped profile 'broken'",
+        ));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_picker_skips_broken_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    // Sorted first, so plain mode would pick it if it weren't skipped
+    fs::write(env.profile_path("a-broken"), "{").expect("write broken profile");
+
+    env.cmd_with_fake_claude()
+        .arg("--plain")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Warning: skipped profile 'a-broken'",
+        ))
+        .stderr(predicate::str::contains("Select Claude profile: work"));
+
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+}
+
+#[test]
+fn test_stats_text_without_profiles() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .arg("stats")
+        .assert()
+        .success()
+        .stdout("Profiles: 0\nCurrent: none\n");
+}
+
+// =============================================================================
+// DELETE MARKER CLEANUP TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_delete_active_profile_clears_markers() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    env.cmd_with_fake_claude().arg("work").assert().success();
+    env.cmd().args(["set-default", "work"]).assert().success();
+
+    env.cmd().args(["delete", "work"]).assert().success();
+
+    assert!(!env.claudectx_dir().join(".current").exists());
+    assert!(!env.claudectx_dir().join(".default").exists());
+    env.cmd()
+        .arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("unsaved account"));
+}
+
+#[test]
+fn test_delete_other_profile_keeps_markers() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("other", &sample_account("other"));
+    env.cmd().args(["save", "work"]).assert().success();
+    env.cmd().args(["set-default", "work"]).assert().success();
+
+    env.cmd()
+        .args(["delete", "other", "--trash"])
+        .assert()
+        .success();
+
+    let current = fs::read_to_string(env.claudectx_dir().join(".current")).expect("read");
+    assert_eq!(current.trim(), "work");
+    assert!(env.claudectx_dir().join(".default").exists());
+}
+
+// =============================================================================
+// SAVE ACCOUNT OVERRIDE TESTS
+// =============================================================================
+
+#[test]
+fn test_save_overrides_account_labels() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+
+    env.cmd()
+        .args([
+            "save",
+            "work",
+            "--display-name",
+            "Jane",
+            "--email",
+            "jane@acme.test",
+            "--org",
+            "Acme",
+        ])
+        .assert()
+        .success();
+
+    let account = &env.read_profile("work")["oauthAccount"];
+    assert_eq!(account["displayName"], "Jane");
+    assert_eq!(account["emailAddress"], "jane@acme.test");
+    assert_eq!(account["organizationName"], "Acme");
+    assert_eq!(account["accountUuid"], "uuid-work");
+    // The live config is left as it was
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["displayName"],
+        "User work"
+    );
+}
+
+#[test]
+fn test_save_override_requires_oauth_account() {
+    let env = TestEnv::new();
+    env.write_settings(&json!({ "extra_account_fields": ["primaryApiKey"] }));
+    env.create_api_key_config("sk-ant-api-abcd");
+
+    env.cmd()
+        .args(["save", "ci", "--org", "Acme"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("need an oauthAccount"));
+    assert!(!env.profile_path("ci").exists());
+}
+
+// =============================================================================
+// PLAIN MODE TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_plain_launch_picks_current_profile_without_prompting() {
+    let env = TestEnv::new();
+    env.create_profile("alpha", &sample_account("alpha"));
+    env.create_profile("work", &sample_account("work"));
+    env.create_claude_config(&sample_account("work"));
+
+    env.cmd_with_fake_claude()
+        .arg("--plain")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Select Claude profile: work"));
+
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+    assert!(env.recorded_claude_args().is_empty());
+}
+
+#[test]
+fn test_plain_confirmation_uses_default_answer() {
+    let env = TestEnv::new();
+    env.create_profile("alpha", &sample_account("alpha"));
+
+    env.cmd()
+        .args(["--plain", "delete", "--all"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Cancelled."))
+        .stderr(predicate::str::contains("Delete 1 profile(s)? no"));
+    assert!(env.profile_path("alpha").exists());
+
+    // An explicit --yes still answers yes
+    env.cmd()
+        .args(["--plain", "--yes", "delete", "--all"])
+        .assert()
+        .success();
+    assert!(!env.profile_path("alpha").exists());
+}
+
+// =============================================================================
+// LAUNCH FAILURE TESTS
+// =============================================================================
+
+#[test]
+fn test_launch_missing_claude_explains_and_keeps_switch() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["work", "--claude-bin"])
+        .arg(env.home_path().join("no-such-claude"))
+        .assert()
+        .code(7)
+        .stderr(predicate::str::contains("not found. Install Claude Code"))
+        .stderr(predicate::str::contains("already switched to 'work'"));
+
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+}
+
+#[test]
+fn test_launch_failure_names_the_config_in_use() {
+    let env = TestEnv::new();
+    let custom_path = env.home_path().join("custom-claude.json");
+    fs::write(
+        &custom_path,
+        serde_json::to_string_pretty(&json!({ "oauthAccount": sample_account("current") }))
+            .expect("serialize"),
+    )
+    .expect("write custom config");
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .env("CLAUDE_CONFIG_PATH", &custom_path)
+        .args(["work", "--claude-bin"])
+        .arg(env.home_path().join("no-such-claude"))
+        .assert()
+        .code(7)
+        .stderr(predicate::str::contains(format!(
+            "{} is already switched to 'work'",
+            custom_path.display()
+        )));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_launch_permission_error_is_not_retried() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    let script = env.home_path().join("not-executable");
+    fs::write(&script, "#!/bin/sh\nexit 0\n").expect("write script");
+
+    env.cmd()
+        .args(["work", "--claude-bin"])
+        .arg(&script)
+        .assert()
+        .code(7)
+        .stderr(predicate::str::contains("Failed to launch claude"))
+        .stderr(predicate::str::contains("retrying").not());
+}
+
+// =============================================================================
+// IMPORT TESTS
+// =============================================================================
+
+#[test]
+fn test_import_restores_exported_profile() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    let file = env.home_path().join("work.claude.json");
+    env.cmd()
+        .args(["export", "work", "--include-secrets", "-o"])
+        .arg(&file)
+        .assert()
+        .success();
+
+    env.cmd()
+        .arg("import")
+        .arg(&file)
+        .args(["--name", "Client A"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Imported 'client-a'"));
+    assert_eq!(
+        env.read_profile("client-a")["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+
+    // Without --name the file name is used
+    env.cmd().args(["delete", "work"]).assert().success();
+    env.cmd().arg("import").arg(&file).assert().success();
+    assert!(env.profile_path("work").exists());
+}
+
+#[test]
+fn test_import_no_clobber_refuses_existing_profile() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    let file = env.home_path().join("other.json");
+    fs::write(
+        &file,
+        serde_json::to_string(&json!({ "oauthAccount": sample_account("other") })).unwrap(),
+    )
+    .expect("write import file");
+
+    env.cmd()
+        .arg("import")
+        .arg(&file)
+        .args(["--name", "work", "--no-clobber"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("already exists (--no-clobber)"));
+    assert_eq!(
+        env.read_profile("work")["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+
+    // --no-clobber wins over --yes
+    env.cmd()
+        .arg("import")
+        .arg(&file)
+        .args(["--name", "work", "--no-clobber", "--yes"])
+        .assert()
+        .code(1);
+
+    env.cmd()
+        .arg("import")
+        .arg(&file)
+        .args(["--name", "work", "--yes"])
+        .assert()
+        .success();
+    assert_eq!(
+        env.read_profile("work")["oauthAccount"]["accountUuid"],
+        "uuid-other"
+    );
+}
+
+// =============================================================================
+// UTF-8 BOM TESTS
+// =============================================================================
+
+#[test]
+fn test_bom_prefixed_config_and_profile_are_read() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    let config = fs::read_to_string(env.claude_config_path()).expect("read config");
+    fs::write(env.claude_config_path(), format!("\u{feff}{}", config)).expect("write config");
+
+    env.cmd().args(["save", "work"]).assert().success();
+    assert_eq!(
+        env.read_profile("work")["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+
+    let profile = fs::read_to_string(env.profile_path("work")).expect("read profile");
+    fs::write(env.profile_path("work"), format!("\u{feff}{}", profile)).expect("write profile");
+
+    env.cmd()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("work"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_bom_prefixed_stdin_config_is_read() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    let piped = json!({ "oauthAccount": sample_account("ci") });
+
+    env.cmd_with_fake_claude()
+        .arg("--from-stdin")
+        .write_stdin(format!("\u{feff}{}", piped))
+        .assert()
+        .success();
+
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-ci"
+    );
+}
+
+#[test]
+fn test_bom_prefixed_state_files_are_read() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    let bom = |value: serde_json::Value| format!("\u{feff}{}", value);
+    fs::write(
+        env.claudectx_dir().join(".settings.json"),
+        bom(json!({ "sort": "recent" })),
+    )
+    .expect("write settings");
+    fs::write(env.usage_path(), bom(json!({ "work": now_secs() }))).expect("write usage");
+    fs::write(
+        env.claudectx_dir().join(".accounts.json"),
+        bom(json!({ "uuid-work": "user-work@example.com" })),
+    )
+    .expect("write accounts");
+    fs::write(
+        env.claudectx_dir().join(".aliases.json"),
+        bom(json!({ "w": "work" })),
+    )
+    .expect("write aliases");
+
+    env.cmd()
+        .args(["config", "sort"])
+        .assert()
+        .success()
+        .stdout("recent\n");
+    env.cmd()
+        .arg("alias")
+        .assert()
+        .success()
+        .stdout("w -> work\n");
+    env.cmd()
+        .args(["list", "--since", "1w"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("work"));
+    // Rewriting the settings drops the BOM instead of mistaking it for a comment
+    env.cmd()
+        .args(["config", "banner", "true"])
+        .assert()
+        .success();
+}
+
+// =============================================================================
+// VERIFY-BACKUP TESTS
+// =============================================================================
+
+impl TestEnv {
+    fn write_backup(&self, name: &str, content: &str) {
+        fs::create_dir_all(self.backups_dir()).expect("mkdir");
+        fs::write(self.backups_dir().join(name), content).expect("write backup");
+    }
+}
+
+#[test]
+fn test_verify_backup_reports_each_backup() {
+    let env = TestEnv::new();
+    let valid = serde_json::to_string(&json!({ "oauthAccount": sample_account("work") })).unwrap();
+    env.write_backup(".claude.json-100.bak", "{\"oauthAccount\": {");
+    env.write_backup(".claude.json-200.bak", "{\"theme\": \"dark\"}");
+    env.write_backup(".claude.json-300.bak", &valid);
+
+    env.cmd()
+        .arg("verify-backup")
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::is_match(r"corrupt\s+\S*\.claude\.json-100\.bak \(invalid JSON")
+                .unwrap(),
+        )
+        .stdout(
+            predicate::str::is_match(
+                r"corrupt\s+\S*\.claude\.json-200\.bak \(missing oauthAccount\)",
+            )
+            .unwrap(),
+        )
+        .stdout(predicate::str::is_match(r"ok\s+\S*\.claude\.json-300\.bak").unwrap());
+}
+
+#[test]
+fn test_verify_backup_fails_when_latest_is_corrupt() {
+    let env = TestEnv::new();
+    let valid = serde_json::to_string(&json!({ "oauthAccount": sample_account("work") })).unwrap();
+    env.write_backup(".claude.json-1000.bak", &valid);
+    // Ordered by timestamp, not by name
+    env.write_backup(".claude.json-999999.bak", &valid[..valid.len() / 2]);
+
+    env.cmd()
+        .arg("verify-backup")
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains("Most recent backup"))
+        .stderr(predicate::str::contains(".claude.json-999999.bak"));
+}
+
+#[test]
+fn test_verify_backup_without_backups() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .arg("verify-backup")
+        .assert()
+        .success()
+        .stdout("No backups found.\n");
+}
+
+// =============================================================================
+// LIST SKIPPED FOOTER TESTS
+// =============================================================================
+
+#[test]
+fn test_list_footer_counts_skipped_profiles() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    fs::write(env.profile_path("broken"), "{ not json").expect("write broken profile");
+    fs::write(
+        env.profile_path("anonymous"),
+        r#"{"oauthAccount": {"displayName": "Nobody"}}"#,
+    )
+    .expect("write anonymous profile");
+
+    for args in [vec!["list"], vec!["list", "--wide"]] {
+        env.cmd()
+            .args(&args)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("work"))
+            .stdout(predicate::str::contains("broken").not())
+            .stdout(predicate::str::contains(
+                "(2 profiles skipped: 1 missing accountUuid, 1 parse error)",
+            ));
+    }
+}
+
+#[test]
+fn test_list_without_problems_has_no_footer() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("skipped").not());
+}
+
+// =============================================================================
+// LAUNCH --CREATE TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_launch_create_saves_missing_profile_and_launches() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+
+    env.cmd_with_fake_claude()
+        .args(["New Name", "--create"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Profile 'new-name' saved."));
+
+    assert_eq!(
+        env.read_profile("new-name")["oauthAccount"]["accountUuid"],
+        "uuid-current"
+    );
+    assert!(env.recorded_claude_args().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_launch_create_leaves_existing_profile_alone() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd_with_fake_claude()
+        .args(["work", "--create"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("saved").not());
+
+    assert_eq!(
+        env.read_profile("work")["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+}
+
+// =============================================================================
+// SETTINGS COMMENT TESTS
+// =============================================================================
+
+#[test]
+fn test_settings_file_accepts_comments() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    fs::create_dir_all(env.claudectx_dir()).expect("mkdir");
+    fs::write(
+        env.claudectx_dir().join(".settings.json"),
+        r#"{
+  // Account-scoped since the API-key-only CI account
+  "extra_account_fields": ["primaryApiKey"],
+  /* URLs keep their slashes:
+     the hook below is not a comment */
+  "after_switch": "echo https://example.com/*"
+}
+"#,
+    )
+    .expect("write settings");
+
+    env.cmd().args(["save", "work"]).assert().success();
+    assert_eq!(env.read_profile("work")["primaryApiKey"], "sk-ant-test-key");
+
+    env.cmd()
+        .args(["config", "after_switch"])
+        .assert()
+        .success()
+        .stdout("echo https://example.com/*\n");
+}
+
+#[test]
+fn test_config_set_refuses_to_drop_comments() {
+    let env = TestEnv::new();
+    fs::create_dir_all(env.claudectx_dir()).expect("mkdir");
+    let annotated = "{\n  // Keep recent profiles first\n  \"sort\": \"recent\"\n}\n";
+    fs::write(env.claudectx_dir().join(".settings.json"), annotated).expect("write settings");
+
+    env.cmd()
+        .args(["config", "banner", "true"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("contains comments"));
+    assert_eq!(
+        fs::read_to_string(env.claudectx_dir().join(".settings.json")).expect("read settings"),
+        annotated
+    );
+}
+
+// =============================================================================
+// TOUCH TESTS
+// =============================================================================
+
+#[test]
+fn test_touch_moves_profile_to_top_of_recent_list() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("alpha", &sample_account("alpha"));
+    env.create_profile("beta", &sample_account("beta"));
+    env.write_usage(&json!({ "alpha": now_secs() - 60, "beta": now_secs() - 3600 }));
+
+    env.cmd()
+        .args(["touch", "beta"])
+        .assert()
+        .success()
+        .stdout("Marked 'beta' as used now\n");
+
+    let output = env
+        .cmd()
+        .args(["list", "--sort", "recent"])
+        .output()
+        .unwrap();
+    assert_eq!(
+        listed_names(&String::from_utf8_lossy(&output.stdout)),
+        vec!["beta", "alpha"]
+    );
+    // The live config is untouched
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-current"
+    );
+}
+
+#[test]
+fn test_touch_missing_profile_fails() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["touch", "ghost"])
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("Profile 'ghost' not found"));
+    assert!(!env.usage_path().exists());
+}
+
+// =============================================================================
+// TRAILING NEWLINE TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_written_files_end_with_newline() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.cmd().args(["save", "current"]).assert().success();
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd_with_fake_claude().arg("work").assert().success();
+
+    let profile = fs::read_to_string(env.profile_path("current")).expect("read profile");
+    assert!(profile.ends_with("}\n"));
+    let config = fs::read_to_string(env.claude_config_path()).expect("read config");
+    assert!(config.ends_with("}\n"));
+}
+
+// =============================================================================
+// RENAME-CURRENT TESTS
+// =============================================================================
+
+#[test]
+fn test_rename_current_renames_active_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("other", &sample_account("other"));
+    env.cmd().args(["set-default", "work"]).assert().success();
+
+    env.cmd()
+        .args(["rename-current", "Day Job"])
+        .assert()
+        .success()
+        .stdout("Renamed profile 'work' to 'day-job'\n");
+
+    assert!(!env.profile_path("work").exists());
+    assert!(env.profile_path("other").exists());
+    let default = fs::read_to_string(env.claudectx_dir().join(".default")).expect("read");
+    assert_eq!(default.trim(), "day-job");
+    env.cmd()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("day-job - User work @ Org work *"));
+}
+
+#[test]
+fn test_rename_current_without_current_profile_fails() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("unsaved"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["rename-current", "job"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("No saved profile matches"));
+    assert!(env.profile_path("work").exists());
+}
+
+// =============================================================================
+// BASE URL TESTS
+// =============================================================================
+
+impl TestEnv {
+    /// Install a fake claude that records $ANTHROPIC_BASE_URL (or "unset")
+    #[cfg(unix)]
+    fn install_base_url_claude(&self) -> std::path::PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let script = self.home_path().join("env-claude");
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\necho \"${{ANTHROPIC_BASE_URL:-unset}}\" > {:?}\n",
+                self.home_path().join("base-url.txt")
+            ),
+        )
+        .expect("write script");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+        script
+    }
+
+    fn recorded_base_url(&self) -> String {
+        fs::read_to_string(self.home_path().join("base-url.txt"))
+            .expect("Fake claude was not launched")
+            .trim()
+            .to_string()
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_base_url_is_passed_to_claude_but_not_written_to_config() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.cmd()
+        .args(["save", "work", "--base-url", "https://gateway.example.com"])
+        .assert()
+        .success();
+    assert_eq!(
+        env.read_profile("work")["baseUrl"],
+        "https://gateway.example.com"
+    );
+    let script = env.install_base_url_claude();
+
+    env.cmd()
+        .args(["work", "--claude-bin"])
+        .arg(&script)
+        .env_remove("ANTHROPIC_BASE_URL")
+        .assert()
+        .success();
+
+    assert_eq!(env.recorded_base_url(), "https://gateway.example.com");
+    assert!(env.read_claude_config().get("baseUrl").is_none());
+    env.cmd()
+        .args(["validate", "work", "--strict"])
+        .assert()
+        .success();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_profile_without_base_url_leaves_env_alone() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    let script = env.install_base_url_claude();
+
+    env.cmd()
+        .args(["work", "--claude-bin"])
+        .arg(&script)
+        .env_remove("ANTHROPIC_BASE_URL")
+        .assert()
+        .success();
+
+    assert_eq!(env.recorded_base_url(), "unset");
+}
+
+#[test]
+fn test_show_displays_base_url() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.cmd()
+        .args(["save", "work", "--base-url", "https://gateway.example.com"])
+        .assert()
+        .success();
+
+    env.cmd()
+        .args(["show", "work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "\"baseUrl\": \"https://gateway.example.com\"",
+        ));
+    let output = env.cmd().args(["show", "work", "--json"]).output().unwrap();
+    let shown: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    assert_eq!(shown["base_url"], "https://gateway.example.com");
+}
+
+// =============================================================================
+// HISTORY TESTS
+// =============================================================================
+
+impl TestEnv {
+    fn history_path(&self) -> std::path::PathBuf {
+        self.claudectx_dir().join(".history")
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_history_lists_switches_newest_first() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("personal", &sample_account("personal"));
+
+    for profile in ["work", "personal"] {
+        env.cmd_with_fake_claude().arg(profile).assert().success();
+    }
+
+    let output = env.cmd().arg("history").output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let profiles: Vec<&str> = stdout
+        .lines()
+        .map(|line| line.rsplit("  ").next().unwrap())
+        .collect();
+    assert_eq!(profiles, vec!["personal", "work"]);
+
+    let output = env
+        .cmd()
+        .args(["history", "-n", "1", "--json"])
+        .output()
+        .unwrap();
+    let entries: serde_json::Value = serde_json::from_slice(&output.stdout).expect("json");
+    assert_eq!(entries.as_array().unwrap().len(), 1);
+    assert_eq!(entries[0]["profile"], "personal");
+    assert!(entries[0]["time"].as_str().unwrap().ends_with('Z'));
+}
+
+#[test]
+fn test_history_empty() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .arg("history")
+        .assert()
+        .success()
+        .stdout("No switches recorded yet.\n");
+    env.cmd()
+        .args(["history", "--json"])
+        .assert()
+        .success()
+        .stdout("[]\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_history_log_is_capped() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    let old: String = (0..500)
+        .map(|i| format!("{}\told-{}\n", 1000 + i, i))
+        .collect();
+    fs::write(env.history_path(), old).expect("write history");
+
+    env.cmd_with_fake_claude().arg("work").assert().success();
+
+    let history = fs::read_to_string(env.history_path()).expect("read history");
+    let lines: Vec<&str> = history.lines().collect();
+    assert_eq!(lines.len(), 500);
+    assert_eq!(lines[0], "1001\told-1");
+    assert!(lines[499].ends_with("\twork"));
+}
+
+// =============================================================================
+// FULL PROFILE SWITCH TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_switch_to_full_profile_backs_up_replaced_config() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("full"));
+    env.cmd()
+        .args(["save", "snap", "--all-fields"])
+        .assert()
+        .success();
+    fs::write(
+        env.claude_config_path(),
+        serde_json::to_string_pretty(&json!({
+            "oauthAccount": sample_account("other"),
+            "mcpServers": { "only-here": {} }
+        }))
+        .unwrap(),
+    )
+    .unwrap();
+
+    env.cmd_with_fake_claude().arg("snap").assert().success();
+
+    assert!(env.read_claude_config().get("mcpServers").is_none());
+    let backups = env.backup_files();
+    assert_eq!(backups.len(), 1);
+    let backup: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(env.backups_dir().join(&backups[0])).unwrap())
+            .unwrap();
+    assert!(backup["mcpServers"]["only-here"].is_object());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_switch_to_slim_profile_patches_without_backup() {
+    let env = TestEnv::new();
+    fs::write(
+        env.claude_config_path(),
+        serde_json::to_string_pretty(&json!({
+            "oauthAccount": sample_account("other"),
+            "mcpServers": { "kept": {} }
+        }))
+        .unwrap(),
+    )
+    .unwrap();
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd_with_fake_claude().arg("work").assert().success();
+
+    let config = env.read_claude_config();
+    assert_eq!(config["oauthAccount"]["accountUuid"], "uuid-work");
+    assert!(config["mcpServers"]["kept"].is_object());
+    assert!(env.backup_files().is_empty());
+}
+
+// =============================================================================
+// CLAUDE VERSION PREFLIGHT TESTS
+// =============================================================================
+
+impl TestEnv {
+    /// Install a fake claude reporting `version` for --version and recording
+    /// whether it was launched otherwise
+    #[cfg(unix)]
+    fn install_versioned_claude(&self, version: &str) -> std::path::PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let script = self.home_path().join("versioned-claude");
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\nif [ \"$1\" = --version ]; then echo '{} (Claude Code)'; exit 0; fi\ntouch {:?}\n",
+                version,
+                self.home_path().join("launched")
+            ),
+        )
+        .expect("write script");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).expect("chmod");
+        script
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_require_claude_version_refuses_older_claude() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    let script = env.install_versioned_claude("1.0.9");
+
+    env.cmd()
+        .args(["work", "--require-claude-version", "1.0.30", "--claude-bin"])
+        .arg(&script)
+        .assert()
+        .code(7)
+        .stderr(predicate::str::contains(
+            "claude 1.0.9 is older than the required 1.0.30",
+        ));
+
+    // Nothing was switched or launched
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-current"
+    );
+    assert!(!env.home_path().join("launched").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_check_claude_version_only_warns() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    let script = env.install_versioned_claude("1.0.9");
+
+    env.cmd()
+        .args(["work", "--check-claude-version", "1.0.30", "--claude-bin"])
+        .arg(&script)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Warning: claude 1.0.9 is older"));
+    assert!(env.home_path().join("launched").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_require_claude_version_accepts_equal_or_newer() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    let script = env.install_versioned_claude("1.2.0");
+
+    env.cmd()
+        .args(["work", "--require-claude-version", "1.2", "--claude-bin"])
+        .arg(&script)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Warning").not());
+    assert!(env.home_path().join("launched").exists());
+}
+
+// =============================================================================
+// PROFILE NAME SAFETY TESTS
+// =============================================================================
+
+#[test]
+fn test_traversal_name_stays_inside_profiles_dir() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+
+    env.cmd()
+        .args(["save", "../../etc/passwd"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("etc-passwd"));
+
+    assert!(env.profile_path("etc-passwd").exists());
+    assert!(!env.home_path().join("etc").exists());
+    let outside: Vec<_> = fs::read_dir(env.home_path())
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().contains("passwd"))
+        .collect();
+    assert!(outside.is_empty());
+}
+
+#[test]
+fn test_name_without_letters_or_digits_is_refused() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+
+    for name in ["../..", "/", "..\\.."] {
+        env.cmd()
+            .args(["save", name])
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("Invalid profile name"));
+    }
+    assert!(!env.profile_path("").exists());
+}
+
+// =============================================================================
+// COPY TESTS
+// =============================================================================
+
+#[test]
+fn test_copy_duplicates_profile() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["copy", "work", "work-2"])
+        .assert()
+        .success()
+        .stdout("Copied profile 'work' to 'work-2'\n");
+
+    assert_eq!(env.read_profile("work-2"), env.read_profile("work"));
+
+    env.cmd()
+        .args(["copy", "work", "work-2"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Profile 'work-2' already exists"));
+    env.cmd().args(["copy", "ghost", "x"]).assert().code(4);
+}
+
+#[test]
+fn test_copy_from_current_takes_account_from_live_config() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.cmd()
+        .args(["save", "work", "--base-url", "https://gateway.example.com"])
+        .assert()
+        .success();
+    env.create_claude_config(&sample_account("sibling"));
+
+    env.cmd()
+        .args(["copy", "work", "work-sibling", "--from-current"])
+        .assert()
+        .success()
+        .stdout("Saved current config as 'work-sibling' with the settings of 'work'\n");
+
+    let copy = env.read_profile("work-sibling");
+    assert_eq!(copy["oauthAccount"]["accountUuid"], "uuid-sibling");
+    assert_eq!(copy["baseUrl"], "https://gateway.example.com");
+    // The template is untouched
+    assert_eq!(
+        env.read_profile("work")["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+}
+
+// =============================================================================
+// SAVE --NAME-FROM-ACCOUNT TESTS
+// =============================================================================
+
+#[test]
+fn test_save_name_from_account_uses_email_local_part() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+
+    env.cmd()
+        .args(["save", "--name-from-account"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("user-work"));
+
+    assert_eq!(
+        env.read_profile("user-work")["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+}
+
+#[test]
+fn test_save_name_from_account_falls_back_to_org_and_yields_to_name() {
+    let env = TestEnv::new();
+    let mut account = sample_account("work");
+    account["emailAddress"] = json!("");
+    env.create_claude_config(&account);
+
+    env.cmd()
+        .args(["save", "--name-from-account"])
+        .assert()
+        .success();
+    assert!(env.profile_path("org-work").exists());
+
+    // An explicit name wins
+    env.cmd()
+        .args(["save", "explicit", "--name-from-account"])
+        .assert()
+        .success();
+    assert!(env.profile_path("explicit").exists());
+}
+
+#[test]
+fn test_save_requires_name_or_flag() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+
+    env.cmd().arg("save").assert().failure();
+}
+
+// =============================================================================
+// ENV TESTS
+// =============================================================================
+
+#[test]
+fn test_env_prints_export_lines() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.cmd()
+        .args(["save", "work", "--base-url", "https://gw.example.com/it's"])
+        .assert()
+        .success();
+
+    env.cmd()
+        .args(["env", "work"])
+        .assert()
+        .success()
+        .stdout("export ANTHROPIC_BASE_URL='https://gw.example.com/it'\\''s'\n");
+    env.cmd()
+        .args(["env", "work", "--shell", "powershell"])
+        .assert()
+        .success()
+        .stdout("$env:ANTHROPIC_BASE_URL = 'https://gw.example.com/it''s'\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_env_output_can_be_evaluated() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.cmd()
+        .args(["save", "work", "--base-url", "https://gw.example.com"])
+        .assert()
+        .success();
+    let output = env.cmd().args(["env", "work"]).output().unwrap();
+
+    let shell = Command::new("sh")
+        .arg("-c")
+        .arg(format!(
+            "{}\necho \"$ANTHROPIC_BASE_URL\"",
+            String::from_utf8_lossy(&output.stdout)
+        ))
+        .output()
+        .unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&shell.stdout),
+        "https://gw.example.com\n"
+    );
+}
+
+#[test]
+fn test_env_without_settings_prints_nothing() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["env", "work"])
+        .assert()
+        .success()
+        .stdout("");
+    env.cmd().args(["env", "ghost"]).assert().code(4);
+}
+
+// =============================================================================
+// COMPACT JSON TESTS
+// =============================================================================
+
+#[test]
+fn test_json_output_is_pretty_unless_compact() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("work", &sample_account("work"));
+
+    for args in [
+        vec!["status", "--json"],
+        vec!["show", "work", "--json"],
+        vec!["show", "work"],
+    ] {
+        let pretty = env.cmd().args(&args).output().unwrap();
+        let pretty = String::from_utf8_lossy(&pretty.stdout).to_string();
+        assert!(
+            pretty.lines().count() > 1,
+            "{:?} not pretty: {}",
+            args,
+            pretty
+        );
+
+        let compact = env.cmd().args(&args).arg("--compact").output().unwrap();
+        let compact = String::from_utf8_lossy(&compact.stdout).to_string();
+        assert_eq!(
+            compact.lines().count(),
+            1,
+            "{:?} not compact: {}",
+            args,
+            compact
+        );
+
+        let pretty: serde_json::Value = serde_json::from_str(&pretty).unwrap();
+        let compact: serde_json::Value = serde_json::from_str(&compact).unwrap();
+        assert_eq!(pretty, compact);
+    }
+}
+
+// =============================================================================
+// LAST TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_last_prints_previous_profile_without_switching() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("personal", &sample_account("personal"));
+    for profile in ["work", "personal"] {
+        env.cmd_with_fake_claude().arg(profile).assert().success();
+    }
+
+    env.cmd().arg("last").assert().success().stdout("work\n");
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-personal"
+    );
+}
+
+#[test]
+fn test_last_without_previous_profile_fails_quietly() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .arg("last")
+        .assert()
+        .code(1)
+        .stdout("")
+        .stderr(predicate::str::contains("No previous profile recorded"));
+}
+
+// =============================================================================
+// CLAUDE_CONFIG_DIR TESTS
+// =============================================================================
+
+#[test]
+fn test_claude_config_dir_is_patched_on_switch() {
+    let env = TestEnv::new();
+    let config_dir = env.home_path().join("claude-dir");
+    fs::create_dir_all(&config_dir).expect("create config dir");
+    fs::write(config_dir.join(".claude.json"), "{}").expect("write config");
+    env.create_claude_config(&sample_account("default"));
+    env.create_profile("work", &sample_account("work"));
+
+    let _ = env
+        .cmd()
+        .env("CLAUDE_CONFIG_DIR", &config_dir)
+        .arg("work")
+        .assert();
+
+    let patched: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(config_dir.join(".claude.json")).expect("read"))
+            .expect("parse");
+    assert_eq!(patched["oauthAccount"]["accountUuid"], "uuid-work");
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-default"
+    );
+}
+
+#[test]
+fn test_claude_config_dir_falls_back_to_config_json() {
+    let env = TestEnv::new();
+    let config_dir = env.home_path().join("claude-dir");
+    fs::create_dir_all(&config_dir).expect("create config dir");
+    fs::write(
+        config_dir.join("config.json"),
+        serde_json::to_string_pretty(&json!({ "oauthAccount": sample_account("dir") }))
+            .expect("serialize"),
+    )
+    .expect("write config");
+
+    env.cmd()
+        .env("CLAUDE_CONFIG_DIR", &config_dir)
+        .args(["save", "dir"])
+        .assert()
+        .success();
+
+    assert_eq!(
+        env.read_profile("dir")["oauthAccount"]["accountUuid"],
+        "uuid-dir"
+    );
+}
+
+#[test]
+fn test_claude_config_path_wins_over_config_dir() {
+    let env = TestEnv::new();
+    let config_dir = env.home_path().join("claude-dir");
+    fs::create_dir_all(&config_dir).expect("create config dir");
+    fs::write(
+        config_dir.join(".claude.json"),
+        serde_json::to_string_pretty(&json!({ "oauthAccount": sample_account("dir") }))
+            .expect("serialize"),
+    )
+    .expect("write config");
+    let custom_path = env.home_path().join("custom-claude.json");
+    fs::write(
+        &custom_path,
+        serde_json::to_string_pretty(&json!({ "oauthAccount": sample_account("custom") }))
+            .expect("serialize"),
+    )
+    .expect("write custom config");
+
+    env.cmd()
+        .env("CLAUDE_CONFIG_DIR", &config_dir)
+        .env("CLAUDE_CONFIG_PATH", &custom_path)
+        .args(["save", "picked"])
+        .assert()
+        .success();
+
+    assert_eq!(
+        env.read_profile("picked")["oauthAccount"]["accountUuid"],
+        "uuid-custom"
+    );
+}
+
+// =============================================================================
+// SORT KEYS TESTS
+// =============================================================================
+
+impl TestEnv {
+    fn raw_profile(&self, name: &str) -> String {
+        fs::read_to_string(self.profile_path(name)).expect("Failed to read profile")
+    }
+}
+
+/// Whether `first` appears before `second` in `text`
+fn appears_before(text: &str, first: &str, second: &str) -> bool {
+    text.find(first).expect("first key") < text.find(second).expect("second key")
+}
+
+#[test]
+fn test_save_keeps_source_key_order_by_default() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+
+    env.cmd().args(["save", "work"]).assert().success();
+
+    let raw = env.raw_profile("work");
+    assert!(appears_before(&raw, "\"emailAddress\"", "\"displayName\""));
+}
+
+#[test]
+fn test_save_sort_keys_sorts_profile_keys() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+
+    env.cmd()
+        .args(["--sort-keys", "save", "work"])
+        .assert()
+        .success();
+
+    let raw = env.raw_profile("work");
+    assert!(appears_before(&raw, "\"displayName\"", "\"emailAddress\""));
+}
+
+#[test]
+fn test_sort_keys_setting_sorts_patched_config() {
+    let env = TestEnv::new();
+    fs::write(
+        env.claude_config_path(),
+        serde_json::to_string_pretty(&json!({
+            "zeta": 1,
+            "oauthAccount": sample_account("old"),
+            "alpha": 2
+        }))
+        .expect("serialize"),
+    )
+    .expect("write config");
+    env.create_profile("work", &sample_account("work"));
+    env.write_settings(&json!({ "sort_keys": true }));
+
+    let _ = env.cmd_with_fake_claude().arg("work").assert();
+
+    let raw = fs::read_to_string(env.claude_config_path()).expect("read config");
+    assert!(appears_before(&raw, "\"alpha\"", "\"oauthAccount\""));
+    assert!(appears_before(&raw, "\"oauthAccount\"", "\"zeta\""));
+}
+
+#[test]
+fn test_no_sort_keys_overrides_setting() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.write_settings(&json!({ "sort_keys": true }));
+
+    env.cmd()
+        .args(["--no-sort-keys", "save", "work"])
+        .assert()
+        .success();
+
+    let raw = env.raw_profile("work");
+    assert!(appears_before(&raw, "\"emailAddress\"", "\"displayName\""));
+}
+
+#[test]
+fn test_config_sort_keys_rejects_non_boolean() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["config", "sort_keys", "yes"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("expected true or false"));
+}
+
+// =============================================================================
+// FUZZY PICKER TESTS
+// =============================================================================
+
+#[test]
+fn test_config_picker_accepts_fuzzy() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["config", "picker", "fuzzy"])
+        .assert()
+        .success();
+
+    env.cmd()
+        .args(["config", "picker"])
+        .assert()
+        .success()
+        .stdout("fuzzy\n");
+}
+
+#[test]
+fn test_config_picker_rejects_unknown_style() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["config", "picker", "tree"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("expected list or fuzzy"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_plain_launch_with_fuzzy_picker_still_skips_prompt() {
+    let env = TestEnv::new();
+    env.create_profile("alpha", &sample_account("alpha"));
+    env.create_profile("work", &sample_account("work"));
+    env.create_claude_config(&sample_account("work"));
+    env.write_settings(&json!({ "picker": "fuzzy" }));
+
+    env.cmd_with_fake_claude()
+        .args(["--plain", "--fuzzy"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Select Claude profile: work"));
+}
+
+// =============================================================================
+// REFRESH TESTS
+// =============================================================================
+
+#[test]
+fn test_refresh_resyncs_account_fields_and_keeps_other_keys() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    let mut profile = env.read_profile("work");
+    profile["baseUrl"] = json!("https://gateway.example.com");
+    fs::write(
+        env.profile_path("work"),
+        serde_json::to_string_pretty(&profile).expect("serialize"),
+    )
+    .expect("write profile");
+    let mut account = sample_account("work");
+    account["displayName"] = json!("Renamed User");
+    env.create_claude_config(&account);
+
+    env.cmd()
+        .args(["refresh", "work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Refreshed profile 'work'"));
+
+    let refreshed = env.read_profile("work");
+    assert_eq!(refreshed["oauthAccount"]["displayName"], "Renamed User");
+    assert_eq!(refreshed["baseUrl"], "https://gateway.example.com");
+}
+
+#[test]
+fn test_refresh_refuses_other_account() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    env.create_claude_config(&sample_account("personal"));
+    let before = fs::read_to_string(env.profile_path("work")).expect("read profile");
+
+    env.cmd()
+        .args(["refresh", "work"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "is not the one saved in profile 'work'",
+        ));
+
+    assert_eq!(
+        fs::read_to_string(env.profile_path("work")).expect("read profile"),
+        before
+    );
+}
+
+#[test]
+fn test_refresh_missing_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+
+    env.cmd()
+        .args(["refresh", "ghost"])
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("ghost"));
+}
+
+// =============================================================================
+// ACCOUNT BANNER TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_banner_shows_active_account_before_launch() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("old"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd_with_fake_claude()
+        .args(["work", "--banner"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "Claude account: User work <user-work@example.com>",
+        ))
+        .stderr(predicate::str::contains("Organization:   Org work"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_banner_is_off_by_default() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("old"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd_with_fake_claude()
+        .arg("work")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Claude account:").not());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_no_banner_overrides_banner_setting() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("old"));
+    env.create_profile("work", &sample_account("work"));
+    env.write_settings(&json!({ "banner": true }));
+
+    env.cmd_with_fake_claude()
+        .arg("work")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Claude account: User work"));
+
+    env.cmd_with_fake_claude()
+        .args(["work", "--no-banner"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Claude account:").not());
+}
+
+// =============================================================================
+// CONFIG FLAG / DOCTOR TESTS
+// =============================================================================
+
+#[test]
+fn test_config_flag_wins_over_env_var() {
+    let env = TestEnv::new();
+    let env_path = env.home_path().join("env-claude.json");
+    fs::write(
+        &env_path,
+        serde_json::to_string_pretty(&json!({ "oauthAccount": sample_account("env") }))
+            .expect("serialize"),
+    )
+    .expect("write env config");
+    let flag_path = env.home_path().join("flag-claude.json");
+    fs::write(
+        &flag_path,
+        serde_json::to_string_pretty(&json!({ "oauthAccount": sample_account("flag") }))
+            .expect("serialize"),
+    )
+    .expect("write flag config");
+
+    env.cmd()
+        .env("CLAUDE_CONFIG_PATH", &env_path)
+        .arg("--config")
+        .arg(&flag_path)
+        .args(["save", "picked"])
+        .assert()
+        .success();
+
+    assert_eq!(
+        env.read_profile("picked")["oauthAccount"]["accountUuid"],
+        "uuid-flag"
+    );
+}
+
+#[test]
+fn test_doctor_lists_config_candidates_and_marks_active() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    let config_dir = env.home_path().join("claude-dir");
+
+    let output = env
+        .cmd()
+        .env("CLAUDE_CONFIG_DIR", &config_dir)
+        .arg("doctor")
+        .output()
+        .expect("run doctor");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    let dir_file = config_dir.join(".claude.json");
+    assert!(stdout.contains(&format!(
+        "* {} ($CLAUDE_CONFIG_DIR, missing)",
+        dir_file.display()
+    )));
+    assert!(stdout.contains(&format!(
+        "  {} (default)",
+        env.claude_config_path().display()
+    )));
+}
+
+#[test]
+fn test_doctor_marks_config_flag_active() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    let flag_path = env.home_path().join("flag-claude.json");
+    fs::write(&flag_path, "{}").expect("write flag config");
+
+    env.cmd()
+        .arg("--config")
+        .arg(&flag_path)
+        .arg("doctor")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "* {} (--config)",
+            flag_path.display()
+        )));
+}
+
+// =============================================================================
+// LOGIN DRY RUN TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_login_dry_run_restores_config_without_running_claude() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    let before = fs::read_to_string(env.claude_config_path()).expect("read config");
+
+    env.cmd_with_fake_claude()
+        .args(["login", "--dry-run", "--name", "work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Backed up existing config to"))
+        .stdout(predicate::str::contains("Dry run: would run"))
+        .stdout(predicate::str::contains(
+            "Dry run: would save the new account as 'work'",
+        ))
+        .stdout(predicate::str::contains("Restored original config."));
+
+    assert_eq!(
+        fs::read_to_string(env.claude_config_path()).expect("read config"),
+        before
+    );
+    assert!(!env.home_path().join("claude-args.txt").exists());
+    assert!(!env.profile_path("work").exists());
+    assert!(env.backup_files().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_login_dry_run_restores_from_backup_dir_on_other_filesystem() {
+    // /dev/shm is a tmpfs on Linux, so renaming from it into the home fails
+    let Ok(backup_dir) = tempfile::TempDir::new_in("/dev/shm") else {
+        return;
+    };
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    let before = fs::read_to_string(env.claude_config_path()).expect("read config");
+
+    env.cmd_with_fake_claude()
+        .arg("--backup-dir")
+        .arg(backup_dir.path())
+        .args(["login", "--dry-run", "--name", "work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Restored original config."));
+
+    assert_eq!(
+        fs::read_to_string(env.claude_config_path()).expect("read config"),
+        before
+    );
+    assert!(env.backup_files_in(backup_dir.path()).is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_login_dry_run_needs_no_name() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+
+    env.cmd_with_fake_claude()
+        .args(["login", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Dry run: would prompt for a profile name",
+        ));
+
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-current"
+    );
+}
+
+// =============================================================================
+// CP-CONFIG TESTS
+// =============================================================================
+
+#[test]
+fn test_cp_config_merges_profile_onto_portable_settings() {
+    let env = TestEnv::new();
+    fs::write(
+        env.claude_config_path(),
+        serde_json::to_string_pretty(&json!({
+            "oauthAccount": sample_account("current"),
+            "userID": "current-user",
+            "theme": "dark"
+        }))
+        .expect("serialize"),
+    )
+    .expect("write config");
+    let live_before = fs::read_to_string(env.claude_config_path()).expect("read config");
+    env.create_profile("work", &sample_account("work"));
+    let dest = env.home_path().join("container.json");
+
+    env.cmd()
+        .arg("cp-config")
+        .arg("work")
+        .arg(&dest)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Wrote config for profile 'work'"));
+
+    let written: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&dest).expect("read dest")).expect("parse");
+    assert_eq!(written["oauthAccount"]["accountUuid"], "uuid-work");
+    assert_eq!(written["userID"], "user-id-work");
+    assert_eq!(written["theme"], "dark");
+    assert_eq!(
+        fs::read_to_string(env.claude_config_path()).expect("read config"),
+        live_before
+    );
+}
+
+#[test]
+fn test_cp_config_without_live_config_uses_skeleton() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    let dest = env.home_path().join("container.json");
+
+    env.cmd()
+        .arg("cp-config")
+        .arg("work")
+        .arg(&dest)
+        .assert()
+        .success();
+
+    let written: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&dest).expect("read dest")).expect("parse");
+    assert_eq!(written["oauthAccount"]["accountUuid"], "uuid-work");
+    assert_eq!(written["hasCompletedOnboarding"], true);
+    assert!(!env.claude_config_path().exists());
+}
+
+#[test]
+fn test_cp_config_refuses_live_config_as_dest() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .arg("--yes")
+        .arg("cp-config")
+        .arg("work")
+        .arg(env.claude_config_path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is the live Claude config"));
+
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-current"
+    );
+}
+
+#[test]
+fn test_cp_config_missing_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+
+    env.cmd()
+        .arg("cp-config")
+        .arg("ghost")
+        .arg(env.home_path().join("out.json"))
+        .assert()
+        .code(4);
+}
+
+// =============================================================================
+// NO-PORTABLE SWITCH TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_no_portable_writes_only_account_fields() {
+    let env = TestEnv::new();
+    fs::write(
+        env.claude_config_path(),
+        serde_json::to_string_pretty(&json!({
+            "oauthAccount": sample_account("current"),
+            "theme": "dark",
+            "mcpServers": { "local": {} }
+        }))
+        .expect("serialize"),
+    )
+    .expect("write config");
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd_with_fake_claude()
+        .args(["work", "--no-portable"])
+        .assert()
+        .success();
+
+    let config = env.read_claude_config();
+    let mut keys: Vec<&String> = config.as_object().expect("object").keys().collect();
+    keys.sort();
+    assert_eq!(keys, ["oauthAccount", "userID"]);
+    assert_eq!(config["oauthAccount"]["accountUuid"], "uuid-work");
+
+    let backups = env.backup_files();
+    assert_eq!(backups.len(), 1);
+    let backup: serde_json::Value = serde_json::from_str(
+        &fs::read_to_string(env.backups_dir().join(&backups[0])).expect("read backup"),
+    )
+    .expect("parse backup");
+    assert_eq!(backup["theme"], "dark");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_backups_in_the_same_second_are_all_kept() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("home", &sample_account("home"));
+
+    for name in ["work", "home", "work"] {
+        env.cmd_with_fake_claude()
+            .args([name, "--no-portable"])
+            .assert()
+            .success();
+    }
+
+    let backups = env.backup_files();
+    assert_eq!(backups.len(), 3);
+    let uuids: Vec<String> = backups
+        .iter()
+        .map(|name| {
+            let backup: serde_json::Value = serde_json::from_str(
+                &fs::read_to_string(env.backups_dir().join(name)).expect("read backup"),
+            )
+            .expect("parse backup");
+            backup["oauthAccount"]["accountUuid"]
+                .as_str()
+                .expect("uuid")
+                .to_string()
+        })
+        .collect();
+    for uuid in ["uuid-current", "uuid-work", "uuid-home"] {
+        assert!(uuids.iter().any(|u| u == uuid), "no backup of {}", uuid);
+    }
+}
+
+#[test]
+fn test_no_portable_conflicts_with_merge() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["work", "--no-portable", "--merge"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+// =============================================================================
+// READ-ONLY PROFILES DIRECTORY TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_save_into_read_only_profiles_dir_is_reported() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    fs::create_dir_all(env.claudectx_dir()).expect("create claudectx dir");
+    fs::set_permissions(env.claudectx_dir(), fs::Permissions::from_mode(0o555))
+        .expect("make read-only");
+
+    // Permissions don't apply to root; nothing to check there
+    let writable = fs::write(env.claudectx_dir().join(".probe"), "").is_ok();
+    if !writable {
+        env.cmd()
+            .args(["save", "work"])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(
+                "Profiles directory is not writable",
+            ));
+    }
+
+    fs::set_permissions(env.claudectx_dir(), fs::Permissions::from_mode(0o755))
+        .expect("restore permissions");
+}
+
+#[test]
+fn test_save_with_file_in_place_of_profiles_dir_is_reported() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    // A regular file where ~/.claudectx should be fails for root too
+    fs::write(env.claudectx_dir(), "").expect("write file");
+
+    env.cmd()
+        .args(["save", "work"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(format!(
+            "Profiles directory is not writable: {}",
+            env.claudectx_dir().display()
+        )));
+}
+
+#[test]
+fn test_config_set_with_file_in_place_of_claudectx_dir_is_reported() {
+    let env = TestEnv::new();
+    fs::write(env.claudectx_dir(), "").expect("write file");
+
+    env.cmd()
+        .args(["config", "sort", "recent"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains(format!(
+            "Profiles directory is not writable: {}",
+            env.claudectx_dir().display()
+        )));
+}
+
+// =============================================================================
+// ALIAS TESTS
+// =============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_alias_launches_its_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["alias", "w", "work"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Alias 'w' now resolves to 'work'"));
+
+    env.cmd_with_fake_claude().arg("w").assert().success();
+
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+    assert!(!env.profile_path("w").exists());
+}
+
+#[test]
+fn test_alias_list_and_remove() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("personal", &sample_account("personal"));
+
+    env.cmd().args(["alias", "w", "work"]).assert().success();
+    env.cmd()
+        .args(["alias", "p", "personal"])
+        .assert()
+        .success();
+
+    env.cmd()
+        .args(["alias", "--list"])
+        .assert()
+        .success()
+        .stdout("p -> personal\nw -> work\n");
+
+    env.cmd()
+        .args(["alias", "--remove", "w"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removed alias 'w' (was 'work')"));
+
+    env.cmd()
+        .arg("alias")
+        .assert()
+        .success()
+        .stdout("p -> personal\n");
+}
+
+#[test]
+fn test_alias_requires_existing_profile() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["alias", "w", "ghost"])
+        .assert()
+        .code(4)
+        .stderr(predicate::str::contains("Profile 'ghost' not found"));
+}
+
+#[test]
+fn test_alias_cannot_shadow_profile() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("personal", &sample_account("personal"));
+
+    env.cmd()
+        .args(["alias", "personal", "work"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already a profile name"));
+}
+
+#[test]
+fn test_alias_remove_unknown() {
+    let env = TestEnv::new();
+
+    env.cmd()
+        .args(["alias", "--remove", "nope"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No alias named 'nope'"));
+}
+
+#[test]
+fn test_rename_retargets_and_delete_removes_aliases() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("personal", &sample_account("personal"));
+    env.cmd().args(["alias", "w", "work"]).assert().success();
+    env.cmd()
+        .args(["alias", "p", "personal"])
+        .assert()
+        .success();
+
+    env.cmd().args(["rename", "work", "job"]).assert().success();
+    env.cmd()
+        .arg("alias")
+        .assert()
+        .success()
+        .stdout("p -> personal\nw -> job\n");
+
+    env.cmd().args(["delete", "personal"]).assert().success();
+    env.cmd()
+        .arg("alias")
+        .assert()
+        .success()
+        .stdout("w -> job\n");
+}
+
+#[test]
+fn test_save_rejects_alias_name() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    env.cmd().args(["alias", "w", "work"]).assert().success();
+
+    env.cmd()
+        .args(["save", "w"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("'w' is an alias of 'work'"));
+    assert!(!env.profile_path("w").exists());
+
+    env.cmd()
+        .args(["rename", "work", "w"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("'w' is an alias of 'work'"));
+    assert!(env.profile_path("work").exists());
+}
+
+#[test]
+fn test_import_and_undelete_reject_alias_name() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    env.create_profile("w", &sample_account("w"));
+    env.cmd()
+        .args(["delete", "w", "--trash"])
+        .assert()
+        .success();
+    env.cmd().args(["alias", "w", "work"]).assert().success();
+
+    let file = env.home_path().join("other.json");
+    fs::write(
+        &file,
+        serde_json::to_string(&json!({ "oauthAccount": sample_account("other") })).unwrap(),
+    )
+    .expect("write import file");
+    env.cmd()
+        .arg("import")
+        .arg(&file)
+        .args(["--name", "w"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("'w' is an alias of 'work'"));
+
+    env.cmd()
+        .args(["undelete", "w"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("'w' is an alias of 'work'"));
+    assert!(!env.profile_path("w").exists());
+    assert_eq!(env.trash_files().len(), 1);
+
+    let bundle = env.home_path().join("bundle");
+    fs::create_dir_all(&bundle).expect("mkdir");
+    fs::write(
+        bundle.join("w.claude.json"),
+        serde_json::to_string(&json!({ "oauthAccount": sample_account("w") })).unwrap(),
+    )
+    .expect("write");
+    let archive = env.home_path().join("profiles.tar.gz");
+    make_archive(&bundle, &archive);
+    env.cmd()
+        .arg("import-all")
+        .arg(&archive)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Skipped 'w': name is an alias of 'work'",
+        ));
+    assert!(!env.profile_path("w").exists());
+}
+
+#[test]
+fn test_alias_alone_prints_its_profile() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    env.cmd().args(["alias", "w", "work"]).assert().success();
+
+    env.cmd()
+        .args(["alias", "w"])
+        .assert()
+        .success()
+        .stdout("w -> work\n");
+}
+
+// =============================================================================
+// PRINT RESULTING CONFIG TESTS
+// =============================================================================
+
+#[test]
+fn test_print_resulting_config_shows_patch_without_writing() {
+    let env = TestEnv::new();
+    fs::write(
+        env.claude_config_path(),
+        serde_json::to_string_pretty(&json!({
+            "oauthAccount": sample_account("current"),
+            "theme": "dark"
+        }))
+        .expect("serialize"),
+    )
+    .expect("write config");
+    let before = fs::read_to_string(env.claude_config_path()).expect("read config");
+    env.create_profile("work", &sample_account("work"));
+
+    let output = env
+        .cmd()
+        .args(["work", "--print-resulting-config"])
+        .output()
+        .expect("run claudectx");
+    assert!(output.status.success());
+
+    let printed: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is JSON");
+    assert_eq!(printed["oauthAccount"]["accountUuid"], "uuid-work");
+    assert_eq!(printed["userID"], "user-id-work");
+    assert_eq!(printed["theme"], "dark");
+
+    assert_eq!(
+        fs::read_to_string(env.claude_config_path()).expect("read config"),
+        before
+    );
+    assert!(!env.usage_path().exists());
+    assert!(!env.home_path().join("claude-args.txt").exists());
+}
+
+#[test]
+fn test_print_resulting_config_follows_no_portable() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args([
+            "work",
+            "--print-resulting-config",
+            "--no-portable",
+            "--compact",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("hasCompletedOnboarding").not())
+        .stdout(predicate::str::contains("\"accountUuid\":\"uuid-work\""));
+    assert!(env.backup_files().is_empty());
+}
+
+#[test]
+fn test_print_resulting_config_missing_profile() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+
+    env.cmd()
+        .args(["ghost", "--print-resulting-config"])
+        .assert()
+        .code(4);
+    assert!(!env.profile_path("ghost").exists());
+}
+
+// =============================================================================
+// WATCH TESTS (--features watch)
+// =============================================================================
+
+#[cfg(all(unix, feature = "watch"))]
+#[test]
+fn test_watch_reapplies_profile_after_drift_and_stops_on_ctrl_c() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+    use std::time::{Duration, Instant};
+
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("work", &sample_account("work"));
+
+    let mut child = Command::cargo_bin("claudectx")
+        .expect("Failed to find binary")
+        .env("CLAUDECTX_HOME", env.home_path())
+        .env_remove("CLAUDE_CONFIG_DIR")
+        .args(["watch", "work"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("spawn watch");
+    let mut stdout = BufReader::new(child.stdout.take().expect("stdout"));
+    let mut line = String::new();
+    while !line.starts_with("Watching") {
+        line.clear();
+        stdout.read_line(&mut line).expect("read watch output");
+    }
+
+    // Another tool logs in with a different account, keeping a portable setting
+    fs::write(
+        env.claude_config_path(),
+        serde_json::to_string_pretty(&json!({
+            "oauthAccount": sample_account("other"),
+            "theme": "dark"
+        }))
+        .expect("serialize"),
+    )
+    .expect("rewrite config");
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while env.read_claude_config()["oauthAccount"]["accountUuid"] != "uuid-work" {
+        assert!(Instant::now() < deadline, "profile was not re-applied");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(env.read_claude_config()["theme"], "dark");
+
+    Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .expect("send SIGINT");
+    let status = child.wait().expect("wait for watch");
+    assert!(status.success());
+
+    let mut rest = String::new();
+    std::io::Read::read_to_string(&mut stdout, &mut rest).expect("read rest");
+    assert!(rest.contains("Stopped watching."));
+}
+
+// =============================================================================
+// EXPORT/IMPORT --FULL TESTS
+// =============================================================================
+
+impl TestEnv {
+    fn write_claude_config(&self, config: &serde_json::Value) {
+        fs::write(
+            self.claude_config_path(),
+            serde_json::to_string_pretty(config).expect("serialize"),
+        )
+        .expect("Failed to write claude config");
+    }
+}
+
+#[test]
+fn test_export_full_bundles_portable_settings() {
+    let env = TestEnv::new();
+    env.write_claude_config(&json!({
+        "oauthAccount": sample_account("current"),
+        "userID": "current-user",
+        "primaryApiKey": "sk-ant-secret-1234",
+        "theme": "dark"
+    }));
+    env.create_profile("work", &sample_account("work"));
+
+    let output = env
+        .cmd()
+        .args(["export", "work", "--full"])
+        .output()
+        .expect("run export");
+    assert!(output.status.success());
+    let bundle: serde_json::Value = serde_json::from_slice(&output.stdout).expect("JSON");
+
+    assert_eq!(
+        bundle["account"]["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+    assert_eq!(bundle["portable"]["theme"], "dark");
+    assert!(bundle["portable"].get("oauthAccount").is_none());
+    assert!(bundle["portable"].get("userID").is_none());
+    assert_ne!(bundle["portable"]["primaryApiKey"], "sk-ant-secret-1234");
+}
+
+#[test]
+fn test_import_full_restores_portable_settings_and_keeps_live_secrets() {
+    let env = TestEnv::new();
+    env.write_claude_config(&json!({
+        "oauthAccount": sample_account("current"),
+        "primaryApiKey": "sk-ant-secret-1234",
+        "theme": "dark"
+    }));
+    env.create_profile("work", &sample_account("work"));
+    let file = env.home_path().join("work.json");
+    env.cmd()
+        .args(["export", "work", "--full", "-o"])
+        .arg(&file)
+        .assert()
+        .success();
+
+    // Settings change after the export; the account stays logged in
+    env.write_claude_config(&json!({
+        "oauthAccount": sample_account("current"),
+        "primaryApiKey": "sk-ant-secret-1234",
+        "theme": "light",
+        "newSetting": true
+    }));
+
+    env.cmd()
+        .args(["--yes", "import", "--full", "--name", "restored"])
+        .arg(&file)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Imported 'restored'"))
+        .stdout(predicate::str::contains("Restored portable settings."));
+
+    assert_eq!(
+        env.read_profile("restored")["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+    let config = env.read_claude_config();
+    assert_eq!(config["theme"], "dark");
+    assert!(config.get("newSetting").is_none());
+    assert_eq!(config["primaryApiKey"], "sk-ant-secret-1234");
+    assert_eq!(config["oauthAccount"]["accountUuid"], "uuid-current");
+    assert_eq!(env.backup_files().len(), 1);
+}
+
+#[test]
+fn test_import_full_rejects_plain_export() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("current"));
+    env.create_profile("work", &sample_account("work"));
+    let file = env.home_path().join("work.json");
+    env.cmd()
+        .args(["export", "work", "-o"])
+        .arg(&file)
+        .assert()
+        .success();
+
+    env.cmd()
+        .args(["--yes", "import", "--full", "--name", "again"])
+        .arg(&file)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not an 'export --full' file"));
+    assert!(!env.profile_path("again").exists());
+}
+
+// =============================================================================
+// PROFILE CONFIG DIRECTORY TESTS
+// =============================================================================
+
+/// A config directory snapshot holding a settings file, a nested file and
+/// its own .claude.json
+fn create_config_dir_snapshot(env: &TestEnv) -> std::path::PathBuf {
+    let snapshot = env.home_path().join("work-dir");
+    fs::create_dir_all(snapshot.join("commands")).expect("create snapshot");
+    fs::write(snapshot.join("settings.json"), r#"{"model":"work"}"#).expect("write settings");
+    fs::write(snapshot.join("commands").join("deploy.md"), "deploy").expect("write command");
+    fs::write(snapshot.join(".claude.json"), "{}").expect("write snapshot config");
+    snapshot
+}
+
+#[test]
+fn test_save_config_dir_stores_absolute_path() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    let snapshot = create_config_dir_snapshot(&env);
+
+    env.cmd()
+        .current_dir(env.home_path())
+        .args(["save", "work", "--config-dir", "work-dir"])
+        .assert()
+        .success();
+
+    assert_eq!(
+        env.read_profile("work")["configDir"],
+        snapshot
+            .canonicalize()
+            .expect("canonicalize")
+            .to_string_lossy()
+            .as_ref()
+    );
+}
+
+#[test]
+fn test_save_config_dir_must_exist() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+
+    env.cmd()
+        .args(["save", "work", "--config-dir", "/nonexistent/claude-dir"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("does not exist"));
+    assert!(!env.profile_path("work").exists());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_switch_syncs_config_dir_when_enabled() {
+    let env = TestEnv::new();
+    let snapshot = create_config_dir_snapshot(&env);
+    let config_dir = env.home_path().join("claude-dir");
+    fs::create_dir_all(&config_dir).expect("create config dir");
+    fs::write(config_dir.join("settings.json"), r#"{"model":"old"}"#).expect("write settings");
+    fs::write(config_dir.join("local.json"), "{}").expect("write local file");
+    fs::write(
+        config_dir.join(".claude.json"),
+        serde_json::to_string_pretty(
+            &json!({ "theme": "dark", "oauthAccount": sample_account("default") }),
+        )
+        .expect("serialize"),
+    )
+    .expect("write config");
+    env.write_settings(&json!({ "config_dirs": true }));
+    create_config_dir_profile(&env, "work", &snapshot);
+
+    env.cmd_with_fake_claude()
+        .env("CLAUDE_CONFIG_DIR", &config_dir)
+        .arg("work")
+        .assert()
+        .success();
+
+    assert_eq!(
+        fs::read_to_string(config_dir.join("settings.json")).expect("read"),
+        r#"{"model":"work"}"#
+    );
+    assert!(config_dir.join("commands").join("deploy.md").exists());
+    assert!(config_dir.join("local.json").exists());
+    // The snapshot's .claude.json is skipped; only the account is patched
+    let patched: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(config_dir.join(".claude.json")).expect("read"))
+            .expect("parse");
+    assert_eq!(patched["theme"], "dark");
+    assert_eq!(patched["oauthAccount"]["accountUuid"], "uuid-work");
+    assert!(patched.get("configDir").is_none());
+
+    let backups = env.backup_files();
+    assert_eq!(backups.len(), 1);
+    assert!(backups[0].starts_with("claude-config-dir-"));
+    assert_eq!(
+        fs::read_to_string(env.backups_dir().join(&backups[0]).join("settings.json"))
+            .expect("read backup"),
+        r#"{"model":"old"}"#
+    );
+}
+
+/// A profile named `name` referencing `snapshot` as its config directory
+fn create_config_dir_profile(env: &TestEnv, name: &str, snapshot: &Path) {
+    let mut profile = json!({ "oauthAccount": sample_account(name) });
+    profile["configDir"] = json!(snapshot.to_string_lossy());
+    fs::create_dir_all(env.claudectx_dir()).expect("create claudectx dir");
+    fs::write(env.profile_path(name), profile.to_string()).expect("write profile");
+}
+
+#[test]
+#[cfg(unix)]
+fn test_switch_keeps_only_latest_config_dir_backup() {
+    let env = TestEnv::new();
+    let snapshot = create_config_dir_snapshot(&env);
+    let config_dir = env.home_path().join("claude-dir");
+    fs::create_dir_all(&config_dir).expect("create config dir");
+    fs::write(config_dir.join(".claude.json"), "{}").expect("write config");
+    env.write_settings(&json!({ "config_dirs": true }));
+    create_config_dir_profile(&env, "work", &snapshot);
+    // An older directory backup from a previous switch
+    fs::create_dir_all(env.backups_dir().join("claude-config-dir-1.bak")).expect("mkdir");
+
+    env.cmd_with_fake_claude()
+        .env("CLAUDE_CONFIG_DIR", &config_dir)
+        .arg("work")
+        .assert()
+        .success();
+
+    let backups = env.backup_files();
+    assert_eq!(backups.len(), 1);
+    assert_ne!(backups[0], "claude-config-dir-1.bak");
+}
+
+#[test]
+#[cfg(unix)]
+fn test_failed_switch_leaves_config_dir_untouched() {
+    let env = TestEnv::new();
+    let snapshot = create_config_dir_snapshot(&env);
+    let config_dir = env.home_path().join("claude-dir");
+    fs::create_dir_all(&config_dir).expect("create config dir");
+    let real = env.home_path().join("real-claude.json");
+    fs::write(&real, "{}").expect("write config");
+    std::os::unix::fs::symlink(&real, config_dir.join(".claude.json")).expect("symlink");
+    env.write_settings(&json!({ "config_dirs": true }));
+    create_config_dir_profile(&env, "work", &snapshot);
+
+    env.cmd_with_fake_claude()
+        .env("CLAUDE_CONFIG_DIR", &config_dir)
+        .args(["--no-migrate", "work"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is a symlink"));
+
+    assert!(!config_dir.join("settings.json").exists());
+    assert!(env.backup_files().is_empty());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_switch_ignores_config_dir_when_disabled() {
+    let env = TestEnv::new();
+    let snapshot = create_config_dir_snapshot(&env);
+    let config_dir = env.home_path().join("claude-dir");
+    fs::create_dir_all(&config_dir).expect("create config dir");
+    fs::write(config_dir.join(".claude.json"), "{}").expect("write config");
+    create_config_dir_profile(&env, "work", &snapshot);
+
+    env.cmd_with_fake_claude()
+        .env("CLAUDE_CONFIG_DIR", &config_dir)
+        .arg("work")
+        .assert()
+        .success();
+
+    assert!(!config_dir.join("settings.json").exists());
+    assert!(env.backup_files().is_empty());
+}
+
+#[test]
+fn test_switch_config_dir_requires_claude_config_dir() {
+    let env = TestEnv::new();
+    let snapshot = create_config_dir_snapshot(&env);
+    env.create_claude_config(&sample_account("default"));
+    env.write_settings(&json!({ "config_dirs": true }));
+    create_config_dir_profile(&env, "work", &snapshot);
+
+    env.cmd()
+        .arg("work")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("$CLAUDE_CONFIG_DIR is not set"));
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-default"
+    );
+}
+
+// =============================================================================
+// DOCTOR FIX TESTS
+// =============================================================================
+
+#[test]
+fn test_doctor_reports_stale_markers_without_fixing() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("work", &sample_account("work"));
+    fs::write(env.claudectx_dir().join(".default"), "gone\n").expect("write default");
+
+    env.cmd()
+        .arg("doctor")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Problems (run 'claudectx doctor --fix' to repair):",
+        ))
+        .stdout(predicate::str::contains("points to missing profile 'gone'"));
+    assert!(env.claudectx_dir().join(".default").exists());
+}
+
+#[test]
+fn test_doctor_reports_no_problems() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .args(["doctor", "--fix"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No problems found."));
+}
+
+#[test]
+fn test_doctor_fix_clears_stale_markers() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("work", &sample_account("work"));
+    fs::write(env.claudectx_dir().join(".current"), "gone\n").expect("write current");
+    fs::write(env.claudectx_dir().join(".default"), "work\n").expect("write default");
+
+    env.cmd()
+        .args(["--yes", "doctor", "--fix"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Fixed: Clear"))
+        .stdout(predicate::str::contains("1 of 1 problem(s) fixed."));
+    assert!(!env.claudectx_dir().join(".current").exists());
+    assert!(env.claudectx_dir().join(".default").exists());
+}
+
+#[test]
+fn test_doctor_fix_recreates_claudectx_dir() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    assert!(!env.claudectx_dir().exists());
+
+    env.cmd()
+        .args(["--yes", "doctor", "--fix"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Fixed: Create missing directory"));
+    assert!(env.claudectx_dir().is_dir());
+}
+
+#[test]
+fn test_doctor_fix_requires_confirmation() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("work", &sample_account("work"));
+    fs::write(env.claudectx_dir().join(".current"), "gone\n").expect("write current");
+
+    env.cmd()
+        .args(["doctor", "--fix"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("requires confirmation"));
+    assert!(env.claudectx_dir().join(".current").exists());
+
+    env.cmd()
+        .args(["--plain", "doctor", "--fix"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Skipped: Clear"))
+        .stdout(predicate::str::contains("0 of 1 problem(s) fixed."));
+    assert!(env.claudectx_dir().join(".current").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_doctor_fix_removes_dangling_symlinks() {
+    let env = TestEnv::new();
+    env.create_profile("work", &sample_account("work"));
+    let missing = env.home_path().join("missing.json");
+    std::os::unix::fs::symlink(&missing, env.claude_config_path()).expect("symlink config");
+    std::os::unix::fs::symlink(&missing, env.profile_path("old")).expect("symlink profile");
+
+    env.cmd()
+        .args(["--yes", "doctor", "--fix"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2 of 2 problem(s) fixed."));
+    assert!(!env.claude_config_path().is_symlink());
+    assert!(!env.profile_path("old").is_symlink());
+    assert!(env.profile_path("work").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_doctor_fix_runs_pending_migration() {
+    let env = TestEnv::new();
+    let target = env.home_path().join("real-claude.json");
+    fs::write(
+        &target,
+        serde_json::to_string_pretty(&json!({ "oauthAccount": sample_account("work") }))
+            .expect("serialize"),
+    )
+    .expect("write config");
+    std::os::unix::fs::symlink(&target, env.claude_config_path()).expect("symlink config");
+    env.create_profile("work", &sample_account("work"));
+
+    env.cmd()
+        .arg("doctor")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Run the pending migration"));
+    assert!(env.claude_config_path().is_symlink());
+
+    env.cmd()
+        .args(["--yes", "doctor", "--fix"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Fixed: Run the pending migration"));
+    assert!(!env.claude_config_path().is_symlink());
+    assert_eq!(
+        env.read_claude_config()["oauthAccount"]["accountUuid"],
+        "uuid-work"
+    );
+}
+
+#[test]
+fn test_doctor_reports_invalid_profile_and_keeps_checking() {
+    let env = TestEnv::new();
+    env.create_claude_config(&sample_account("work"));
+    env.create_profile("work", &sample_account("work"));
+    fs::write(env.profile_path("broken"), "{").expect("write broken profile");
+    fs::write(env.claudectx_dir().join(".current"), "gone\n").expect("write current");
+
+    env.cmd()
+        .arg("doctor")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("points to missing profile 'gone'"))
+        .stdout(predicate::str::contains(
+            "- Profile 'broken' is not valid JSON",
+        ));
+
+    env.cmd()
+        .args(["--yes", "doctor", "--fix"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 of 1 problem(s) fixed."))
+        .stdout(predicate::str::contains(
+            "- Profile 'broken' is not valid JSON",
+        ));
+    assert!(!env.claudectx_dir().join(".current").exists());
+    assert_eq!(
+        fs::read_to_string(env.profile_path("broken")).expect("read"),
+        "{"
+    );
+}